@@ -0,0 +1,311 @@
+use crate::{
+    errors::{BitcoinError, SigningError, StoreError},
+    policy::estimated_signed_weight,
+    signer::{SessionId, SigningRequest},
+};
+use bitcoin::{Address, Network, ScriptBuf, Txid};
+use frost_secp256k1_tr::Identifier;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+/// Output of a transaction waiting for approval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSummary {
+    pub script_pubkey: ScriptBuf,
+    pub amount: u64,
+    /// Output pays back to the group.
+    pub change: bool,
+}
+
+/// Human readable summary of a signing request shown to the operator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionSummary {
+    pub txid: Txid,
+    pub outputs: Vec<OutputSummary>,
+    pub input_amount: u64,
+    pub fee: u64,
+    /// Fee rate in sat/vB, estimated for the signed transaction.
+    pub fee_rate: f64,
+}
+
+impl TransactionSummary {
+    pub fn new(request: &SigningRequest) -> Result<Self, SigningError> {
+        let transaction = &request.transaction;
+        let group_script = request.prev_tx_outs.first().map(|output| &output.script_pubkey);
+        let outputs: Vec<OutputSummary> = transaction
+            .output
+            .iter()
+            .map(|output| OutputSummary {
+                script_pubkey: output.script_pubkey.clone(),
+                amount: output.value.to_sat(),
+                change: Some(&output.script_pubkey) == group_script,
+            })
+            .collect();
+
+        let input_amount: u64 = request.prev_tx_outs.iter().map(|output| output.value.to_sat()).sum();
+        let output_amount: u64 = outputs.iter().map(|output| output.amount).sum();
+        let fee = input_amount.checked_sub(output_amount).ok_or_else(|| {
+            BitcoinError::Spend(format!("outputs ({output_amount} sat) exceed the spent amount ({input_amount} sat)"))
+        })?;
+        let fee_rate = fee as f64 * 4.0 / estimated_signed_weight(transaction) as f64;
+
+        Ok(Self { txid: transaction.compute_txid(), outputs, input_amount, fee, fee_rate })
+    }
+
+    /// Amount paid out of the group.
+    pub fn amount(&self) -> u64 {
+        self.outputs.iter().filter(|output| !output.change).map(|output| output.amount).sum()
+    }
+
+    /// Amount returned to the group as change.
+    pub fn change(&self) -> u64 {
+        self.outputs.iter().filter(|output| output.change).map(|output| output.amount).sum()
+    }
+
+    /// Renders the summary with the output addresses of the given network.
+    pub fn render(&self, network: Network) -> String {
+        let mut summary = format!("Transaction {}\n", self.txid);
+        for output in &self.outputs {
+            let destination = match Address::from_script(&output.script_pubkey, network) {
+                Ok(address) => address.to_string(),
+                Err(_) => format!("script {}", output.script_pubkey.to_hex_string()),
+            };
+            let change = if output.change { " (change)" } else { "" };
+            let _ = writeln!(summary, "  {destination}: {} sat{change}", output.amount);
+        }
+        let _ = writeln!(summary, "  Spent: {} sat", self.input_amount);
+        let _ = writeln!(summary, "  Paid out: {} sat", self.amount());
+        let _ = writeln!(summary, "  Change: {} sat", self.change());
+        let _ = write!(summary, "  Fee: {} sat ({:.2} sat/vB)", self.fee, self.fee_rate);
+        summary
+    }
+}
+
+/// Signing request waiting for an operator decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingRequest {
+    pub session_id: SessionId,
+    /// Signer asking for the decision, every participant of a session is approved on its own.
+    pub participant: Identifier,
+    /// Index of the participant, as `signer approve --participant` takes it.
+    #[serde(default)]
+    pub index: Option<u16>,
+    pub summary: TransactionSummary,
+    /// The request is rejected when no decision was made by then.
+    pub deadline: SystemTime,
+}
+
+/// Operator decision on a signing request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApprovalDecision {
+    Approved,
+    Rejected { reason: String },
+}
+
+/// Decision as stored, bound to the transaction it was made on.
+#[derive(Serialize, Deserialize)]
+struct DecisionRecord {
+    txid: Txid,
+    decision: ApprovalDecision,
+}
+
+/// Queue of signing requests waiting for an operator decision.
+pub trait ApprovalQueue: Send + Sync {
+    /// Adds a request to the queue, submitting the same session and participant again replaces it. An existing
+    /// decision is kept only if the request is for the same transaction.
+    fn submit(&self, request: &PendingRequest) -> Result<(), StoreError>;
+
+    /// Requests without a decision whose deadline has not passed.
+    fn pending(&self) -> Result<Vec<PendingRequest>, StoreError>;
+
+    /// Records the decision on a pending request of the participant, fails once the request has expired.
+    fn decide(
+        &self,
+        session_id: SessionId,
+        participant: Identifier,
+        decision: ApprovalDecision,
+    ) -> Result<(), StoreError>;
+
+    /// Decision on a participant's request, if one was made.
+    fn decision(&self, session_id: SessionId, participant: Identifier) -> Result<Option<ApprovalDecision>, StoreError>;
+}
+
+/// File based queue shared between the signer and the operator's CLI, one JSON file per request and decision.
+pub struct FileApprovalQueue {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileApprovalQueue {
+    /// Opens a queue in the given directory, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| StoreError::Io(e.to_string()))?;
+        Ok(Self { dir, lock: Mutex::new(()) })
+    }
+
+    fn request_path(&self, session_id: SessionId, participant: Identifier) -> PathBuf {
+        self.dir.join(format!("{session_id}-{}.request.json", hex::encode(participant.serialize())))
+    }
+
+    fn decision_path(&self, session_id: SessionId, participant: Identifier) -> PathBuf {
+        self.dir.join(format!("{session_id}-{}.decision.json", hex::encode(participant.serialize())))
+    }
+
+    fn read_request(
+        &self,
+        session_id: SessionId,
+        participant: Identifier,
+    ) -> Result<Option<PendingRequest>, StoreError> {
+        match fs::read_to_string(self.request_path(session_id, participant)) {
+            Ok(contents) => serde_json::from_str(&contents).map(Some).map_err(|e| StoreError::Corrupt(e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StoreError::Io(e.to_string())),
+        }
+    }
+
+    /// Decision on the current request, a decision made on another transaction doesn't count.
+    fn read_decision(
+        &self,
+        session_id: SessionId,
+        participant: Identifier,
+    ) -> Result<Option<ApprovalDecision>, StoreError> {
+        let record: DecisionRecord = match fs::read_to_string(self.decision_path(session_id, participant)) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| StoreError::Corrupt(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(StoreError::Io(e.to_string())),
+        };
+        let request = self.read_request(session_id, participant)?;
+        Ok(request.filter(|request| request.summary.txid == record.txid).map(|_| record.decision))
+    }
+
+    fn read_requests(&self) -> Result<Vec<PendingRequest>, StoreError> {
+        let mut requests = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(|e| StoreError::Io(e.to_string()))? {
+            let path = entry.map_err(|e| StoreError::Io(e.to_string()))?.path();
+            if path.to_str().is_some_and(|path| path.ends_with(".request.json")) {
+                let contents = fs::read_to_string(&path).map_err(|e| StoreError::Io(e.to_string()))?;
+                let request: PendingRequest =
+                    serde_json::from_str(&contents).map_err(|e| StoreError::Corrupt(e.to_string()))?;
+                requests.push(request);
+            }
+        }
+        requests.sort_by_key(|request| request.deadline);
+        Ok(requests)
+    }
+}
+
+impl ApprovalQueue for FileApprovalQueue {
+    fn submit(&self, request: &PendingRequest) -> Result<(), StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let (session_id, participant) = (request.session_id, request.participant);
+        if self
+            .read_request(session_id, participant)?
+            .is_some_and(|existing| existing.summary.txid != request.summary.txid)
+        {
+            match fs::remove_file(self.decision_path(session_id, participant)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(StoreError::Io(e.to_string())),
+                _ => {}
+            }
+        }
+        let contents = serde_json::to_vec_pretty(request).map_err(|e| StoreError::Corrupt(e.to_string()))?;
+        fs::write(self.request_path(session_id, participant), contents).map_err(|e| StoreError::Io(e.to_string()))
+    }
+
+    fn pending(&self) -> Result<Vec<PendingRequest>, StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let now = SystemTime::now();
+        let mut pending = Vec::new();
+        for request in self.read_requests()? {
+            if request.deadline > now && self.read_decision(request.session_id, request.participant)?.is_none() {
+                pending.push(request);
+            }
+        }
+        Ok(pending)
+    }
+
+    fn decide(
+        &self,
+        session_id: SessionId,
+        participant: Identifier,
+        decision: ApprovalDecision,
+    ) -> Result<(), StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let request = match self.read_request(session_id, participant)? {
+            Some(request) if self.read_decision(session_id, participant)?.is_none() => request,
+            _ => return Err(StoreError::UnknownSession(session_id)),
+        };
+        if request.deadline <= SystemTime::now() {
+            return Err(StoreError::Expired(session_id));
+        }
+        let record = DecisionRecord { txid: request.summary.txid, decision };
+        let contents = serde_json::to_vec(&record).map_err(|e| StoreError::Corrupt(e.to_string()))?;
+        fs::write(self.decision_path(session_id, participant), contents).map_err(|e| StoreError::Io(e.to_string()))
+    }
+
+    fn decision(&self, session_id: SessionId, participant: Identifier) -> Result<Option<ApprovalDecision>, StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        self.read_decision(session_id, participant)
+    }
+}
+
+/// In memory approval queue, decisions are made through the same instance.
+#[derive(Default)]
+pub struct MemoryApprovalQueue {
+    requests: Mutex<BTreeMap<(SessionId, Identifier), (PendingRequest, Option<ApprovalDecision>)>>,
+}
+
+impl ApprovalQueue for MemoryApprovalQueue {
+    fn submit(&self, request: &PendingRequest) -> Result<(), StoreError> {
+        let mut requests = self.requests.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        requests
+            .entry((request.session_id, request.participant))
+            .and_modify(|(existing, decision)| {
+                if existing.summary.txid != request.summary.txid {
+                    *decision = None;
+                }
+                *existing = request.clone();
+            })
+            .or_insert_with(|| (request.clone(), None));
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<PendingRequest>, StoreError> {
+        let requests = self.requests.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let now = SystemTime::now();
+        Ok(requests
+            .values()
+            .filter(|(request, decision)| decision.is_none() && request.deadline > now)
+            .map(|(request, _)| request.clone())
+            .collect())
+    }
+
+    fn decide(
+        &self,
+        session_id: SessionId,
+        participant: Identifier,
+        decision: ApprovalDecision,
+    ) -> Result<(), StoreError> {
+        let mut requests = self.requests.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        match requests.get_mut(&(session_id, participant)) {
+            Some((request, None)) if request.deadline <= SystemTime::now() => Err(StoreError::Expired(session_id)),
+            Some((_, current @ None)) => {
+                *current = Some(decision);
+                Ok(())
+            }
+            _ => Err(StoreError::UnknownSession(session_id)),
+        }
+    }
+
+    fn decision(&self, session_id: SessionId, participant: Identifier) -> Result<Option<ApprovalDecision>, StoreError> {
+        let requests = self.requests.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        Ok(requests.get(&(session_id, participant)).and_then(|(_, decision)| decision.clone()))
+    }
+}
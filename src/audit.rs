@@ -0,0 +1,223 @@
+use crate::{errors::AuditError, signer::SessionId};
+use bitcoin::{
+    hashes::{sha256, Hash},
+    secp256k1::{schnorr, Keypair, Message, Secp256k1, SecretKey, XOnlyPublicKey},
+    Txid,
+};
+use frost_secp256k1_tr::Identifier;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Previous hash of the first entry in a log.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Ceremony event recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditEvent {
+    /// A signing session started for a transaction.
+    SessionStarted { session_id: SessionId, txid: Txid, sighash: String },
+
+    /// Participants chosen to sign.
+    Participants { session_id: SessionId, participants: Vec<Identifier> },
+
+    /// Message received from a participant, with the SHA-256 hash of its serialized form.
+    MessageReceived { session_id: SessionId, from: Identifier, kind: String, digest: String },
+
+    /// The session produced a signed transaction.
+    SessionCompleted { session_id: SessionId, txid: Txid },
+
+    /// The session failed.
+    SessionFailed { session_id: SessionId, error: String },
+
+    /// A signed transaction was broadcast.
+    Broadcast { txid: Txid },
+}
+
+/// Entry of the audit log, hash-chained to the previous entry and signed by the node that wrote it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub index: u64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub event: AuditEvent,
+    pub prev_hash: String,
+    /// X-only public key of the writing node.
+    pub signer: String,
+    /// SHA-256 hash of the fields above.
+    pub hash: String,
+    /// BIP-340 signature of the hash.
+    pub signature: String,
+}
+
+/// Hashed part of an entry.
+#[derive(Serialize)]
+struct EntryBody<'a> {
+    index: u64,
+    timestamp: u64,
+    event: &'a AuditEvent,
+    prev_hash: &'a str,
+    signer: &'a str,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> Result<sha256::Hash, AuditError> {
+        let body = EntryBody {
+            index: self.index,
+            timestamp: self.timestamp,
+            event: &self.event,
+            prev_hash: &self.prev_hash,
+            signer: &self.signer,
+        };
+        let bytes = serde_json::to_vec(&body).map_err(|e| AuditError::Corrupt(e.to_string()))?;
+        Ok(sha256::Hash::hash(&bytes))
+    }
+}
+
+/// Append-only audit log file, one JSON entry per line.
+pub struct AuditLog {
+    path: PathBuf,
+    keypair: Keypair,
+    /// Index and hash of the last entry.
+    tail: Mutex<(u64, String)>,
+}
+
+impl AuditLog {
+    /// Opens a log, creating it if needed. New entries continue the chain of the existing ones.
+    pub fn open(path: impl AsRef<Path>, secret_key: SecretKey) -> Result<Self, AuditError> {
+        let path = path.as_ref().to_path_buf();
+        let tail = match load_entries(&path)?.last() {
+            Some(last) => (last.index + 1, last.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let keypair = Keypair::from_secret_key(&Secp256k1::new(), &secret_key);
+        Ok(Self { path, keypair, tail: Mutex::new(tail) })
+    }
+
+    /// Public key the entries are signed with.
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        self.keypair.x_only_public_key().0
+    }
+
+    /// Appends and syncs an entry for the event.
+    pub fn record(&self, event: AuditEvent) -> Result<AuditEntry, AuditError> {
+        let mut tail = self.tail.lock().map_err(|e| AuditError::Io(e.to_string()))?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| AuditError::Io(e.to_string()))?;
+
+        let mut entry = AuditEntry {
+            index: tail.0,
+            timestamp: timestamp.as_secs(),
+            event,
+            prev_hash: tail.1.clone(),
+            signer: self.public_key().to_string(),
+            hash: String::new(),
+            signature: String::new(),
+        };
+        let hash = entry.compute_hash()?;
+        let signature =
+            Secp256k1::new().sign_schnorr_no_aux_rand(&Message::from_digest(hash.to_byte_array()), &self.keypair);
+        entry.hash = hash.to_string();
+        entry.signature = signature.to_string();
+
+        let mut line = serde_json::to_vec(&entry).map_err(|e| AuditError::Corrupt(e.to_string()))?;
+        line.push(b'\n');
+        let mut file =
+            OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| AuditError::Io(e.to_string()))?;
+        file.write_all(&line).map_err(|e| AuditError::Io(e.to_string()))?;
+        file.sync_data().map_err(|e| AuditError::Io(e.to_string()))?;
+
+        *tail = (entry.index + 1, entry.hash.clone());
+        Ok(entry)
+    }
+}
+
+impl fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLog").field("path", &self.path).field("signer", &self.public_key()).finish_non_exhaustive()
+    }
+}
+
+/// Loads all entries of a log, empty if the log does not exist.
+pub fn load_entries(path: impl AsRef<Path>) -> Result<Vec<AuditEntry>, AuditError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AuditError::Io(e.to_string())),
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| serde_json::from_str(line).map_err(|e| AuditError::Corrupt(format!("line {index}: {e}"))))
+        .collect()
+}
+
+/// Checks the hash chain and the entry signatures. With trusted keys every entry must be signed by one of them.
+pub fn verify_entries(entries: &[AuditEntry], trusted_keys: &[XOnlyPublicKey]) -> Result<(), AuditError> {
+    let secp = Secp256k1::verification_only();
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (index, entry) in entries.iter().enumerate() {
+        let index = index as u64;
+        if entry.index != index || entry.prev_hash != prev_hash {
+            return Err(AuditError::BrokenChain(index));
+        }
+        let hash = entry.compute_hash()?;
+        if hash.to_string() != entry.hash {
+            return Err(AuditError::InvalidHash(index));
+        }
+
+        let signer = XOnlyPublicKey::from_str(&entry.signer).map_err(|_| AuditError::InvalidSignature(index))?;
+        let signature =
+            schnorr::Signature::from_str(&entry.signature).map_err(|_| AuditError::InvalidSignature(index))?;
+        secp.verify_schnorr(&signature, &Message::from_digest(hash.to_byte_array()), &signer)
+            .map_err(|_| AuditError::InvalidSignature(index))?;
+        if !trusted_keys.is_empty() && !trusted_keys.contains(&signer) {
+            return Err(AuditError::UntrustedSigner(index));
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(())
+}
+
+/// Verifies a log file, returns the number of entries.
+pub fn verify_log(path: impl AsRef<Path>, trusted_keys: &[XOnlyPublicKey]) -> Result<usize, AuditError> {
+    let entries = load_entries(path)?;
+    verify_entries(&entries, trusted_keys)?;
+    Ok(entries.len())
+}
+
+/// Loads the node's hex encoded signing key, generating and saving a new one readable only by the owner if the file
+/// does not exist.
+pub fn load_or_create_key(path: impl AsRef<Path>) -> Result<SecretKey, AuditError> {
+    let path = path.as_ref();
+    match fs::read_to_string(path) {
+        Ok(contents) => SecretKey::from_str(contents.trim()).map_err(|e| AuditError::Corrupt(e.to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let secret_key = loop {
+                let mut bytes = [0u8; 32];
+                OsRng.fill_bytes(&mut bytes);
+                if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+                    break secret_key;
+                }
+            };
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut file = options.open(path).map_err(|e| AuditError::Io(e.to_string()))?;
+            file.write_all(secret_key.display_secret().to_string().as_bytes())
+                .and_then(|()| file.sync_all())
+                .map_err(|e| AuditError::Io(e.to_string()))?;
+            Ok(secret_key)
+        }
+        Err(e) => Err(AuditError::Io(e.to_string())),
+    }
+}
@@ -0,0 +1,37 @@
+use anyhow::{anyhow, Context, Error};
+use bitcoin::secp256k1::{PublicKey, XOnlyPublicKey};
+use clap::Parser;
+use frost_demo::envelope::GroupId;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+
+#[derive(Parser)]
+#[command(name = "frost-relay", about = "WebSocket relay for FROST signers behind NATs")]
+struct Cli {
+    /// Address to listen on for WebSocket connections.
+    #[arg(long, default_value = "127.0.0.1:9000")]
+    listen: SocketAddr,
+
+    /// Coordinator of a group as <GROUP_ID>=<PUBLIC_KEY>, both hex as printed by `group-address` and `coordinator-key`.
+    /// Groups without one have no coordinator.
+    #[arg(long = "coordinator", value_parser = parse_coordinator)]
+    coordinators: Vec<(GroupId, XOnlyPublicKey)>,
+}
+
+fn parse_coordinator(s: &str) -> Result<(GroupId, XOnlyPublicKey), Error> {
+    let (group_id, public_key) = s.split_once('=').ok_or_else(|| anyhow!("Expected <GROUP_ID>=<PUBLIC_KEY>"))?;
+    let public_key: PublicKey = public_key.parse().context("Invalid public key")?;
+    Ok((group_id.parse().context("Invalid group id")?, public_key.x_only_public_key().0))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let filter = EnvFilter::builder().with_default_directive(LevelFilter::INFO.into()).from_env_lossy();
+    tracing_subscriber::fmt().with_env_filter(filter).with_target(false).without_time().init();
+
+    let cli = Cli::parse();
+    let listener =
+        TcpListener::bind(cli.listen).await.with_context(|| format!("Failed to listen on {}", cli.listen))?;
+    frost_demo::relay::serve(listener, cli.coordinators.into_iter().collect()).await.context("Relay failed")
+}
@@ -7,14 +7,15 @@ use bitcoin::{
     transaction::Transaction,
     Amount, OutPoint, ScriptBuf, Sequence, TxIn, TxOut, Txid, Witness,
 };
-use bitcoincore_rpc::{Auth, Client, RpcApi};
+use bitcoincore_rpc::{Auth, Client};
 use frost_secp256k1_tr::{
     self as frost, round1::SigningCommitments, Ciphersuite, Identifier, Signature, SigningPackage,
 };
 use std::{collections::BTreeMap, str::FromStr};
 use tracing::{debug, warn};
 
-const DEFAULT_FEE: u64 = 500;
+/// Fee of a spend, in satoshis, when none is given.
+pub const DEFAULT_FEE: u64 = 500;
 const DUST_P2TR: u64 = 330;
 
 /// Create spend transaction
@@ -25,12 +26,30 @@ pub fn create_unsigned_transaction(
     pay_amount: Amount,
     change_addr: Address,
 ) -> Result<Transaction, BitcoinError> {
-    let fee = Amount::from_sat(DEFAULT_FEE);
+    create_unsigned_transaction_with_fee(
+        utxo,
+        utxo_to_spend,
+        to_addr,
+        pay_amount,
+        change_addr,
+        Amount::from_sat(DEFAULT_FEE),
+    )
+}
+
+/// Create spend transaction paying the given fee.
+pub fn create_unsigned_transaction_with_fee(
+    utxo: OutPoint,
+    utxo_to_spend: &TxOut,
+    to_addr: Address,
+    pay_amount: Amount,
+    change_addr: Address,
+    fee: Amount,
+) -> Result<Transaction, BitcoinError> {
     let dust = Amount::from_sat(DUST_P2TR);
     let total_value = utxo_to_spend.value;
     if pay_amount + fee > total_value {
         return Err(BitcoinError::Spend(format!(
-            "amount ({pay_amount}) + fee ({DEFAULT_FEE}) exceeds utxo value ({total_value})"
+            "amount ({pay_amount}) + fee ({fee}) exceeds utxo value ({total_value})"
         )));
     }
 
@@ -116,20 +135,3 @@ pub fn parse_utxo(utxo_str: &str) -> Result<OutPoint, BitcoinError> {
     let vout = vout_str.parse::<u32>().map_err(|_| BitcoinError::Utxo("Invalid vout".to_string()))?;
     Ok(OutPoint { txid, vout })
 }
-
-/// Fetches the specific transaction output (TxOut) we intend to spend.
-pub fn fetch_utxo_to_spend(rpc_client: &Client, outpoint: &OutPoint) -> Result<TxOut, BitcoinError> {
-    let prev_tx =
-        rpc_client.get_raw_transaction(&outpoint.txid, None).map_err(|e| BitcoinError::Client(e.to_string()))?;
-
-    prev_tx
-        .output
-        .get(outpoint.vout as usize)
-        .cloned()
-        .ok_or_else(|| BitcoinError::Utxo("Vout index out of bounds for the previous transaction".to_string()))
-}
-
-/// Broadcasts the signed transaction to the Bitcoin network.
-pub fn broadcast_transaction(rpc_client: &Client, signed_tx: &bitcoin::Transaction) -> Result<Txid, BitcoinError> {
-    rpc_client.send_raw_transaction(signed_tx).map_err(|e| BitcoinError::Client(e.to_string()))
-}
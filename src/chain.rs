@@ -0,0 +1,438 @@
+use crate::{bitcoin::DEFAULT_FEE, errors::BitcoinError};
+use async_trait::async_trait;
+use bitcoin::{
+    consensus,
+    hashes::{sha256, Hash},
+    Amount, FeeRate, OutPoint, Script, ScriptBuf, Transaction, TxOut, Txid,
+};
+use bitcoincore_rpc::{json::ScanTxOutRequest, Client, RpcApi};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// Blocks a spend should confirm within.
+pub const FEE_TARGET_BLOCKS: u16 = 6;
+
+/// Virtual size of a spend of one key path input to two P2TR outputs.
+pub const SPEND_VSIZE: u64 = 154;
+
+/// Fee of a spend when the backend has no estimate.
+pub const FALLBACK_FEE: Amount = Amount::from_sat(DEFAULT_FEE);
+
+/// Highest fee rate paid on a backend's estimate, whatever the policy allows.
+pub const MAX_FEE_RATE: FeeRate = FeeRate::from_sat_per_vb_unchecked(200);
+
+/// Unspent output of a script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Utxo {
+    /// Outpoint as "txid:vout".
+    #[schema(value_type = String)]
+    pub outpoint: OutPoint,
+    /// Value in satoshis.
+    #[schema(value_type = u64)]
+    pub value: Amount,
+    /// Hex encoded output script.
+    #[schema(value_type = String)]
+    pub script_pubkey: ScriptBuf,
+    /// Height of the block the output was confirmed in, none while unconfirmed.
+    pub height: Option<u64>,
+}
+
+/// Transaction paying to or spending from a script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub txid: Txid,
+    /// Height of the block the transaction was confirmed in, none while unconfirmed.
+    pub height: Option<u64>,
+}
+
+/// Orders history oldest first, unconfirmed transactions last.
+fn sort_history(history: &mut [HistoryEntry]) {
+    history.sort_by_key(|entry| (entry.height.is_none(), entry.height, entry.txid));
+}
+
+/// Source of chain data and sink of signed transactions.
+#[async_trait]
+pub trait ChainBackend: Send + Sync {
+    async fn get_transaction(&self, txid: &Txid) -> Result<Transaction, BitcoinError>;
+
+    /// Output of a transaction, spent or not.
+    async fn get_tx_out(&self, outpoint: &OutPoint) -> Result<TxOut, BitcoinError> {
+        let transaction = self.get_transaction(&outpoint.txid).await?;
+        transaction
+            .output
+            .get(outpoint.vout as usize)
+            .cloned()
+            .ok_or_else(|| BitcoinError::Utxo("Vout index out of bounds for the previous transaction".to_string()))
+    }
+
+    async fn list_unspent(&self, script: &Script) -> Result<Vec<Utxo>, BitcoinError>;
+
+    /// Transactions of a script, oldest first and unconfirmed ones last.
+    async fn history(&self, _script: &Script) -> Result<Vec<HistoryEntry>, BitcoinError> {
+        Err(client_error("The backend doesn't index address history"))
+    }
+
+    async fn broadcast(&self, transaction: &Transaction) -> Result<Txid, BitcoinError>;
+
+    /// Fee rate for confirmation within the number of blocks.
+    async fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, BitcoinError>;
+
+    async fn tip_height(&self) -> Result<u64, BitcoinError>;
+}
+
+/// Fee of a spend confirming within [`FEE_TARGET_BLOCKS`], [`FALLBACK_FEE`] if the backend has no estimate. The rate
+/// is capped at `max_fee_rate` and [`MAX_FEE_RATE`], a backend can't make the group overpay.
+pub async fn estimate_spend_fee(backend: &dyn ChainBackend, max_fee_rate: Option<FeeRate>) -> Amount {
+    let ceiling = max_fee_rate.map_or(MAX_FEE_RATE, |max| max.min(MAX_FEE_RATE));
+    match backend.estimate_fee_rate(FEE_TARGET_BLOCKS).await {
+        Ok(rate) if rate > ceiling => {
+            warn!(
+                "Fee estimate of {} sat/vB exceeds {} sat/vB, paying the lower rate",
+                rate.to_sat_per_vb_ceil(),
+                ceiling.to_sat_per_vb_ceil()
+            );
+            ceiling.fee_vb(SPEND_VSIZE).unwrap_or(FALLBACK_FEE)
+        }
+        Ok(rate) => rate.fee_vb(SPEND_VSIZE).unwrap_or(FALLBACK_FEE),
+        Err(e) => {
+            warn!("No fee estimate, paying {FALLBACK_FEE}: {e}");
+            FALLBACK_FEE
+        }
+    }
+}
+
+pub(crate) fn client_error(error: impl ToString) -> BitcoinError {
+    BitcoinError::Client(error.to_string())
+}
+
+/// Fee rate of an estimate in sat/kvB, rounded up to whole sat/kwu to never underpay.
+pub(crate) fn fee_rate_from_sat_per_kvb(sat_per_kvb: u64) -> FeeRate {
+    FeeRate::from_sat_per_kwu(sat_per_kvb.div_ceil(4))
+}
+
+/// Bitcoin Core over JSON-RPC.
+pub struct CoreRpcBackend {
+    client: Arc<Client>,
+}
+
+impl CoreRpcBackend {
+    pub fn new(client: Client) -> Self {
+        Self { client: Arc::new(client) }
+    }
+
+    /// Runs a call to the node off the async runtime, the RPC client blocks.
+    async fn call<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&Client) -> Result<T, bitcoincore_rpc::Error> + Send + 'static,
+    ) -> Result<T, BitcoinError> {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || call(&client)).await.map_err(client_error)?.map_err(client_error)
+    }
+}
+
+#[async_trait]
+impl ChainBackend for CoreRpcBackend {
+    async fn get_transaction(&self, txid: &Txid) -> Result<Transaction, BitcoinError> {
+        let txid = *txid;
+        self.call(move |client| client.get_raw_transaction(&txid, None)).await
+    }
+
+    /// Scans the UTXO set of the node, only confirmed outputs are found.
+    async fn list_unspent(&self, script: &Script) -> Result<Vec<Utxo>, BitcoinError> {
+        let request = ScanTxOutRequest::Single(format!("raw({})", script.to_hex_string()));
+        let result = self.call(move |client| client.scan_tx_out_set_blocking(&[request])).await?;
+        Ok(result
+            .unspents
+            .into_iter()
+            .map(|utxo| Utxo {
+                outpoint: OutPoint { txid: utxo.txid, vout: utxo.vout },
+                value: utxo.amount,
+                script_pubkey: utxo.script_pub_key,
+                height: Some(utxo.height),
+            })
+            .collect())
+    }
+
+    async fn broadcast(&self, transaction: &Transaction) -> Result<Txid, BitcoinError> {
+        let transaction = transaction.clone();
+        self.call(move |client| client.send_raw_transaction(&transaction)).await
+    }
+
+    async fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, BitcoinError> {
+        let estimate = self.call(move |client| client.estimate_smart_fee(target_blocks, None)).await?;
+        // Estimated in BTC per kvB
+        let per_kvb = estimate
+            .fee_rate
+            .ok_or_else(|| client_error(format!("No fee estimate: {:?}", estimate.errors.unwrap_or_default())))?;
+        Ok(fee_rate_from_sat_per_kvb(per_kvb.to_sat()))
+    }
+
+    async fn tip_height(&self) -> Result<u64, BitcoinError> {
+        self.call(|client| client.get_block_count()).await
+    }
+}
+
+#[derive(Deserialize)]
+struct EsploraStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct EsploraTx {
+    txid: Txid,
+    status: EsploraStatus,
+}
+
+impl EsploraStatus {
+    fn height(&self) -> Option<u64> {
+        self.block_height.filter(|_| self.confirmed)
+    }
+}
+
+#[derive(Deserialize)]
+struct EsploraUtxo {
+    txid: Txid,
+    vout: u32,
+    value: u64,
+    status: EsploraStatus,
+}
+
+/// Esplora REST API, as served by blockstream.info and mempool.space.
+pub struct EsploraBackend {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl EsploraBackend {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.trim_end_matches('/').to_string(), client: reqwest::Client::new() }
+    }
+
+    async fn get(&self, path: &str) -> Result<reqwest::Response, BitcoinError> {
+        let response = self.client.get(format!("{}{path}", self.url)).send().await.map_err(client_error)?;
+        response.error_for_status().map_err(client_error)
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String, BitcoinError> {
+        self.get(path).await?.text().await.map_err(client_error)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, BitcoinError> {
+        self.get(path).await?.json().await.map_err(client_error)
+    }
+}
+
+/// Esplora identifies scripts by their SHA256 hash, unlike Electrum not reversed.
+fn esplora_script_hash(script: &Script) -> String {
+    sha256::Hash::hash(script.as_bytes()).to_string()
+}
+
+#[async_trait]
+impl ChainBackend for EsploraBackend {
+    async fn get_transaction(&self, txid: &Txid) -> Result<Transaction, BitcoinError> {
+        let raw = self.get(&format!("/tx/{txid}/raw")).await?.bytes().await.map_err(client_error)?;
+        consensus::deserialize(&raw).map_err(client_error)
+    }
+
+    async fn list_unspent(&self, script: &Script) -> Result<Vec<Utxo>, BitcoinError> {
+        let utxos: Vec<EsploraUtxo> =
+            self.get_json(&format!("/scripthash/{}/utxo", esplora_script_hash(script))).await?;
+        Ok(utxos
+            .into_iter()
+            .map(|utxo| Utxo {
+                outpoint: OutPoint { txid: utxo.txid, vout: utxo.vout },
+                value: Amount::from_sat(utxo.value),
+                script_pubkey: script.to_owned(),
+                height: utxo.status.height(),
+            })
+            .collect())
+    }
+
+    /// Pages through the confirmed transactions, which Esplora lists newest first, until a page is empty or doesn't
+    /// move past the last transaction seen. Servers differ in their page size.
+    async fn history(&self, script: &Script) -> Result<Vec<HistoryEntry>, BitcoinError> {
+        let hash = esplora_script_hash(script);
+        let mut transactions: Vec<EsploraTx> = Vec::new();
+        let mut path = format!("/scripthash/{hash}/txs/chain");
+        let mut last_seen = None;
+        loop {
+            let page: Vec<EsploraTx> = self.get_json(&path).await?;
+            let Some(last) = page.last().map(|tx| tx.txid) else {
+                break;
+            };
+            if last_seen == Some(last) {
+                break;
+            }
+            last_seen = Some(last);
+            path = format!("/scripthash/{hash}/txs/chain/{last}");
+            transactions.extend(page);
+        }
+        let mempool: Vec<EsploraTx> = self.get_json(&format!("/scripthash/{hash}/txs/mempool")).await?;
+        transactions.extend(mempool);
+
+        let mut history: Vec<_> =
+            transactions.into_iter().map(|tx| HistoryEntry { txid: tx.txid, height: tx.status.height() }).collect();
+        sort_history(&mut history);
+        Ok(history)
+    }
+
+    async fn broadcast(&self, transaction: &Transaction) -> Result<Txid, BitcoinError> {
+        let response = self
+            .client
+            .post(format!("{}/tx", self.url))
+            .body(consensus::encode::serialize_hex(transaction))
+            .send()
+            .await
+            .map_err(client_error)?;
+        let status = response.status();
+        let body = response.text().await.map_err(client_error)?;
+        if !status.is_success() {
+            return Err(client_error(format!("Broadcast rejected ({status}): {body}")));
+        }
+        body.trim().parse().map_err(client_error)
+    }
+
+    /// Esplora estimates in sat/vB for a fixed set of targets, the nearest target not above the asked one is used.
+    async fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, BitcoinError> {
+        let estimates: HashMap<String, f64> = self.get_json("/fee-estimates").await?;
+        let estimates: BTreeMap<u16, f64> =
+            estimates.into_iter().filter_map(|(target, rate)| Some((target.parse().ok()?, rate))).collect();
+        let (_, per_vb) = estimates
+            .range(..=target_blocks)
+            .next_back()
+            .or_else(|| estimates.iter().next())
+            .ok_or_else(|| client_error("No fee estimates"))?;
+        // Rounded to whole sat/kvB first so float noise doesn't round up a whole sat/kwu
+        Ok(fee_rate_from_sat_per_kvb((per_vb * 1000.0).round() as u64))
+    }
+
+    async fn tip_height(&self) -> Result<u64, BitcoinError> {
+        self.get_text("/blocks/tip/height").await?.trim().parse().map_err(client_error)
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Known transactions with the height they confirmed at.
+    transactions: HashMap<Txid, (Transaction, Option<u64>)>,
+    broadcast: Vec<Transaction>,
+    fee_rate: Option<FeeRate>,
+    tip_height: u64,
+}
+
+/// In-memory chain for tests, broadcast transactions stay unconfirmed.
+#[derive(Default)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+}
+
+impl MockBackend {
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds a transaction confirmed at the height, unconfirmed if none.
+    pub fn add_transaction(&self, transaction: Transaction, height: Option<u64>) {
+        let mut state = self.state();
+        if let Some(height) = height {
+            state.tip_height = state.tip_height.max(height);
+        }
+        state.transactions.insert(transaction.compute_txid(), (transaction, height));
+    }
+
+    pub fn set_fee_rate(&self, fee_rate: FeeRate) {
+        self.state().fee_rate = Some(fee_rate);
+    }
+
+    /// Transactions broadcast so far, oldest first.
+    pub fn broadcast_transactions(&self) -> Vec<Transaction> {
+        self.state().broadcast.clone()
+    }
+}
+
+impl MockState {
+    fn is_spent(&self, outpoint: &OutPoint) -> bool {
+        self.transactions.values().any(|(tx, _)| tx.input.iter().any(|input| input.previous_output == *outpoint))
+    }
+}
+
+#[async_trait]
+impl ChainBackend for MockBackend {
+    async fn get_transaction(&self, txid: &Txid) -> Result<Transaction, BitcoinError> {
+        let state = self.state();
+        let (transaction, _) =
+            state.transactions.get(txid).ok_or_else(|| client_error(format!("Unknown tx {txid}")))?;
+        Ok(transaction.clone())
+    }
+
+    async fn list_unspent(&self, script: &Script) -> Result<Vec<Utxo>, BitcoinError> {
+        let state = self.state();
+        let mut utxos = Vec::new();
+        for (txid, (transaction, height)) in &state.transactions {
+            for (vout, output) in transaction.output.iter().enumerate() {
+                let outpoint = OutPoint { txid: *txid, vout: vout as u32 };
+                if output.script_pubkey.as_script() == script && !state.is_spent(&outpoint) {
+                    let script_pubkey = output.script_pubkey.clone();
+                    utxos.push(Utxo { outpoint, value: output.value, script_pubkey, height: *height });
+                }
+            }
+        }
+        utxos.sort_by_key(|utxo| utxo.outpoint);
+        Ok(utxos)
+    }
+
+    async fn history(&self, script: &Script) -> Result<Vec<HistoryEntry>, BitcoinError> {
+        let state = self.state();
+        let pays = |tx: &Transaction| tx.output.iter().any(|output| output.script_pubkey.as_script() == script);
+        let spends = |tx: &Transaction| {
+            tx.input.iter().any(|input| {
+                let outpoint = input.previous_output;
+                state.transactions.get(&outpoint.txid).is_some_and(|(previous, _)| {
+                    previous
+                        .output
+                        .get(outpoint.vout as usize)
+                        .is_some_and(|output| output.script_pubkey.as_script() == script)
+                })
+            })
+        };
+        let mut history: Vec<_> = state
+            .transactions
+            .iter()
+            .filter(|(_, (tx, _))| pays(tx) || spends(tx))
+            .map(|(txid, (_, height))| HistoryEntry { txid: *txid, height: *height })
+            .collect();
+        sort_history(&mut history);
+        Ok(history)
+    }
+
+    /// Accepts transactions spending known unspent outputs.
+    async fn broadcast(&self, transaction: &Transaction) -> Result<Txid, BitcoinError> {
+        let mut state = self.state();
+        for input in &transaction.input {
+            let outpoint = input.previous_output;
+            let known =
+                state.transactions.get(&outpoint.txid).is_some_and(|(tx, _)| tx.output.len() > outpoint.vout as usize);
+            if !known || state.is_spent(&outpoint) {
+                return Err(client_error(format!("Missing or spent input {outpoint}")));
+            }
+        }
+        let txid = transaction.compute_txid();
+        state.transactions.insert(txid, (transaction.clone(), None));
+        state.broadcast.push(transaction.clone());
+        Ok(txid)
+    }
+
+    async fn estimate_fee_rate(&self, _: u16) -> Result<FeeRate, BitcoinError> {
+        self.state().fee_rate.ok_or_else(|| client_error("No fee estimate"))
+    }
+
+    async fn tip_height(&self) -> Result<u64, BitcoinError> {
+        Ok(self.state().tip_height)
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    envelope::{Envelope, GroupId},
+    errors::CodecError,
+    offline::{MessageFile, OfflineRequest},
+    signer::{SessionId, SigningMessage, SigningRequest},
+    websocket::CoordinatorMessage,
+};
+use bitcoin::{consensus, secp256k1::schnorr, Transaction, TxOut};
+use frost_secp256k1_tr as frost;
+use frost_secp256k1_tr::{Identifier, SigningPackage};
+use serde::Serialize;
+
+/// Version of the wire format, the first byte of every frame.
+pub const WIRE_VERSION: u8 = 1;
+
+/// Largest frame accepted, checked before anything is decoded.
+pub const MAX_WIRE_SIZE: usize = 1 << 20;
+
+/// Size of the frame header, the version and the length of the body.
+const HEADER_SIZE: usize = 5;
+
+/// Tags of the `SigningMessage` variants.
+const NONCE_COMMITMENT: u8 = 1;
+const SIGNATURE_SHARE: u8 = 2;
+const PREPROCESSED_COMMITMENTS: u8 = 3;
+const ABORT: u8 = 4;
+
+/// Tags of the `CoordinatorMessage` variants.
+const REQUEST: u8 = 1;
+const PACKAGE: u8 = 2;
+
+/// Compact binary encoding, the body of the wire format and what QR codes carry. Integers are big-endian, variable
+/// length fields are prefixed with their length as a u32, FROST values use their own serialization and transactions
+/// the consensus encoding.
+pub trait Compact: Sized {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError>;
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError>;
+}
+
+pub fn to_bytes<T: Compact>(value: &T) -> Result<Vec<u8>, CodecError> {
+    let mut out = Vec::new();
+    value.encode(&mut out)?;
+    Ok(out)
+}
+
+/// Decodes a value that must span all of `bytes`.
+pub fn from_bytes<T: Compact>(bytes: &[u8]) -> Result<T, CodecError> {
+    let mut reader = Reader { bytes };
+    let value = T::decode(&mut reader)?;
+    if !reader.bytes.is_empty() {
+        return Err(CodecError::TrailingBytes(reader.bytes.len()));
+    }
+    Ok(value)
+}
+
+/// Encodes a value as a wire frame: the format version, the length of the body as a u32 and the body.
+pub fn to_wire<T: Compact>(value: &T) -> Result<Vec<u8>, CodecError> {
+    let body = to_bytes(value)?;
+    if HEADER_SIZE + body.len() > MAX_WIRE_SIZE {
+        return Err(CodecError::Oversized { size: HEADER_SIZE + body.len(), max: MAX_WIRE_SIZE });
+    }
+    let mut frame = Vec::with_capacity(HEADER_SIZE + body.len());
+    frame.push(WIRE_VERSION);
+    put_blob(&mut frame, &body)?;
+    Ok(frame)
+}
+
+/// Decodes a wire frame. Only the canonical encoding is accepted, so every value has exactly one frame.
+pub fn from_wire<T: Compact>(frame: &[u8]) -> Result<T, CodecError> {
+    if frame.len() > MAX_WIRE_SIZE {
+        return Err(CodecError::Oversized { size: frame.len(), max: MAX_WIRE_SIZE });
+    }
+    let mut reader = Reader { bytes: frame };
+    let version = reader.u8("version")?;
+    if version != WIRE_VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+    let body = reader.blob("body")?;
+    if !reader.bytes.is_empty() {
+        return Err(CodecError::TrailingBytes(reader.bytes.len()));
+    }
+    let value = from_bytes(body)?;
+    // FROST and consensus decoding accept some inputs they would never produce
+    if to_bytes(&value)? != body {
+        return Err(CodecError::NonCanonical);
+    }
+    Ok(value)
+}
+
+/// JSON representation of a wire frame, for logs and debugging.
+pub fn debug_json<T: Compact + Serialize>(frame: &[u8]) -> Result<String, CodecError> {
+    serde_json::to_string_pretty(&from_wire::<T>(frame)?).map_err(|e| invalid("JSON", e))
+}
+
+/// Cursor over the encoded bytes.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, what: &'static str) -> Result<&'a [u8], CodecError> {
+        if self.bytes.len() < len {
+            return Err(CodecError::Truncated(what));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self, what: &'static str) -> Result<[u8; N], CodecError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N, what)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self, what: &'static str) -> Result<u8, CodecError> {
+        Ok(self.array::<1>(what)?[0])
+    }
+
+    fn u16(&mut self, what: &'static str) -> Result<u16, CodecError> {
+        Ok(u16::from_be_bytes(self.array(what)?))
+    }
+
+    fn u32(&mut self, what: &'static str) -> Result<u32, CodecError> {
+        Ok(u32::from_be_bytes(self.array(what)?))
+    }
+
+    fn u64(&mut self, what: &'static str) -> Result<u64, CodecError> {
+        Ok(u64::from_be_bytes(self.array(what)?))
+    }
+
+    fn blob(&mut self, what: &'static str) -> Result<&'a [u8], CodecError> {
+        let len = self.u32(what)?;
+        self.take(len as usize, what)
+    }
+}
+
+fn put_len(out: &mut Vec<u8>, len: usize) -> Result<(), CodecError> {
+    let len = u32::try_from(len).map_err(|_| CodecError::Invalid(format!("length {len} exceeds u32")))?;
+    out.extend_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+fn put_blob(out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), CodecError> {
+    put_len(out, bytes.len())?;
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn invalid(what: &str, e: impl std::fmt::Display) -> CodecError {
+    CodecError::Invalid(format!("{what}: {e}"))
+}
+
+impl Compact for Identifier {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        put_blob(out, &self.serialize())
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Identifier::deserialize(reader.blob("identifier")?).map_err(|e| invalid("identifier", e))
+    }
+}
+
+impl Compact for SessionId {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        out.extend_from_slice(self.as_bytes());
+        Ok(())
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(SessionId::from(reader.array("session id")?))
+    }
+}
+
+impl Compact for frost::round1::SigningCommitments {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        put_blob(out, &self.serialize().map_err(|e| invalid("commitments", e))?)
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Self::deserialize(reader.blob("commitments")?).map_err(|e| invalid("commitments", e))
+    }
+}
+
+impl Compact for SigningMessage {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        match self {
+            SigningMessage::NonceCommitment(session_id, sender, commitments) => {
+                out.push(NONCE_COMMITMENT);
+                session_id.encode(out)?;
+                sender.encode(out)?;
+                commitments.encode(out)
+            }
+            SigningMessage::SignatureShare(session_id, sender, share) => {
+                out.push(SIGNATURE_SHARE);
+                session_id.encode(out)?;
+                sender.encode(out)?;
+                put_blob(out, &share.serialize())
+            }
+            SigningMessage::PreprocessedCommitments(sender, batch) => {
+                out.push(PREPROCESSED_COMMITMENTS);
+                sender.encode(out)?;
+                put_len(out, batch.len())?;
+                for (id, commitments) in batch {
+                    out.extend_from_slice(&id.to_be_bytes());
+                    commitments.encode(out)?;
+                }
+                Ok(())
+            }
+            SigningMessage::Abort(session_id, sender, reason) => {
+                out.push(ABORT);
+                session_id.encode(out)?;
+                sender.encode(out)?;
+                put_blob(out, reason.as_bytes())
+            }
+        }
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        match reader.u8("message tag")? {
+            NONCE_COMMITMENT => Ok(SigningMessage::NonceCommitment(
+                SessionId::decode(reader)?,
+                Identifier::decode(reader)?,
+                Box::new(Compact::decode(reader)?),
+            )),
+            SIGNATURE_SHARE => {
+                let session_id = SessionId::decode(reader)?;
+                let sender = Identifier::decode(reader)?;
+                let share = frost::round2::SignatureShare::deserialize(reader.blob("signature share")?)
+                    .map_err(|e| invalid("signature share", e))?;
+                Ok(SigningMessage::SignatureShare(session_id, sender, share))
+            }
+            PREPROCESSED_COMMITMENTS => {
+                let sender = Identifier::decode(reader)?;
+                let count = reader.u32("batch length")?;
+                // Every entry takes more than a byte, so a count beyond the input is rejected before allocating
+                if count as usize > reader.bytes.len() {
+                    return Err(CodecError::Truncated("batch"));
+                }
+                let batch = (0..count)
+                    .map(|_| Ok((reader.u64("commitment id")?, Compact::decode(reader)?)))
+                    .collect::<Result<_, CodecError>>()?;
+                Ok(SigningMessage::PreprocessedCommitments(sender, batch))
+            }
+            ABORT => {
+                let session_id = SessionId::decode(reader)?;
+                let sender = Identifier::decode(reader)?;
+                let reason =
+                    String::from_utf8(reader.blob("abort reason")?.to_vec()).map_err(|e| invalid("abort reason", e))?;
+                Ok(SigningMessage::Abort(session_id, sender, reason))
+            }
+            tag => Err(CodecError::UnknownVariant { kind: "message", tag }),
+        }
+    }
+}
+
+impl Compact for Envelope {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        out.extend_from_slice(&self.version.to_be_bytes());
+        out.extend_from_slice(self.group_id.as_bytes());
+        out.push(self.round);
+        out.extend_from_slice(&self.sequence.to_be_bytes());
+        self.message.encode(out)?;
+        out.extend_from_slice(self.signature.as_ref());
+        Ok(())
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Envelope {
+            version: reader.u16("version")?,
+            group_id: GroupId::from(reader.array("group id")?),
+            round: reader.u8("round")?,
+            sequence: reader.u64("sequence")?,
+            message: SigningMessage::decode(reader)?,
+            signature: schnorr::Signature::from_slice(reader.take(64, "signature")?)
+                .map_err(|e| invalid("signature", e))?,
+        })
+    }
+}
+
+impl Compact for SigningRequest {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        put_blob(out, &consensus::serialize(&self.transaction))?;
+        put_blob(out, &consensus::serialize(&self.prev_tx_outs))
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let transaction: Transaction =
+            consensus::deserialize(reader.blob("transaction")?).map_err(|e| invalid("transaction", e))?;
+        let prev_tx_outs: Vec<TxOut> =
+            consensus::deserialize(reader.blob("spent outputs")?).map_err(|e| invalid("spent outputs", e))?;
+        Ok(SigningRequest { transaction, prev_tx_outs })
+    }
+}
+
+impl Compact for OfflineRequest {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.session_id.encode(out)?;
+        self.request.encode(out)
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(OfflineRequest { session_id: SessionId::decode(reader)?, request: SigningRequest::decode(reader)? })
+    }
+}
+
+impl Compact for MessageFile {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        match &self.to {
+            Some(to) => {
+                out.push(1);
+                to.encode(out)?;
+            }
+            None => out.push(0),
+        }
+        self.envelope.encode(out)
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let to = match reader.u8("addressee")? {
+            0 => None,
+            1 => Some(Identifier::decode(reader)?),
+            tag => return Err(CodecError::UnknownVariant { kind: "addressee", tag }),
+        };
+        Ok(MessageFile { to, envelope: Envelope::decode(reader)? })
+    }
+}
+
+impl Compact for SigningPackage {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        put_blob(out, &self.serialize().map_err(|e| invalid("signing package", e))?)
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        SigningPackage::deserialize(reader.blob("signing package")?).map_err(|e| invalid("signing package", e))
+    }
+}
+
+impl Compact for CoordinatorMessage {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        match self {
+            CoordinatorMessage::Request(request) => {
+                out.push(REQUEST);
+                request.encode(out)
+            }
+            CoordinatorMessage::Package(request, signing_package) => {
+                out.push(PACKAGE);
+                request.encode(out)?;
+                signing_package.encode(out)
+            }
+        }
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, CodecError> {
+        match reader.u8("coordinator message tag")? {
+            REQUEST => Ok(CoordinatorMessage::Request(OfflineRequest::decode(reader)?)),
+            PACKAGE => {
+                Ok(CoordinatorMessage::Package(OfflineRequest::decode(reader)?, SigningPackage::decode(reader)?))
+            }
+            tag => Err(CodecError::UnknownVariant { kind: "coordinator message", tag }),
+        }
+    }
+}
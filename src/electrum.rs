@@ -0,0 +1,200 @@
+use crate::{
+    chain::{client_error, fee_rate_from_sat_per_kvb, ChainBackend, HistoryEntry, Utxo},
+    errors::BitcoinError,
+};
+use async_trait::async_trait;
+use bitcoin::{
+    consensus,
+    hashes::{sha256, Hash},
+    Amount, FeeRate, OutPoint, Script, Transaction, Txid,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::Mutex,
+    time::timeout,
+};
+
+/// Protocol version asked for when connecting.
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// Time allowed for the server to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest line read from the server, a response or a notification.
+const MAX_LINE_LEN: u64 = 8 * 1024 * 1024;
+
+/// Electrum server over plain TCP, reconnecting after a failed request.
+pub struct ElectrumBackend {
+    addr: String,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl ElectrumBackend {
+    /// Server at `tcp://host:port` or `host:port`, TLS servers aren't supported.
+    pub fn new(url: &str) -> Result<Self, BitcoinError> {
+        let addr = url.strip_prefix("tcp://").unwrap_or(url);
+        if addr.contains("://") {
+            return Err(client_error(format!("Unsupported Electrum server {url}, expected tcp://host:port")));
+        }
+        Ok(Self { addr: addr.to_string(), connection: Mutex::default() })
+    }
+
+    async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, BitcoinError> {
+        let mut guard = self.connection.lock().await;
+        let mut connection = match guard.take() {
+            Some(connection) => connection,
+            None => Connection::open(&self.addr).await?,
+        };
+        let response = timeout(REQUEST_TIMEOUT, connection.call(method, params))
+            .await
+            .map_err(|_| client_error(format!("Electrum request {method} timed out")))??;
+        // The connection is only kept if it's still in sync
+        *guard = Some(connection);
+        let result = response.map_err(|e| client_error(format!("Electrum request {method} failed: {e}")))?;
+        serde_json::from_value(result).map_err(client_error)
+    }
+}
+
+struct Connection {
+    stream: BufReader<TcpStream>,
+    next_id: u64,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: Option<u64>,
+    #[serde(default)]
+    result: Value,
+    error: Option<Value>,
+}
+
+impl Connection {
+    async fn open(addr: &str) -> Result<Self, BitcoinError> {
+        let stream = TcpStream::connect(addr).await.map_err(client_error)?;
+        let mut connection = Self { stream: BufReader::new(stream), next_id: 0 };
+        // Servers expect the version to be negotiated first
+        connection.call("server.version", json!(["frost-demo", PROTOCOL_VERSION])).await?.map_err(client_error)?;
+        Ok(connection)
+    }
+
+    /// Sends a request and reads up to its response, the error of the server is returned as the inner error.
+    async fn call(&mut self, method: &str, params: Value) -> Result<Result<Value, String>, BitcoinError> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let mut request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string();
+        request.push('\n');
+        self.stream.get_mut().write_all(request.as_bytes()).await.map_err(client_error)?;
+
+        loop {
+            let mut line = String::new();
+            let read = (&mut self.stream).take(MAX_LINE_LEN).read_line(&mut line).await.map_err(client_error)?;
+            if read == 0 {
+                return Err(client_error("Electrum server closed the connection"));
+            }
+            if !line.ends_with('\n') && read as u64 == MAX_LINE_LEN {
+                return Err(client_error(format!("Electrum response exceeds {MAX_LINE_LEN} bytes")));
+            }
+            let response: Response = serde_json::from_str(&line).map_err(client_error)?;
+            // Subscription notifications have no id
+            if response.id != Some(id) {
+                continue;
+            }
+            return Ok(match response.error {
+                Some(error) => Err(error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string())),
+                None => Ok(response.result),
+            });
+        }
+    }
+}
+
+/// Electrum identifies scripts by their SHA256 hash, reversed.
+pub fn electrum_script_hash(script: &Script) -> String {
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+    hex::encode(hash)
+}
+
+/// Height of a history entry or unspent output, zero or negative while unconfirmed.
+fn confirmed_height(height: i64) -> Option<u64> {
+    u64::try_from(height).ok().filter(|height| *height > 0)
+}
+
+#[derive(Deserialize)]
+struct ElectrumUtxo {
+    tx_hash: Txid,
+    tx_pos: u32,
+    height: i64,
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct ElectrumHistory {
+    tx_hash: Txid,
+    height: i64,
+}
+
+#[derive(Deserialize)]
+struct ElectrumHeader {
+    height: u64,
+}
+
+#[async_trait]
+impl ChainBackend for ElectrumBackend {
+    async fn get_transaction(&self, txid: &Txid) -> Result<Transaction, BitcoinError> {
+        let raw: String = self.request("blockchain.transaction.get", json!([txid])).await?;
+        consensus::deserialize(&hex::decode(raw).map_err(client_error)?).map_err(client_error)
+    }
+
+    async fn list_unspent(&self, script: &Script) -> Result<Vec<Utxo>, BitcoinError> {
+        let params = json!([electrum_script_hash(script)]);
+        let utxos: Vec<ElectrumUtxo> = self.request("blockchain.scripthash.listunspent", params).await?;
+        Ok(utxos
+            .into_iter()
+            .map(|utxo| Utxo {
+                outpoint: OutPoint { txid: utxo.tx_hash, vout: utxo.tx_pos },
+                value: Amount::from_sat(utxo.value),
+                script_pubkey: script.to_owned(),
+                height: confirmed_height(utxo.height),
+            })
+            .collect())
+    }
+
+    /// Electrum already orders history oldest first, unconfirmed transactions last.
+    async fn history(&self, script: &Script) -> Result<Vec<HistoryEntry>, BitcoinError> {
+        let params = json!([electrum_script_hash(script)]);
+        let history: Vec<ElectrumHistory> = self.request("blockchain.scripthash.get_history", params).await?;
+        Ok(history
+            .into_iter()
+            .map(|entry| HistoryEntry { txid: entry.tx_hash, height: confirmed_height(entry.height) })
+            .collect())
+    }
+
+    async fn broadcast(&self, transaction: &Transaction) -> Result<Txid, BitcoinError> {
+        let params = json!([consensus::encode::serialize_hex(transaction)]);
+        let txid: String = self.request("blockchain.transaction.broadcast", params).await?;
+        txid.parse().map_err(client_error)
+    }
+
+    async fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, BitcoinError> {
+        // Estimated in BTC per kvB, -1 without an estimate
+        let per_kvb: f64 = self.request("blockchain.estimatefee", json!([target_blocks])).await?;
+        if per_kvb <= 0.0 {
+            return Err(client_error("No fee estimate"));
+        }
+        // Rounded to whole sats first so float noise doesn't round up a whole sat/kwu
+        Ok(fee_rate_from_sat_per_kvb((per_kvb * 100_000_000.0).round() as u64))
+    }
+
+    async fn tip_height(&self) -> Result<u64, BitcoinError> {
+        let header: ElectrumHeader = self.request("blockchain.headers.subscribe", json!([])).await?;
+        Ok(header.height)
+    }
+}
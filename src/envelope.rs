@@ -0,0 +1,321 @@
+use crate::{
+    errors::{EnvelopeError, SigningError},
+    signer::{SessionId, SigningMessage},
+};
+use bitcoin::{
+    hashes::{sha256, Hash, HashEngine},
+    secp256k1::{schnorr, Keypair, Message, PublicKey, Scalar, Secp256k1, SecretKey, XOnlyPublicKey},
+};
+use frost_secp256k1_tr::{
+    keys::{KeyPackage, PublicKeyPackage, VerifyingShare},
+    Identifier,
+};
+use hex::FromHex;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Version of the envelope format, envelopes of other versions are rejected.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Domain separation tags of the hashes.
+const GROUP_TAG: &[u8] = b"frost-demo/group";
+const ENVELOPE_TAG: &[u8] = b"frost-demo/envelope";
+const IDENTITY_TAG: &[u8] = b"frost-demo/identity";
+
+pub(crate) fn tagged_hash(tag: &[u8], data: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_ref());
+    engine.input(tag_hash.as_ref());
+    engine.input(data);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Tweak turning a participant's share into its identity key, the tagged hash of its verifying share.
+fn identity_tweak(verifying_share: &VerifyingShare) -> Result<(PublicKey, Scalar), SigningError> {
+    let public_key = PublicKey::from_slice(&verifying_share.serialize()?)
+        .map_err(|e| SigningError::InternalError(format!("Invalid verifying share: {e}")))?;
+    let tweak = Scalar::from_be_bytes(tagged_hash(IDENTITY_TAG, &public_key.serialize()))
+        .map_err(|e| SigningError::InternalError(format!("Invalid identity tweak: {e}")))?;
+    Ok((public_key, tweak))
+}
+
+/// Identity key a participant signs envelopes and transport handshakes with. It is derived from the signing share with
+/// a public tweak, so the share itself never signs anything but FROST shares.
+pub fn identity_keypair(key_package: &KeyPackage) -> Result<Keypair, SigningError> {
+    let (_, tweak) = identity_tweak(key_package.verifying_share())?;
+    let secret_key = SecretKey::from_slice(&key_package.signing_share().serialize())
+        .and_then(|share| share.add_tweak(&tweak))
+        .map_err(|e| SigningError::InternalError(format!("Invalid signing share: {e}")))?;
+    Ok(Keypair::from_secret_key(&Secp256k1::new(), &secret_key))
+}
+
+/// Identity public keys of the members of a group.
+pub fn identity_keys(public_key_package: &PublicKeyPackage) -> Result<BTreeMap<Identifier, PublicKey>, SigningError> {
+    let secp = Secp256k1::verification_only();
+    public_key_package
+        .verifying_shares()
+        .iter()
+        .map(|(id, verifying_share)| {
+            let (public_key, tweak) = identity_tweak(verifying_share)?;
+            let identity = public_key
+                .add_exp_tweak(&secp, &tweak)
+                .map_err(|e| SigningError::InternalError(format!("Invalid identity key: {e}")))?;
+            Ok((*id, identity))
+        })
+        .collect()
+}
+
+/// Identifier of a signing group, the hash of its public key package.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GroupId(#[serde_as(as = "Hex")] [u8; 32]);
+
+impl GroupId {
+    pub fn new(public_key_package: &PublicKeyPackage) -> Result<Self, SigningError> {
+        Ok(Self(tagged_hash(GROUP_TAG, &public_key_package.serialize()?)))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for GroupId {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for GroupId {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <[u8; 32]>::from_hex(s).map(Self)
+    }
+}
+
+/// Message between participants, bound to a group and a round and signed by its sender.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub version: u16,
+    pub group_id: GroupId,
+    pub round: u8,
+    /// Counter of the sender, increased with every envelope it seals. Its high 32 bits are a random epoch of the
+    /// sealer, so a restarted sender does not repeat the sequence numbers of its previous run.
+    pub sequence: u64,
+    pub message: SigningMessage,
+    /// BIP-340 signature of the sender over the fields above.
+    pub signature: schnorr::Signature,
+}
+
+/// Signed part of an envelope.
+#[derive(Serialize)]
+struct SignedFields<'a> {
+    version: u16,
+    group_id: &'a GroupId,
+    round: u8,
+    sequence: u64,
+    message: &'a SigningMessage,
+}
+
+impl SignedFields<'_> {
+    fn digest(&self) -> Result<Message, EnvelopeError> {
+        let serialized = serde_json::to_vec(self).map_err(|e| EnvelopeError::Malformed(e.to_string()))?;
+        Ok(Message::from_digest(tagged_hash(ENVELOPE_TAG, &serialized)))
+    }
+}
+
+impl Envelope {
+    /// Participant that sent the envelope.
+    pub fn sender(&self) -> Identifier {
+        self.message.sender()
+    }
+
+    fn digest(&self) -> Result<Message, EnvelopeError> {
+        SignedFields {
+            version: self.version,
+            group_id: &self.group_id,
+            round: self.round,
+            sequence: self.sequence,
+            message: &self.message,
+        }
+        .digest()
+    }
+}
+
+/// Seals the messages a participant sends.
+pub struct Sealer {
+    group_id: GroupId,
+    keypair: Keypair,
+    sequence: AtomicU64,
+}
+
+impl Sealer {
+    /// Creates a sealer signing with the participant's identity key, starting at a random epoch.
+    pub fn new(key_package: &KeyPackage, public_key_package: &PublicKeyPackage) -> Result<Self, SigningError> {
+        let keypair = identity_keypair(key_package)?;
+        let sequence = AtomicU64::new(u64::from(OsRng.next_u32()) << 32);
+        Ok(Self { group_id: GroupId::new(public_key_package)?, keypair, sequence })
+    }
+
+    /// Continues the sequence of an earlier sealer of the participant, e.g. one of a previous process.
+    pub fn with_sequence(self, sequence: u64) -> Self {
+        Self { sequence: AtomicU64::new(sequence), ..self }
+    }
+
+    /// Sequence number of the next sealed envelope.
+    pub fn next_sequence(&self) -> u64 {
+        self.sequence.load(Ordering::SeqCst)
+    }
+
+    /// Wraps a message in a signed envelope with the next sequence number.
+    pub fn seal(&self, message: SigningMessage) -> Result<Envelope, SigningError> {
+        let round = message.round();
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        let fields =
+            SignedFields { version: PROTOCOL_VERSION, group_id: &self.group_id, round, sequence, message: &message };
+        let signature = Secp256k1::new().sign_schnorr_no_aux_rand(&fields.digest()?, &self.keypair);
+        Ok(Envelope { version: PROTOCOL_VERSION, group_id: self.group_id, round, sequence, message, signature })
+    }
+}
+
+/// Number of sequence numbers below the highest one seen that are still accepted out of order.
+const REPLAY_WINDOW: u64 = 64;
+
+/// Number of earlier epochs of a sender whose envelopes stay rejected after it restarted.
+const RETIRED_EPOCHS: usize = 8;
+
+fn epoch(sequence: u64) -> u32 {
+    (sequence >> 32) as u32
+}
+
+/// Sequence numbers seen from a sender: the highest one and a bitmap of the ones just below it.
+struct SequenceWindow {
+    highest: u64,
+    /// Bit `n` is set when `highest - n` was seen.
+    seen: u64,
+    retired: VecDeque<u32>,
+}
+
+impl SequenceWindow {
+    fn new(sequence: u64) -> Self {
+        Self { highest: sequence, seen: 1, retired: VecDeque::new() }
+    }
+
+    /// Records a sequence number, false if it was seen before or is too old to tell.
+    fn insert(&mut self, sequence: u64) -> bool {
+        if epoch(sequence) != epoch(self.highest) {
+            // A restarted sender starts a new epoch, envelopes of its earlier runs are replays
+            if self.retired.contains(&epoch(sequence)) {
+                return false;
+            }
+            if self.retired.len() == RETIRED_EPOCHS {
+                self.retired.pop_front();
+            }
+            self.retired.push_back(epoch(self.highest));
+            self.highest = sequence;
+            self.seen = 1;
+            return true;
+        }
+        if sequence > self.highest {
+            let shift = sequence - self.highest;
+            self.seen = (if shift < REPLAY_WINDOW { self.seen << shift } else { 0 }) | 1;
+            self.highest = sequence;
+            return true;
+        }
+        let offset = self.highest - sequence;
+        if offset >= REPLAY_WINDOW || self.seen & (1 << offset) != 0 {
+            return false;
+        }
+        self.seen |= 1 << offset;
+        true
+    }
+}
+
+/// Checks received envelopes: version, group, round, sender signature and that the same envelope was not seen before.
+pub struct ReplayGuard {
+    group_id: GroupId,
+    senders: BTreeMap<Identifier, XOnlyPublicKey>,
+    /// Sequence numbers seen per session and sender, envelopes outside a session share one window per sender.
+    seen: Mutex<BTreeMap<(Option<SessionId>, Identifier), SequenceWindow>>,
+}
+
+impl ReplayGuard {
+    /// Creates a guard accepting envelopes from the members of the group, signed with their identity keys.
+    pub fn new(public_key_package: &PublicKeyPackage) -> Result<Self, SigningError> {
+        let senders = identity_keys(public_key_package)?
+            .into_iter()
+            .map(|(id, public_key)| (id, public_key.x_only_public_key().0))
+            .collect();
+        Ok(Self { group_id: GroupId::new(public_key_package)?, senders, seen: Mutex::new(BTreeMap::new()) })
+    }
+
+    /// Accepts an envelope of the given round or a later one, and aborts.
+    pub fn open(&self, envelope: &Envelope, current_round: u8) -> Result<(), EnvelopeError> {
+        self.verify(envelope)?;
+        // Aborts are valid in every round
+        if envelope.round < current_round && !matches!(envelope.message, SigningMessage::Abort(..)) {
+            return Err(EnvelopeError::StaleRound { round: envelope.round, current: current_round });
+        }
+
+        let sender = envelope.sender();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        match seen.entry((envelope.message.session_id(), sender)) {
+            Entry::Vacant(entry) => {
+                entry.insert(SequenceWindow::new(envelope.sequence));
+            }
+            Entry::Occupied(mut entry) => {
+                if !entry.get_mut().insert(envelope.sequence) {
+                    return Err(EnvelopeError::Duplicate { sender, sequence: envelope.sequence });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that a group member sealed the envelope, without accepting it yet.
+    pub fn verify(&self, envelope: &Envelope) -> Result<(), EnvelopeError> {
+        if envelope.version != PROTOCOL_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(envelope.version));
+        }
+        if envelope.group_id != self.group_id {
+            return Err(EnvelopeError::ForeignGroup(envelope.group_id.to_string()));
+        }
+        if envelope.round != envelope.message.round() {
+            return Err(EnvelopeError::Malformed(format!(
+                "{} sent in round {}",
+                envelope.message.kind(),
+                envelope.round
+            )));
+        }
+
+        let sender = envelope.sender();
+        let public_key = self.senders.get(&sender).ok_or(EnvelopeError::UnknownSender(sender))?;
+        Secp256k1::verification_only()
+            .verify_schnorr(&envelope.signature, &envelope.digest()?, public_key)
+            .map_err(|_| EnvelopeError::InvalidSignature(sender))
+    }
+
+    /// Forgets the envelopes seen in a session that is over.
+    pub fn forget_session(&self, session_id: SessionId) {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|(session, _), _| *session != Some(session_id));
+    }
+}
@@ -1,3 +1,7 @@
+use crate::{
+    preprocess::CommitmentId,
+    signer::{SessionId, SigningPhase, SigningRound},
+};
 use frost_secp256k1_tr as frost;
 use thiserror::Error;
 
@@ -16,18 +20,51 @@ pub enum SigningError {
     #[error("Internal error: {0}")]
     InternalError(String),
 
-    #[error("Signing ceremony timed out: {0}")]
-    Timeout(String),
+    #[error("Signing ceremony timed out in {round}, missing participants: {missing:?}")]
+    Timeout { round: SigningRound, missing: Vec<frost::Identifier> },
 
     #[error("Not enough signers")]
     NotEnoughSigners,
 
+    #[error("Unknown signer {0:?}")]
+    UnknownSigner(frost::Identifier),
+
     #[error("Received an invalid signature share from participant {0:?}")]
     InvalidSignatureShare(frost::Identifier),
 
     #[error("Invalid state: {0}")]
     InvalidState(String),
 
+    #[error("Invalid transition from {from} to {to}")]
+    InvalidTransition { from: SigningPhase, to: SigningPhase },
+
+    #[error("Signing package message {received} does not match the transaction sighash {expected}")]
+    SighashMismatch { expected: String, received: String },
+
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+
+    #[error("Signing request for session {session_id} was rejected: {reason}")]
+    ApprovalRejected { session_id: SessionId, reason: String },
+
+    #[error("Session {session_id} was aborted by {by:?}: {reason}")]
+    Aborted { session_id: SessionId, by: frost::Identifier, reason: String },
+
+    #[error("Signer already runs the maximum of {0} sessions")]
+    TooManySessions(usize),
+
+    #[error("Nonces for session {0} were already used")]
+    NonceReuse(SessionId),
+
+    #[error("No preprocessed commitments left for participant {0:?}")]
+    NoncePoolExhausted(frost::Identifier),
+
+    #[error("Preprocessed commitment {0} is unknown or was already used")]
+    UnknownCommitment(CommitmentId),
+
+    #[error("State store error: {0}")]
+    Store(#[from] StoreError),
+
     #[error("Transport error: {0}")]
     Transport(#[from] TransportError),
 
@@ -36,6 +73,147 @@ pub enum SigningError {
 
     #[error("Bitcoin error: {0}")]
     Bitcoin(#[from] BitcoinError),
+
+    #[error("Audit log error: {0}")]
+    Audit(#[from] AuditError),
+
+    #[error("Rejected message: {0}")]
+    Envelope(#[from] EnvelopeError),
+
+    #[error("Key data error: {0}")]
+    KeyData(#[from] KeyDataError),
+}
+
+impl SigningError {
+    /// Short machine readable reason, used as the metrics label of failed ceremonies.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SigningError::InternalError(_) => "internal",
+            SigningError::Timeout { .. } => "timeout",
+            SigningError::NotEnoughSigners => "not_enough_signers",
+            SigningError::UnknownSigner(_) => "unknown_signer",
+            SigningError::InvalidSignatureShare(_) => "invalid_share",
+            SigningError::InvalidState(_) => "invalid_state",
+            SigningError::InvalidTransition { .. } => "invalid_transition",
+            SigningError::SighashMismatch { .. } => "sighash_mismatch",
+            SigningError::PolicyViolation(_) => "policy_violation",
+            SigningError::ApprovalRejected { .. } => "approval_rejected",
+            SigningError::Aborted { .. } => "aborted",
+            SigningError::TooManySessions(_) => "too_many_sessions",
+            SigningError::NonceReuse(_) => "nonce_reuse",
+            SigningError::NoncePoolExhausted(_) => "nonce_pool_exhausted",
+            SigningError::UnknownCommitment(_) => "unknown_commitment",
+            SigningError::Store(_) => "store",
+            SigningError::Transport(_) => "transport",
+            SigningError::Frost(_) => "frost",
+            SigningError::Bitcoin(_) => "bitcoin",
+            SigningError::Audit(_) => "audit",
+            SigningError::Envelope(_) => "rejected_message",
+            SigningError::KeyData(_) => "key_data",
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EnvelopeError {
+    #[error("Unsupported protocol version {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("Message for foreign group {0}")]
+    ForeignGroup(String),
+
+    #[error("Message from unknown sender {0:?}")]
+    UnknownSender(frost::Identifier),
+
+    #[error("Invalid signature of sender {0:?}")]
+    InvalidSignature(frost::Identifier),
+
+    #[error("Message of round {round} received in round {current}")]
+    StaleRound { round: u8, current: u8 },
+
+    #[error("Duplicate message {sequence} from {sender:?}")]
+    Duplicate { sender: frost::Identifier, sequence: u64 },
+
+    #[error("Malformed message: {0}")]
+    Malformed(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CodecError {
+    #[error("Input ends inside the {0}")]
+    Truncated(&'static str),
+
+    #[error("{0} trailing bytes after the encoded value")]
+    TrailingBytes(usize),
+
+    #[error("Unknown {kind} tag {tag}")]
+    UnknownVariant { kind: &'static str, tag: u8 },
+
+    #[error("Invalid {0}")]
+    Invalid(String),
+
+    #[error("Unsupported wire format version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Frame of {size} bytes exceeds the maximum of {max}")]
+    Oversized { size: usize, max: usize },
+
+    #[error("Encoding is not canonical")]
+    NonCanonical,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum QrError {
+    #[error("UR error: {0}")]
+    Ur(String),
+
+    #[error("Unsupported UR type {0}")]
+    UnsupportedType(String),
+
+    #[error("UR sequence is incomplete, scan more parts")]
+    Incomplete,
+
+    #[error("QR code error: {0}")]
+    Qr(String),
+
+    #[error("Encoding error: {0}")]
+    Codec(#[from] CodecError),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum StoreError {
+    #[error("State store I/O error: {0}")]
+    Io(String),
+
+    #[error("Corrupt state store entry: {0}")]
+    Corrupt(String),
+
+    #[error("No pending request for session {0}")]
+    UnknownSession(SessionId),
+
+    #[error("Request for session {0} has expired")]
+    Expired(SessionId),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum AuditError {
+    #[error("Audit log I/O error: {0}")]
+    Io(String),
+
+    #[error("Corrupt audit log: {0}")]
+    Corrupt(String),
+
+    #[error("Audit log chain is broken at entry {0}")]
+    BrokenChain(u64),
+
+    #[error("Audit log entry {0} does not match its hash")]
+    InvalidHash(u64),
+
+    #[error("Audit log entry {0} has an invalid signature")]
+    InvalidSignature(u64),
+
+    #[error("Audit log entry {0} is signed by an untrusted key")]
+    UntrustedSigner(u64),
 }
 
 #[derive(Error, Debug, Clone, PartialEq)]
@@ -48,6 +226,36 @@ pub enum TransportError {
 
     #[error("Transport receive error: {0}")]
     Receive(String),
+
+    #[error("Unknown participant {0:?}")]
+    UnknownParticipant(frost::Identifier),
+
+    #[error("Transport connection error: {0}")]
+    Connect(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ApiError {
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Unknown session {0}")]
+    UnknownSession(SessionId),
+
+    #[error("Session {0} has no signed transaction")]
+    NotSigned(SessionId),
+
+    #[error("No chain backend configured")]
+    NoBackend,
+
+    #[error("Signing error: {0}")]
+    Signing(#[from] SigningError),
+
+    #[error("Bitcoin error: {0}")]
+    Bitcoin(#[from] BitcoinError),
+
+    #[error("Key data error: {0}")]
+    KeyData(#[from] KeyDataError),
 }
 
 #[derive(Error, Debug, Clone, PartialEq)]
@@ -0,0 +1,249 @@
+use crate::{
+    errors::{ApiError, BitcoinError, KeyDataError, SigningError},
+    service::{self, ApiToken, SessionState as State, SigningService, SpendRequest, SESSION_EVENTS_CAPACITY},
+    signer::SessionId,
+};
+use futures::Stream;
+use std::{pin::Pin, str::FromStr};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{service::Interceptor, transport::Server, Request, Response, Status};
+use tracing::{info, warn};
+
+pub mod proto {
+    tonic::include_proto!("frost.signer.v1");
+}
+
+use proto::{
+    session_event::Event,
+    signer_server::{Signer, SignerServer},
+    CreateSpendRequest, GetAddressRequest, GetAddressResponse, GetGroupInfoRequest, GroupInfo, SessionEvent,
+    SessionRef, SessionState, SessionStatus, SignPsbtRequest,
+};
+
+/// gRPC service around the [`SigningService`].
+#[derive(Clone)]
+pub struct SignerService {
+    service: SigningService,
+}
+
+impl SignerService {
+    pub fn new(service: SigningService) -> Self {
+        Self { service }
+    }
+}
+
+fn parse_session_id(session: &SessionRef) -> Result<SessionId, Status> {
+    SessionId::from_str(&session.session_id).map_err(|e| Status::invalid_argument(format!("Invalid session id: {e}")))
+}
+
+impl From<service::SessionStatus> for SessionStatus {
+    fn from(status: service::SessionStatus) -> Self {
+        let state = match status.state {
+            State::Running => SessionState::Running,
+            State::Complete => SessionState::Complete,
+            State::Failed => SessionState::Failed,
+        };
+        SessionStatus {
+            session_id: status.session_id.to_string(),
+            state: state.into(),
+            phase: status.phase.map(|phase| phase.to_string()).unwrap_or_default(),
+            signed_transaction: status.signed_transaction.unwrap_or_default(),
+            txid: status.txid.map(|txid| txid.to_string()).unwrap_or_default(),
+            psbt: status.psbt.unwrap_or_default(),
+            error: status.error.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<service::SessionEvent> for SessionEvent {
+    fn from(event: service::SessionEvent) -> Self {
+        let session_id = event.session_id().to_string();
+        let event = match event {
+            service::SessionEvent::Transition(transition) => Event::Transition(proto::Transition {
+                participant: hex::encode(transition.participant_id.serialize()),
+                from: transition.from.to_string(),
+                to: transition.to.to_string(),
+            }),
+            service::SessionEvent::Finished(status) => Event::Finished(status.into()),
+        };
+        SessionEvent { session_id, event: Some(event) }
+    }
+}
+
+#[tonic::async_trait]
+impl Signer for SignerService {
+    async fn get_group_info(&self, _: Request<GetGroupInfoRequest>) -> Result<Response<GroupInfo>, Status> {
+        let info = self.service.group_info()?;
+        Ok(Response::new(GroupInfo {
+            group_id: info.group_id,
+            threshold: info.threshold.into(),
+            total: info.total.into(),
+            verifying_key: info.verifying_key,
+            participants: info.participants,
+        }))
+    }
+
+    async fn get_address(&self, request: Request<GetAddressRequest>) -> Result<Response<GetAddressResponse>, Status> {
+        let address = self.service.address(Some(&request.get_ref().network))?;
+        Ok(Response::new(GetAddressResponse { address: address.to_string() }))
+    }
+
+    async fn create_spend(&self, request: Request<CreateSpendRequest>) -> Result<Response<SessionRef>, Status> {
+        let request = request.into_inner();
+        let session_id = self
+            .service
+            .create_spend(SpendRequest {
+                utxo: request.utxo,
+                to: request.to,
+                amount: request.amount,
+                broadcast: request.broadcast,
+            })
+            .await?;
+        Ok(Response::new(SessionRef { session_id: session_id.to_string() }))
+    }
+
+    async fn sign_psbt(&self, request: Request<SignPsbtRequest>) -> Result<Response<SessionRef>, Status> {
+        let session_id = self.service.sign_psbt(&request.get_ref().psbt)?;
+        Ok(Response::new(SessionRef { session_id: session_id.to_string() }))
+    }
+
+    async fn get_session_status(&self, request: Request<SessionRef>) -> Result<Response<SessionStatus>, Status> {
+        let session_id = parse_session_id(request.get_ref())?;
+        Ok(Response::new(self.service.status(session_id)?.into()))
+    }
+
+    type StreamSessionEventsStream = Pin<Box<dyn Stream<Item = Result<SessionEvent, Status>> + Send>>;
+
+    async fn stream_session_events(
+        &self,
+        request: Request<SessionRef>,
+    ) -> Result<Response<Self::StreamSessionEventsStream>, Status> {
+        let session_id = parse_session_id(request.get_ref())?;
+        let mut events = self.service.subscribe();
+        let status = self.service.status(session_id)?;
+
+        let (sender, receiver) = mpsc::channel(SESSION_EVENTS_CAPACITY);
+        tokio::spawn(async move {
+            if status.state != State::Running {
+                let _ = sender.send(Ok(service::SessionEvent::Finished(status).into())).await;
+                return;
+            }
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.session_id() == session_id => {
+                        let finished = matches!(event, service::SessionEvent::Finished(_));
+                        if sender.send(Ok(event.into())).await.is_err() || finished {
+                            break;
+                        }
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        let message = error.to_string();
+        match error {
+            ApiError::InvalidRequest(_) => Status::invalid_argument(message),
+            ApiError::UnknownSession(_) => Status::not_found(message),
+            ApiError::NotSigned(_) | ApiError::NoBackend => Status::failed_precondition(message),
+            ApiError::Signing(error) => error.into(),
+            ApiError::Bitcoin(error) => error.into(),
+            ApiError::KeyData(error) => error.into(),
+        }
+    }
+}
+
+impl From<SigningError> for Status {
+    fn from(error: SigningError) -> Self {
+        let message = error.to_string();
+        match error {
+            SigningError::Timeout { .. } => Status::deadline_exceeded(message),
+            SigningError::NotEnoughSigners => Status::unavailable(message),
+            SigningError::PolicyViolation(_) | SigningError::ApprovalRejected { .. } => {
+                Status::permission_denied(message)
+            }
+            SigningError::Aborted { .. } => Status::aborted(message),
+            SigningError::TooManySessions(_) | SigningError::NoncePoolExhausted(_) => {
+                Status::resource_exhausted(message)
+            }
+            SigningError::SighashMismatch { .. }
+            | SigningError::InvalidState(_)
+            | SigningError::InvalidTransition { .. }
+            | SigningError::NonceReuse(_) => Status::failed_precondition(message),
+            SigningError::Bitcoin(error) => error.into(),
+            _ => Status::internal(message),
+        }
+    }
+}
+
+impl From<BitcoinError> for Status {
+    fn from(error: BitcoinError) -> Self {
+        let message = error.to_string();
+        match error {
+            BitcoinError::Address(_) | BitcoinError::Utxo(_) | BitcoinError::Spend(_) => {
+                Status::invalid_argument(message)
+            }
+            BitcoinError::Client(_) => Status::unavailable(message),
+            BitcoinError::Sighash(_) => Status::internal(message),
+        }
+    }
+}
+
+impl From<KeyDataError> for Status {
+    fn from(error: KeyDataError) -> Self {
+        Status::internal(error.to_string())
+    }
+}
+
+/// Checks the bearer token of every call, without a token every call is let through.
+#[derive(Clone)]
+pub struct BearerAuth {
+    token: Option<ApiToken>,
+}
+
+impl BearerAuth {
+    pub fn new(token: Option<ApiToken>) -> Self {
+        Self { token }
+    }
+}
+
+impl Interceptor for BearerAuth {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(token) = &self.token else {
+            return Ok(request);
+        };
+        let authorization = request.metadata().get("authorization").and_then(|value| value.to_str().ok());
+        if !token.authorizes(authorization) {
+            return Err(Status::unauthenticated("Missing or invalid bearer token"));
+        }
+        Ok(request)
+    }
+}
+
+/// Serves the signer API on the listener, every call needs the bearer token unless `auth` has none.
+pub async fn serve(
+    listener: TcpListener,
+    service: SignerService,
+    auth: BearerAuth,
+) -> Result<(), tonic::transport::Error> {
+    if let Ok(addr) = listener.local_addr() {
+        info!(%addr, "Signer API listening.");
+    }
+    if auth.token.is_none() {
+        warn!("Signer API serves unauthenticated calls.");
+    }
+    Server::builder()
+        .add_service(SignerServer::with_interceptor(service, auth))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
@@ -23,31 +23,55 @@ pub struct KeyData {
 impl KeyData {
     /// Derives group address
     pub fn address(&self, network: Network) -> Result<Address, KeyDataError> {
-        let secp_engine = Secp256k1::new();
-
-        // g the FROST group verifying key
-        let group_verifying_key = self.public.verifying_key();
-        let mut affine_point = group_verifying_key.to_element().to_affine();
-
-        // for a taproot keypath spend, the internal public key must have an even
-        // y coordinate. If it's odd, we must use its negation.
-        if affine_point.y_is_odd().into() {
-            affine_point = -affine_point;
-        }
-
-        // serialize the potential internal key to a compressed public key format
-        let pk_bytes = affine_point.to_encoded_point(true);
-        let bitcoin_public_key =
-            PublicKey::from_slice(pk_bytes.as_bytes()).map_err(|e| KeyDataError::PublicKey(e.to_string()))?;
-
-        // get the x only public key from the inner secp256k1 key
-        let (x_only_pk, _parity) = bitcoin_public_key.inner.x_only_public_key();
-        let untweaked_pk = UntweakedPublicKey::from(x_only_pk);
-
-        // create the P2TR address from the final, tweaked internal key.
-        let address = Address::p2tr(&secp_engine, untweaked_pk, None, network);
-        Ok(address)
+        group_address(&self.public, network)
     }
+
+    /// Key share of one participant, without the shares of the others.
+    pub fn participant(&self, id: Identifier) -> Option<ParticipantKey> {
+        let key_package = self.key_packages.get(&id)?.clone();
+        Some(ParticipantKey { key_package, public: self.public.clone() })
+    }
+}
+
+/// Key share a single signer holds, with the public keys of its group.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParticipantKey {
+    pub key_package: KeyPackage,
+    pub public: PublicKeyPackage,
+}
+
+/// Derives the address of the group with the given public key package.
+pub fn group_address(public: &PublicKeyPackage, network: Network) -> Result<Address, KeyDataError> {
+    let secp_engine = Secp256k1::new();
+
+    // g the FROST group verifying key
+    let group_verifying_key = public.verifying_key();
+    let mut affine_point = group_verifying_key.to_element().to_affine();
+
+    // for a taproot keypath spend, the internal public key must have an even
+    // y coordinate. If it's odd, we must use its negation.
+    if affine_point.y_is_odd().into() {
+        affine_point = -affine_point;
+    }
+
+    // serialize the potential internal key to a compressed public key format
+    let pk_bytes = affine_point.to_encoded_point(true);
+    let bitcoin_public_key =
+        PublicKey::from_slice(pk_bytes.as_bytes()).map_err(|e| KeyDataError::PublicKey(e.to_string()))?;
+
+    // get the x only public key from the inner secp256k1 key
+    let (x_only_pk, _parity) = bitcoin_public_key.inner.x_only_public_key();
+    let untweaked_pk = UntweakedPublicKey::from(x_only_pk);
+
+    // create the P2TR address from the final, tweaked internal key.
+    let address = Address::p2tr(&secp_engine, untweaked_pk, None, network);
+    Ok(address)
+}
+
+/// Index the participant's identifier was derived from, among the indices of the group.
+pub fn participant_index(public: &PublicKeyPackage, id: Identifier) -> Option<u16> {
+    let total = u16::try_from(public.verifying_shares().len()).ok()?;
+    (1..=total).find(|index| Identifier::try_from(*index).is_ok_and(|candidate| candidate == id))
 }
 
 /// Loads and parses the FROST key data from a JSON file.
@@ -55,3 +79,9 @@ pub async fn load_key_data(path: &Path) -> Result<KeyData, KeyDataError> {
     let keys_json = tokio::fs::read_to_string(path).await.map_err(|e| KeyDataError::File(e.to_string()))?;
     serde_json::from_str(&keys_json).map_err(|e| KeyDataError::JsonParse(e.to_string()))
 }
+
+/// Loads and parses the key share of a single participant from a JSON file.
+pub async fn load_participant_key(path: &Path) -> Result<ParticipantKey, KeyDataError> {
+    let key_json = tokio::fs::read_to_string(path).await.map_err(|e| KeyDataError::File(e.to_string()))?;
+    serde_json::from_str(&key_json).map_err(|e| KeyDataError::JsonParse(e.to_string()))
+}
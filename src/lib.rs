@@ -1,22 +1,46 @@
+pub mod approval;
+pub mod audit;
 pub mod bitcoin;
+pub mod chain;
+pub mod codec;
+pub mod electrum;
+pub mod envelope;
 pub mod errors;
+pub mod grpc;
 pub mod keys;
+pub mod metrics;
+pub mod offline;
+pub mod policy;
+pub mod preprocess;
+pub mod qr;
+pub mod relay;
+pub mod rest;
+pub mod service;
 pub mod signer;
+pub mod simulation;
+pub mod store;
 pub mod transport;
+pub mod websocket;
 
 use crate::{
-    bitcoin::{broadcast_transaction, create_rpc_client, create_unsigned_transaction, fetch_utxo_to_spend, parse_utxo},
+    audit::AuditEvent,
+    bitcoin::{create_unsigned_transaction_with_fee, parse_utxo},
+    chain::{estimate_spend_fee, ChainBackend, FALLBACK_FEE},
+    envelope::GroupId,
     keys::load_key_data,
-    signer::run_signing_ceremony,
+    offline::OfflineRequest,
+    relay::Topic,
+    signer::{run_remote_session, run_signing_ceremony_with_config, CeremonyConfig, SessionId, SigningRequest},
+    websocket::{RelayIdentity, WebSocketTransport},
 };
-use ::bitcoin::{Address, Amount, Network, Txid};
+use ::bitcoin::{secp256k1::SecretKey, Address, Amount, FeeRate, Network, Transaction, TxOut, Txid};
 use anyhow::{Context, Error};
 use frost::keys::{generate_with_dealer, IdentifierList, KeyPackage};
 use frost_secp256k1_tr as frost;
 use keys::KeyData;
 use rand::rngs::OsRng;
 use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
-use std::{collections::BTreeMap, path::Path, str::FromStr};
+use std::{collections::BTreeMap, path::Path, str::FromStr, sync::Arc};
 use tokio::{fs::File, io::AsyncWriteExt};
 use tracing::info;
 
@@ -37,40 +61,86 @@ pub struct SpendArgs<'a> {
     /// Bitcoin network to use.
     pub network: Network,
 
-    /// URL of the Bitcoin Core RPC server.
-    pub rpc_url: &'a str,
+    /// Backend the spent output is fetched from and the signed transaction broadcast to.
+    pub backend: &'a dyn ChainBackend,
 
-    /// RPC username for authentication (optional).
-    pub rpc_user: Option<&'a str>,
+    /// Signing ceremony timeouts and participant selection.
+    pub ceremony: CeremonyConfig,
 
-    /// RPC password for authentication (optional).
-    pub rpc_pass: Option<&'a str>,
+    /// Pays the backend's fee estimate, capped by the policy's max fee rate, instead of the default fee.
+    pub estimate_fee: bool,
 }
 
 /// Constructs a spend transaction, signs it in MPC, and broadcasts it to the network.
 pub async fn spend(args: SpendArgs<'_>) -> Result<Txid, Error> {
-    let rpc_client = create_rpc_client(args.rpc_url, args.rpc_user, args.rpc_pass)?;
+    let (key_data, unsigned_transaction, utxo_to_spend) = prepare_spend(&args).await?;
+
+    info!("Starting FROST signing ceremony...");
+    let signed_tx =
+        run_signing_ceremony_with_config(key_data, unsigned_transaction, &[utxo_to_spend], &args.ceremony).await?;
+
+    info!("Broadcasting signed transaction to the network...");
+    let final_txid = args.backend.broadcast(&signed_tx).await?;
+    if let Some(audit) = &args.ceremony.audit {
+        audit.record(AuditEvent::Broadcast { txid: final_txid })?;
+    }
+
+    Ok(final_txid)
+}
+
+/// Constructs a spend transaction, signs it with the signers connected to the relay at `relay_url`, and broadcasts it
+/// to the network. The coordinator connects with `coordinator_key`, the key the relay and the signers expect.
+pub async fn spend_over_relay(args: SpendArgs<'_>, relay_url: &str, coordinator_key: SecretKey) -> Result<Txid, Error> {
+    let (key_data, unsigned_transaction, utxo_to_spend) = prepare_spend(&args).await?;
+    let request = OfflineRequest {
+        session_id: SessionId::random(),
+        request: SigningRequest::new(unsigned_transaction, &[utxo_to_spend]),
+    };
+
+    info!("Connecting to the relay at {relay_url}...");
+    let topic = Topic { group_id: GroupId::new(&key_data.public)?, session_id: None };
+    let identity = RelayIdentity::coordinator(coordinator_key, &key_data.public)?;
+    let transport = Arc::new(WebSocketTransport::connect(relay_url, topic, identity).await?);
+    let signed_tx = run_remote_session(&key_data, transport, &request, &args.ceremony).await?;
+
+    info!("Broadcasting signed transaction to the network...");
+    let final_txid = args.backend.broadcast(&signed_tx).await?;
+    if let Some(audit) = &args.ceremony.audit {
+        audit.record(AuditEvent::Broadcast { txid: final_txid })?;
+    }
+
+    Ok(final_txid)
+}
+
+/// Builds the signing request of a spend for air-gapped signers, in a new session.
+pub async fn create_offline_request(args: SpendArgs<'_>) -> Result<OfflineRequest, Error> {
+    let (_, transaction, utxo_to_spend) = prepare_spend(&args).await?;
+    Ok(OfflineRequest { session_id: SessionId::random(), request: SigningRequest::new(transaction, &[utxo_to_spend]) })
+}
+
+/// Builds the unsigned spend transaction, returns it with the key data and the output it spends.
+async fn prepare_spend(args: &SpendArgs<'_>) -> Result<(KeyData, Transaction, TxOut), Error> {
     let utxo = parse_utxo(args.utxo)?;
     let key_data = load_key_data(args.keys_path).await?;
     let destination_address = Address::from_str(args.to)?.require_network(args.network)?;
     let change_address = key_data.address(args.network).context("Failed to derive change address")?;
 
-    let utxo_to_spend = fetch_utxo_to_spend(&rpc_client, &utxo)?;
-    let unsigned_transaction = create_unsigned_transaction(
+    let utxo_to_spend = args.backend.get_tx_out(&utxo).await?;
+    let fee = if args.estimate_fee {
+        let max_fee_rate = args.ceremony.policy.as_ref().and_then(|policy| policy.max_fee_rate);
+        estimate_spend_fee(args.backend, max_fee_rate.and_then(FeeRate::from_sat_per_vb)).await
+    } else {
+        FALLBACK_FEE
+    };
+    let unsigned_transaction = create_unsigned_transaction_with_fee(
         utxo,
         &utxo_to_spend,
         destination_address,
         Amount::from_sat(args.amount),
         change_address,
+        fee,
     )?;
-
-    info!("Starting FROST signing ceremony...");
-    let signed_tx = run_signing_ceremony(key_data, unsigned_transaction, &[utxo_to_spend]).await?;
-
-    info!("Broadcasting signed transaction to the network...");
-    let final_txid = broadcast_transaction(&rpc_client, &signed_tx)?;
-
-    Ok(final_txid)
+    Ok((key_data, unsigned_transaction, utxo_to_spend))
 }
 
 pub async fn generate_keys(threshold: u16, total: u16, output: &Path, seed: Option<[u8; 32]>) -> Result<(), Error> {
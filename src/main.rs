@@ -1,19 +1,59 @@
 use anyhow::{Context, Error};
-use bitcoin::Network;
-use clap::{Parser, Subcommand, ValueEnum};
-use frost_demo::{generate_keys, keys::KeyData, spend, SpendArgs};
-use std::path::PathBuf;
-use tracing::info;
+use bitcoin::{
+    consensus::encode::serialize_hex,
+    secp256k1::{PublicKey, Secp256k1, XOnlyPublicKey},
+    Amount, Network,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use frost_demo::{
+    approval::{ApprovalDecision, ApprovalQueue, FileApprovalQueue},
+    audit::{load_entries, load_or_create_key, verify_log, AuditLog},
+    bitcoin::create_rpc_client,
+    chain::{ChainBackend, CoreRpcBackend, EsploraBackend},
+    create_offline_request,
+    electrum::ElectrumBackend,
+    envelope::{Envelope, GroupId},
+    generate_keys,
+    grpc::{self, BearerAuth, SignerService},
+    keys::{participant_index, KeyData, ParticipantKey},
+    metrics,
+    offline::{
+        aggregate_shares, collect_commitments, read_message, write_message, FileTransport, OfflineRequest,
+        OfflineSigner,
+    },
+    policy::SignerPolicy,
+    qr::{render, QrPayload, UrDecoder, UrEncoder, DEFAULT_FRAGMENT_LEN},
+    relay::Topic,
+    rest,
+    service::{ApiToken, SigningService},
+    signer::{CeremonyConfig, SessionId, SignerSelection},
+    spend, spend_over_relay,
+    store::FileJournal,
+    transport::Transport,
+    websocket::{run_remote_signer, RelayIdentity, WebSocketTransport},
+    SpendArgs,
+};
+use frost_secp256k1_tr::Identifier;
+use std::{
+    collections::BTreeSet,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
 use tracing_subscriber::{filter::LevelFilter, EnvFilter};
 
-/// The default public RPC endpoint for the Bitcoin (https://signet-rpc.publicnode.com, https://bitcoin-testnet-rpc.publicnode.com)
-const DEFAULT_BITCOIN_CORE_RPC_URL: &str = "https://bitcoin-testnet-rpc.publicnode.com";
-
 #[derive(Parser)]
 #[command(name = "frost-demo", about = "FROST BTC Taproot threshold signing demo")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Address to serve Prometheus metrics on at `/metrics` (e.g. 127.0.0.1:9464), not served if not given.
+    #[arg(long, global = true)]
+    metrics_addr: Option<SocketAddr>,
 }
 
 #[derive(Subcommand)]
@@ -44,6 +84,20 @@ enum Commands {
         network: CliNetwork,
     },
 
+    /// Lists the history, UTXOs and balance of the group address.
+    Scan {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// Bitcoin network of the address.
+        #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+        network: CliNetwork,
+
+        #[command(flatten)]
+        chain: ChainArgs,
+    },
+
     /// Spend from a threshold address
     Spend {
         /// JSON file containing threshold key shares.
@@ -66,17 +120,583 @@ enum Commands {
         #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
         network: CliNetwork,
 
-        /// URL of the Bitcoin Core RPC server.
-        #[arg(long, default_value = DEFAULT_BITCOIN_CORE_RPC_URL)]
-        rpc_url: String,
+        #[command(flatten)]
+        chain: ChainArgs,
+
+        /// Pays the backend's fee estimate, capped by the policy's max fee rate and 200 sat/vB, instead of the default fee.
+        #[arg(long)]
+        estimate_fee: bool,
+
+        /// Comma separated participant indices to sign with (e.g. 1,3), defaults to the first responsive ones.
+        #[arg(long, value_delimiter = ',')]
+        signers: Vec<u16>,
+
+        /// Seconds to wait for nonce commitments (round 1).
+        #[arg(long, default_value_t = 60)]
+        round1_timeout: u64,
+
+        /// Seconds to wait for signature shares (round 2).
+        #[arg(long, default_value_t = 60)]
+        round2_timeout: u64,
+
+        /// Overall deadline for the signing ceremony in seconds.
+        #[arg(long, default_value_t = 120)]
+        ceremony_timeout: u64,
+
+        /// Wait for every selected participant in each round instead of continuing once the threshold is met.
+        #[arg(long)]
+        wait_for_all: bool,
+
+        #[command(flatten)]
+        signer_config: SignerConfigArgs,
+
+        /// WebSocket URL of a relay (e.g. ws://relay.example:9000) to sign with the signers running behind it
+        /// (`signer run`) instead of local ones.
+        #[arg(long)]
+        relay: Option<String>,
+
+        /// Hex encoded key the coordinator connects to the relay with, generated if missing.
+        #[arg(long, default_value = "coordinator.key")]
+        coordinator_key: PathBuf,
+    },
+
+    /// Prints the public key of the coordinator's relay key, the relay and the signers are configured with it.
+    CoordinatorKey {
+        /// Hex encoded key of the coordinator, generated if missing.
+        #[arg(long, default_value = "coordinator.key")]
+        key: PathBuf,
+    },
+
+    /// Runs a signer behind a relay and decides on signing requests awaiting approval.
+    Signer {
+        #[command(subcommand)]
+        command: SignerCommands,
+    },
+
+    /// Audit log commands.
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommands,
+    },
+
+    /// Signs as an air-gapped participant, messages are exchanged as files.
+    Sign {
+        #[command(subcommand)]
+        command: SignCommands,
+    },
+
+    /// Serves the gRPC signer API for backend services.
+    Grpc {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        listen: SocketAddr,
+
+        /// Bitcoin network of the spends.
+        #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+        network: CliNetwork,
+
+        #[command(flatten)]
+        chain: ChainArgs,
+
+        #[command(flatten)]
+        signer_config: SignerConfigArgs,
+
+        /// Pays the backend's fee estimate, capped by the policy's max fee rate and 200 sat/vB, instead of the default fee.
+        #[arg(long)]
+        estimate_fee: bool,
+
+        /// Bearer token required from API clients, at least 16 characters.
+        #[arg(long, env = "FROST_API_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+
+        /// Serves the API without a token, only for a listener no one else can reach.
+        #[arg(long, conflicts_with = "token")]
+        insecure: bool,
+    },
+
+    /// Serves the REST API, its OpenAPI document is served at /openapi.json.
+    Serve {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+
+        /// Bitcoin network of the spends.
+        #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+        network: CliNetwork,
+
+        #[command(flatten)]
+        chain: ChainArgs,
+
+        #[command(flatten)]
+        signer_config: SignerConfigArgs,
+
+        /// Pays the backend's fee estimate, capped by the policy's max fee rate and 200 sat/vB, instead of the default fee.
+        #[arg(long)]
+        estimate_fee: bool,
+
+        /// Bearer token required from API clients, at least 16 characters.
+        #[arg(long, env = "FROST_API_TOKEN", hide_env_values = true)]
+        token: String,
+    },
+
+    /// Moves requests, signing packages and messages across an air gap as animated QR codes.
+    Qr {
+        #[command(subcommand)]
+        command: QrCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum QrCommands {
+    /// Shows a file as a sequence of QR codes, looping until interrupted if it takes more than one.
+    Show {
+        /// What the file contains.
+        #[arg(long, value_enum)]
+        kind: QrKind,
+
+        /// File to show, JSON for requests and packages.
+        #[arg(long)]
+        file: PathBuf,
+
+        /// Maximum bytes per QR code.
+        #[arg(long, default_value_t = DEFAULT_FRAGMENT_LEN)]
+        fragment_len: usize,
+
+        /// Milliseconds each QR code is shown for.
+        #[arg(long, default_value_t = 500)]
+        interval_ms: u64,
+
+        /// Prints the UR parts as text instead of QR codes.
+        #[arg(long)]
+        text: bool,
+    },
+
+    /// Decodes scanned UR parts, one per line, and writes the file they carry.
+    Decode {
+        /// File with the scanned parts, read from stdin if not given.
+        #[arg(long)]
+        input: Option<PathBuf>,
+
+        /// Output file.
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+/// Chain backend UTXOs and prevouts are fetched from and transactions broadcast to.
+#[derive(Args)]
+struct ChainArgs {
+    /// Kind of chain backend.
+    #[arg(long, value_enum, default_value_t = Backend::Core)]
+    backend: Backend,
+
+    /// URL of the backend, a public endpoint of the network by default.
+    #[arg(long, alias = "rpc-url")]
+    url: Option<String>,
+
+    /// RPC username for authentication (optional).
+    #[arg(long)]
+    rpc_user: Option<String>,
+
+    /// RPC password for authentication (optional).
+    #[arg(long)]
+    rpc_pass: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Backend {
+    /// Bitcoin Core JSON-RPC.
+    Core,
+
+    /// Esplora REST API.
+    Esplora,
+
+    /// Electrum server over TCP.
+    Electrum,
+}
+
+impl ChainArgs {
+    /// Connects to the backend at `--url`, a public endpoint of the network by default.
+    fn connect(&self, network: CliNetwork) -> Result<Arc<dyn ChainBackend>, Error> {
+        let url = match &self.url {
+            Some(url) => url.as_str(),
+            None => default_url(self.backend, network)
+                .with_context(|| format!("No public {:?} endpoint for {network:?}, pass --url", self.backend))?,
+        };
+        Ok(match self.backend {
+            Backend::Core => {
+                let client = create_rpc_client(url, self.rpc_user.as_deref(), self.rpc_pass.as_deref())?;
+                Arc::new(CoreRpcBackend::new(client))
+            }
+            Backend::Esplora => Arc::new(EsploraBackend::new(url)),
+            Backend::Electrum => Arc::new(ElectrumBackend::new(url)?),
+        })
+    }
+}
+
+/// Public endpoint of a backend on the network.
+fn default_url(backend: Backend, network: CliNetwork) -> Option<&'static str> {
+    match (backend, network) {
+        (Backend::Core, CliNetwork::Bitcoin) => Some("https://bitcoin-rpc.publicnode.com"),
+        (Backend::Core, CliNetwork::Testnet) => Some("https://bitcoin-testnet-rpc.publicnode.com"),
+        (Backend::Core, CliNetwork::Signet) => Some("https://signet-rpc.publicnode.com"),
+        (Backend::Esplora, CliNetwork::Bitcoin) => Some("https://blockstream.info/api"),
+        (Backend::Esplora, CliNetwork::Testnet) => Some("https://blockstream.info/testnet/api"),
+        (Backend::Esplora, CliNetwork::Testnet4) => Some("https://mempool.space/testnet4/api"),
+        (Backend::Esplora, CliNetwork::Signet) => Some("https://mempool.space/signet/api"),
+        (Backend::Electrum, CliNetwork::Bitcoin) => Some("tcp://electrum.blockstream.info:50001"),
+        (Backend::Electrum, CliNetwork::Testnet) => Some("tcp://electrum.blockstream.info:60001"),
+        _ => None,
+    }
+}
+
+/// Content of a file shown as QR codes.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum QrKind {
+    /// Signing request (`OfflineRequest`).
+    Request,
+
+    /// Signing package from the coordinator.
+    Package,
+
+    /// Message file of a signer.
+    Message,
+}
+
+#[derive(Subcommand)]
+enum SignCommands {
+    /// Coordinator: builds the signing request of a spend for the air-gapped signers.
+    Request {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// UTXO to spend from (txid:vout).
+        #[arg(long)]
+        utxo: String,
+
+        /// Destination address to send funds to.
+        #[arg(long)]
+        to: String,
+
+        /// Amount in satoshis to send.
+        #[arg(long)]
+        amount: u64,
+
+        /// Bitcoin network to use.
+        #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+        network: CliNetwork,
+
+        #[command(flatten)]
+        chain: ChainArgs,
+
+        /// Pays the backend's fee estimate, capped by the policy's max fee rate and 200 sat/vB, instead of the default fee.
+        #[arg(long)]
+        estimate_fee: bool,
+
+        /// Output file for the signing request.
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Generates the nonces for a signing request, writes the commitment to the outbox to carry to the coordinator.
+    Round1 {
+        #[command(flatten)]
+        signer: OfflineSignerArgs,
+
+        /// File the encrypted nonces are kept in until round 2.
+        #[arg(long, default_value = "nonces.enc")]
+        nonces: PathBuf,
+
+        /// Signing request (JSON) from the coordinator.
+        #[arg(long)]
+        request: PathBuf,
+
+        /// Directory the commitment is written to.
+        #[arg(long)]
+        outbox: PathBuf,
+    },
+
+    /// Coordinator: collects the commitments carried into the inbox and writes the signing package.
+    Package {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// Signing request (JSON) sent to the signers.
+        #[arg(long)]
+        request: PathBuf,
+
+        /// Directory the signers' commitments were carried to.
+        #[arg(long)]
+        inbox: PathBuf,
+
+        /// Output file for the signing package.
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Signs the signing package from the coordinator, writes the signature share to the outbox to carry back.
+    Round2 {
+        #[command(flatten)]
+        signer: OfflineSignerArgs,
+
+        /// File the encrypted nonces were kept in since round 1.
+        #[arg(long, default_value = "nonces.enc")]
+        nonces: PathBuf,
+
+        /// Signing request (JSON) from the coordinator.
+        #[arg(long)]
+        request: PathBuf,
+
+        /// Signing package (JSON) from the coordinator.
+        #[arg(long)]
+        package: PathBuf,
+
+        /// Directory the signature share is written to.
+        #[arg(long)]
+        outbox: PathBuf,
+    },
+
+    /// Coordinator: aggregates the signature shares carried into the inbox and writes the signed transaction.
+    Aggregate {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// Signing request (JSON) sent to the signers.
+        #[arg(long)]
+        request: PathBuf,
+
+        /// Signing package (JSON) sent to the signers.
+        #[arg(long)]
+        package: PathBuf,
+
+        /// Directory the signers' shares were carried to.
+        #[arg(long)]
+        inbox: PathBuf,
+
+        /// Output file for the signed transaction (hex).
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Broadcasts the signed transaction through the chain backend.
+        #[arg(long)]
+        broadcast: bool,
+
+        /// Bitcoin network the transaction is broadcast on.
+        #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+        network: CliNetwork,
+
+        #[command(flatten)]
+        chain: ChainArgs,
+    },
+}
+
+/// Journals, checks and audit log of the signers in a ceremony.
+#[derive(Args)]
+struct SignerConfigArgs {
+    /// Directory for the signers' session journals, used to recover from crashes.
+    #[arg(long)]
+    state_dir: Option<PathBuf>,
+
+    /// JSON file with the transaction policy the signers enforce before releasing their shares.
+    #[arg(long)]
+    policy: Option<PathBuf>,
+
+    /// Queue directory where signing requests wait for operator approval (see `signer approve`).
+    #[arg(long)]
+    approval_dir: Option<PathBuf>,
+
+    /// Seconds an operator has to approve a signing request.
+    #[arg(long, default_value_t = 600)]
+    approval_timeout: u64,
+
+    /// Append-only audit log file the ceremony is recorded to.
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+
+    /// Hex encoded key the audit log entries are signed with, generated if missing (defaults to <audit-log>.key).
+    #[arg(long)]
+    audit_key: Option<PathBuf>,
+}
+
+impl SignerConfigArgs {
+    fn ceremony_config(&self, network: Network) -> Result<CeremonyConfig, Error> {
+        let policy = match &self.policy {
+            Some(path) => Some(read_json::<SignerPolicy>(path).context("Failed to read policy file")?),
+            None => None,
+        };
+        let audit = match &self.audit_log {
+            Some(path) => {
+                let key_path = self.audit_key.clone().unwrap_or_else(|| path.with_extension("key"));
+                let audit = AuditLog::open(path, load_or_create_key(key_path)?)?;
+                info!("Recording ceremonies to {path:?}, entries signed by {}", audit.public_key());
+                Some(Arc::new(audit))
+            }
+            None => None,
+        };
+        Ok(CeremonyConfig {
+            state_dir: self.state_dir.clone(),
+            policy,
+            network,
+            approval_dir: self.approval_dir.clone(),
+            approval_timeout: Duration::from_secs(self.approval_timeout),
+            audit,
+            ..Default::default()
+        })
+    }
+}
+
+/// Key share, state and checks of an air-gapped signer.
+#[derive(Args)]
+struct OfflineSignerArgs {
+    /// JSON file with the participant's key share, as written by `signer export`.
+    #[arg(long)]
+    key: PathBuf,
 
-        /// RPC username for authentication (optional).
+    /// Directory the signer journals its sessions and signed amounts to, a session is never signed twice.
+    #[arg(long, default_value = "signer-state")]
+    state_dir: PathBuf,
+
+    /// JSON file with the transaction policy checked before signing.
+    #[arg(long)]
+    policy: Option<PathBuf>,
+
+    /// Bitcoin network the destinations allowed by the policy belong to.
+    #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+    network: CliNetwork,
+
+    /// Queue directory where signing requests wait for operator approval (see `signer approve`).
+    #[arg(long)]
+    approval_dir: Option<PathBuf>,
+
+    /// Seconds an operator has to approve a signing request.
+    #[arg(long, default_value_t = 600)]
+    approval_timeout: u64,
+}
+
+impl OfflineSignerArgs {
+    fn participant_key(&self) -> Result<ParticipantKey, Error> {
+        read_json(&self.key).context("Failed to read key share file")
+    }
+
+    fn signer(&self) -> Result<(OfflineSigner, Identifier), Error> {
+        let key = self.participant_key()?;
+        let id = *key.key_package.identifier();
+        let store = Arc::new(FileJournal::open(&self.state_dir)?);
+        let mut signer = OfflineSigner::new(key.key_package, key.public, store)?;
+        if let Some(path) = &self.policy {
+            let policy = read_json::<SignerPolicy>(path).context("Failed to read policy file")?;
+            signer = signer.with_policy(policy, self.network.into())?;
+        }
+        if let Some(approval_dir) = &self.approval_dir {
+            let approvals = Arc::new(FileApprovalQueue::open(approval_dir)?);
+            signer = signer.with_approval_queue(approvals, Duration::from_secs(self.approval_timeout));
+        }
+        Ok((signer, id))
+    }
+}
+
+#[derive(Subcommand)]
+enum AuditCommands {
+    /// Checks the hash chain and signatures of an audit log.
+    Verify {
+        /// Audit log file.
         #[arg(long)]
-        rpc_user: Option<String>,
+        log: PathBuf,
+
+        /// X-only public keys (hex) trusted to write the log. Without them the log is only checked for integrity and
+        /// reported as unauthenticated.
+        #[arg(long, value_delimiter = ',')]
+        trusted_keys: Vec<XOnlyPublicKey>,
+    },
+}
 
-        /// RPC password for authentication (optional).
+#[derive(Subcommand)]
+enum SignerCommands {
+    /// Signs for the coordinator over a relay, with the checks of the air-gapped signer, until interrupted.
+    Run {
+        #[command(flatten)]
+        signer: OfflineSignerArgs,
+
+        /// WebSocket URL of the relay (e.g. ws://relay.example:9000).
+        #[arg(long)]
+        relay: String,
+
+        /// Public key of the coordinator (hex), as printed by `coordinator-key`.
+        #[arg(long)]
+        coordinator_key: PublicKey,
+
+        /// Directory the encrypted nonces of the sessions are kept in between the rounds.
+        #[arg(long, default_value = "signer-nonces")]
+        nonce_dir: PathBuf,
+    },
+
+    /// Writes the key share of one participant to its own file, for a signer that should not hold the others.
+    Export {
+        /// JSON file containing threshold key shares.
+        #[arg(long)]
+        keys: PathBuf,
+
+        /// Identifier of the exported participant.
+        #[arg(long)]
+        participant: u16,
+
+        /// Output file for the participant's key share (JSON).
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Lists the signing requests awaiting approval.
+    Pending {
+        /// Queue directory of the signer.
         #[arg(long)]
-        rpc_pass: Option<String>,
+        approval_dir: PathBuf,
+
+        /// Bitcoin network used to display the output addresses.
+        #[arg(long, value_enum, default_value_t = CliNetwork::Signet)]
+        network: CliNetwork,
+    },
+
+    /// Approves a signing request.
+    Approve {
+        /// Session of the signing request.
+        session: SessionId,
+
+        /// Identifier of the participant whose request is decided.
+        #[arg(long)]
+        participant: u16,
+
+        /// Queue directory of the signer.
+        #[arg(long)]
+        approval_dir: PathBuf,
+    },
+
+    /// Rejects a signing request.
+    Reject {
+        /// Session of the signing request.
+        session: SessionId,
+
+        /// Identifier of the participant whose request is decided.
+        #[arg(long)]
+        participant: u16,
+
+        /// Queue directory of the signer.
+        #[arg(long)]
+        approval_dir: PathBuf,
+
+        /// Reason given for the rejection.
+        #[arg(long, default_value = "rejected by operator")]
+        reason: String,
     },
 }
 
@@ -111,21 +731,23 @@ impl From<CliNetwork> for Network {
     }
 }
 
-// Setup metrics recorder to export metrics to Prometheus
-fn setup_metrics_recorder() {
-    let _ =
-        metrics_exporter_prometheus::PrometheusBuilder::new().install_recorder().context("failed to install recorder");
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let filter = EnvFilter::builder().with_default_directive(LevelFilter::INFO.into()).from_env_lossy();
     tracing_subscriber::fmt().with_env_filter(filter).with_target(false).without_time().init();
 
-    setup_metrics_recorder();
-
     let cli = Cli::parse();
 
+    // Setup metrics recorder to export metrics to Prometheus
+    let metrics_listener = match cli.metrics_addr {
+        Some(addr) => Some(TcpListener::bind(addr).await.context("Failed to bind the metrics address")?),
+        None => None,
+    };
+    metrics::install_recorder(metrics_listener).context("Failed to install metrics recorder")?;
+    if let Some(addr) = cli.metrics_addr {
+        info!("Serving metrics on http://{addr}/metrics");
+    }
+
     match &cli.command {
         Commands::Keygen { threshold, parties, output } => {
             info!("Generating {threshold} of {parties} threshold keys...");
@@ -142,27 +764,319 @@ async fn main() -> Result<(), Error> {
             let address = key_data.address(btc_network).context("Failed to derive address from key data")?;
 
             info!("Group address for '{btc_network}': {address}");
+            info!("Group ID: {}", GroupId::new(&key_data.public).context("Failed to derive group ID")?);
         }
 
-        Commands::Spend { keys, utxo, to, amount, network, rpc_url, rpc_user, rpc_pass } => {
+        Commands::Scan { keys, network, chain } => {
+            let key_data: KeyData = read_json(keys).context("Failed to read keys file")?;
+            let address = key_data.address((*network).into()).context("Failed to derive address from key data")?;
+            let script = address.script_pubkey();
+            let backend = chain.connect(*network)?;
+
+            info!("Tip height: {}", backend.tip_height().await?);
+            for entry in backend.history(&script).await? {
+                match entry.height {
+                    Some(height) => info!("Transaction {} confirmed at {height}", entry.txid),
+                    None => info!("Transaction {} unconfirmed", entry.txid),
+                }
+            }
+            let utxos = backend.list_unspent(&script).await?;
+            for utxo in &utxos {
+                info!("UTXO {} of {}", utxo.outpoint, utxo.value);
+            }
+            let balance: Amount = utxos.iter().map(|utxo| utxo.value).sum();
+            info!("Balance of {address}: {balance}");
+        }
+
+        Commands::Spend {
+            keys,
+            utxo,
+            to,
+            amount,
+            network,
+            chain,
+            estimate_fee,
+            signers,
+            round1_timeout,
+            round2_timeout,
+            ceremony_timeout,
+            wait_for_all,
+            signer_config,
+            relay,
+            coordinator_key,
+        } => {
             info!("Spending {amount} sats to {to} on the {network:?} network...");
 
+            let backend = chain.connect(*network)?;
             let args = SpendArgs {
                 keys_path: keys,
                 utxo,
                 to,
                 amount: *amount,
                 network: (*network).into(),
-                rpc_url,
-                rpc_user: rpc_user.as_deref(),
-                rpc_pass: rpc_pass.as_deref(),
+                backend: backend.as_ref(),
+                ceremony: CeremonyConfig {
+                    round1_timeout: Duration::from_secs(*round1_timeout),
+                    round2_timeout: Duration::from_secs(*round2_timeout),
+                    ceremony_timeout: Duration::from_secs(*ceremony_timeout),
+                    early_completion: !wait_for_all,
+                    selection: SignerSelection::from_indices(signers)?,
+                    ..signer_config.ceremony_config((*network).into())?
+                },
+                estimate_fee: *estimate_fee,
+            };
+            let tx_id = match relay {
+                Some(relay) => spend_over_relay(args, relay, load_or_create_key(coordinator_key)?).await?,
+                None => spend(args).await?,
             };
-            let tx_id = spend(args).await?;
 
             info!("Transaction signed and broadcasted!");
             info!("TxID: {tx_id}");
         }
+
+        Commands::CoordinatorKey { key } => {
+            let public_key = load_or_create_key(key)?.public_key(&Secp256k1::new());
+            info!("Coordinator public key: {public_key}");
+        }
+
+        Commands::Grpc { keys, listen, network, chain, signer_config, estimate_fee, token, insecure } => {
+            let token = token.as_deref().map(ApiToken::new).transpose()?;
+            if token.is_none() && !insecure {
+                anyhow::bail!(
+                    "The gRPC API needs a token (--token or FROST_API_TOKEN), or --insecure to serve without"
+                );
+            }
+            let key_data = read_json(keys).context("Failed to read keys file")?;
+            let config = signer_config.ceremony_config((*network).into())?;
+            let mut service =
+                SigningService::new(key_data, (*network).into(), config)?.with_backend(chain.connect(*network)?);
+            if *estimate_fee {
+                service = service.with_fee_estimation();
+            }
+            grpc::serve(TcpListener::bind(listen).await?, SignerService::new(service), BearerAuth::new(token)).await?;
+        }
+
+        Commands::Serve { keys, listen, network, chain, signer_config, estimate_fee, token } => {
+            let token = ApiToken::new(token)?;
+            let key_data = read_json(keys).context("Failed to read keys file")?;
+            let config = signer_config.ceremony_config((*network).into())?;
+            let mut service =
+                SigningService::new(key_data, (*network).into(), config)?.with_backend(chain.connect(*network)?);
+            if *estimate_fee {
+                service = service.with_fee_estimation();
+            }
+            rest::serve(TcpListener::bind(listen).await?, service, token).await?;
+        }
+
+        Commands::Audit { command } => match command {
+            AuditCommands::Verify { log, trusted_keys } => {
+                let entries = verify_log(log, trusted_keys)?;
+                if trusted_keys.is_empty() {
+                    let signers: BTreeSet<String> = load_entries(log)?.into_iter().map(|entry| entry.signer).collect();
+                    warn!(
+                        "UNAUTHENTICATED: the hash chain of audit log {log:?} is intact ({entries} entries), but anyone \
+                         could have written it. Pass --trusted-keys to check the signers: {signers:?}"
+                    );
+                } else {
+                    info!("Audit log {log:?} is intact, {entries} entries verified");
+                }
+            }
+        },
+
+        Commands::Signer { command } => match command {
+            SignerCommands::Run { signer, relay, coordinator_key, nonce_dir } => {
+                let key = signer.participant_key()?;
+                let identity = RelayIdentity::participant(&key.key_package, &key.public, *coordinator_key)?;
+                let topic = Topic { group_id: GroupId::new(&key.public)?, session_id: None };
+                let transport = WebSocketTransport::connect(relay, topic, identity).await?;
+                let (offline_signer, id) = signer.signer()?;
+                let participant =
+                    participant_index(&key.public, id).map_or_else(|| hex::encode(id.serialize()), |i| i.to_string());
+                info!("Participant {participant} is signing for the coordinator behind {relay}");
+                run_remote_signer(offline_signer, &transport, nonce_dir).await?;
+            }
+
+            SignerCommands::Export { keys, participant, output } => {
+                let key_data: KeyData = read_json(keys).context("Failed to read keys file")?;
+                let key = key_data
+                    .participant(Identifier::try_from(*participant)?)
+                    .with_context(|| format!("No key share for participant {participant} in {keys:?}"))?;
+                std::fs::write(output, serde_json::to_string_pretty(&key)?)
+                    .with_context(|| format!("Failed to write {output:?}"))?;
+                info!("Key share of participant {participant} written to {output:?}");
+            }
+
+            SignerCommands::Pending { approval_dir, network } => {
+                let queue = FileApprovalQueue::open(approval_dir)?;
+                let pending = queue.pending()?;
+                if pending.is_empty() {
+                    info!("No signing requests awaiting approval.");
+                }
+                for request in pending {
+                    let participant = request
+                        .index
+                        .map_or_else(|| hex::encode(request.participant.serialize()), |index| index.to_string());
+                    info!(
+                        "Session {} for participant {participant}:\n{}",
+                        request.session_id,
+                        request.summary.render((*network).into())
+                    );
+                }
+            }
+
+            SignerCommands::Approve { session, participant, approval_dir } => {
+                let id = Identifier::try_from(*participant)?;
+                FileApprovalQueue::open(approval_dir)?.decide(*session, id, ApprovalDecision::Approved)?;
+                info!("Approved signing request of participant {participant} for session {session}");
+            }
+
+            SignerCommands::Reject { session, participant, approval_dir, reason } => {
+                let id = Identifier::try_from(*participant)?;
+                let decision = ApprovalDecision::Rejected { reason: reason.clone() };
+                FileApprovalQueue::open(approval_dir)?.decide(*session, id, decision)?;
+                info!("Rejected signing request of participant {participant} for session {session}");
+            }
+        },
+
+        Commands::Sign { command } => match command {
+            SignCommands::Request { keys, utxo, to, amount, network, chain, estimate_fee, out } => {
+                let backend = chain.connect(*network)?;
+                let args = SpendArgs {
+                    keys_path: keys,
+                    utxo,
+                    to,
+                    amount: *amount,
+                    network: (*network).into(),
+                    backend: backend.as_ref(),
+                    ceremony: CeremonyConfig::default(),
+                    estimate_fee: *estimate_fee,
+                };
+                let request = create_offline_request(args).await?;
+                std::fs::write(out, serde_json::to_string_pretty(&request)?)
+                    .with_context(|| format!("Failed to write {out:?}"))?;
+                info!("Signing request for session {} written to {out:?}", request.session_id);
+            }
+
+            SignCommands::Round1 { signer, nonces, request, outbox } => {
+                let (offline_signer, id) = signer.signer()?;
+                let request = read_json::<OfflineRequest>(request).context("Failed to read signing request")?;
+                let envelope = offline_signer.round1(&request, nonces)?;
+                FileTransport::new(Some(id), outbox, outbox).broadcast(envelope).await?;
+                info!("Commitment for session {} written to {outbox:?}", request.session_id);
+            }
+
+            SignCommands::Package { keys, request, inbox, out } => {
+                let key_data: KeyData = read_json(keys).context("Failed to read keys file")?;
+                let request = read_json::<OfflineRequest>(request).context("Failed to read signing request")?;
+                let package = collect_commitments(&key_data, &request, read_inbox(inbox).await?)?;
+                std::fs::write(out, serde_json::to_string_pretty(&package)?)
+                    .with_context(|| format!("Failed to write {out:?}"))?;
+                info!(
+                    "Signing package of {} signers for session {} written to {out:?}",
+                    package.signing_commitments().len(),
+                    request.session_id
+                );
+            }
+
+            SignCommands::Round2 { signer, nonces, request, package, outbox } => {
+                let (mut offline_signer, id) = signer.signer()?;
+                let request = read_json::<OfflineRequest>(request).context("Failed to read signing request")?;
+                let package = read_json(package).context("Failed to read signing package")?;
+                match offline_signer.round2(&request, &package, nonces)? {
+                    Some(envelope) => {
+                        FileTransport::new(Some(id), outbox, outbox).broadcast(envelope).await?;
+                        info!("Signature share for session {} written to {outbox:?}", request.session_id);
+                    }
+                    None => info!(
+                        "Session {} awaits operator approval, run round 2 again once it was decided",
+                        request.session_id
+                    ),
+                }
+            }
+
+            SignCommands::Aggregate { keys, request, package, inbox, out, broadcast, network, chain } => {
+                let key_data: KeyData = read_json(keys).context("Failed to read keys file")?;
+                let request = read_json::<OfflineRequest>(request).context("Failed to read signing request")?;
+                let package = read_json(package).context("Failed to read signing package")?;
+                let transaction = aggregate_shares(&key_data, &request, &package, read_inbox(inbox).await?)?;
+                std::fs::write(out, serialize_hex(&transaction)).with_context(|| format!("Failed to write {out:?}"))?;
+                info!("Signed transaction {} written to {out:?}", transaction.compute_txid());
+                if *broadcast {
+                    let txid = chain.connect(*network)?.broadcast(&transaction).await?;
+                    info!("Transaction broadcasted, TxID: {txid}");
+                }
+            }
+        },
+
+        Commands::Qr { command } => match command {
+            QrCommands::Show { kind, file, fragment_len, interval_ms, text } => {
+                let payload = match kind {
+                    QrKind::Request => QrPayload::Request(read_json(file)?),
+                    QrKind::Package => QrPayload::Package(read_json(file)?),
+                    QrKind::Message => QrPayload::Message(read_message(file)?),
+                };
+                let mut encoder = UrEncoder::new(&payload, *fragment_len)?;
+                let count = encoder.fragment_count();
+                if *text {
+                    for _ in 0..count {
+                        println!("{}", encoder.next_part()?);
+                    }
+                    return Ok(());
+                }
+                for index in 0.. {
+                    let part = encoder.next_part()?;
+                    // Clears the terminal so the codes replace each other like frames
+                    print!("\x1b[2J\x1b[H{}", render(&part)?);
+                    println!(
+                        "Part {} of {count}{}",
+                        index % count + 1,
+                        if index >= count { " (fountain)" } else { "" }
+                    );
+                    if count == 1 {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(*interval_ms)).await;
+                }
+            }
+
+            QrCommands::Decode { input, out } => {
+                let scanned = match input {
+                    Some(path) => std::fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?,
+                    None => std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?,
+                };
+                let mut decoder = UrDecoder::default();
+                for part in scanned.lines().filter(|line| !line.trim().is_empty()) {
+                    decoder.receive(part)?;
+                    if decoder.is_complete() {
+                        break;
+                    }
+                }
+                match decoder.payload()? {
+                    QrPayload::Request(request) => std::fs::write(out, serde_json::to_string_pretty(&request)?),
+                    QrPayload::Package(package) => std::fs::write(out, serde_json::to_string_pretty(&package)?),
+                    // Message files are wire frames, like the ones in the outboxes
+                    QrPayload::Message(message) => Ok(write_message(out, &message)?),
+                }
+                .with_context(|| format!("Failed to write {out:?}"))?;
+                info!("Decoded UR sequence written to {out:?}");
+            }
+        },
     }
 
     Ok(())
 }
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Reads every message file carried into the inbox.
+async fn read_inbox(inbox: &Path) -> Result<Vec<Envelope>, Error> {
+    let transport = FileTransport::new(None, inbox, inbox);
+    let mut envelopes = Vec::new();
+    while let Some((_, envelope)) = transport.receive().await? {
+        envelopes.push(envelope);
+    }
+    Ok(envelopes)
+}
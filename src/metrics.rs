@@ -0,0 +1,103 @@
+use crate::{
+    errors::SigningError,
+    signer::{SigningMessage, SigningRound},
+};
+use ::metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use axum::{routing::get, Router};
+use frost_secp256k1_tr::Identifier;
+use metrics_exporter_prometheus::{BuildError, PrometheusBuilder, PrometheusHandle};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::warn;
+
+pub const CEREMONIES_STARTED: &str = "frost_ceremonies_started_total";
+pub const CEREMONIES_COMPLETED: &str = "frost_ceremonies_completed_total";
+pub const CEREMONIES_FAILED: &str = "frost_ceremonies_failed_total";
+pub const CEREMONY_DURATION: &str = "frost_ceremony_duration_seconds";
+pub const ROUND_DURATION: &str = "frost_round_duration_seconds";
+pub const ROUND_TIMEOUTS: &str = "frost_round_timeouts_total";
+pub const MESSAGES_RECEIVED: &str = "frost_messages_received_total";
+pub const INVALID_SHARES: &str = "frost_invalid_signature_shares_total";
+
+/// Histogram buckets in seconds, from in-memory rounds to rounds waiting on an operator.
+const DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// Installs the Prometheus recorder, serving `/metrics` on the given listener if any.
+/// Must be called from within a Tokio runtime when serving.
+pub fn install_recorder(listener: Option<TcpListener>) -> Result<PrometheusHandle, BuildError> {
+    let handle = PrometheusBuilder::new().set_buckets(DURATION_BUCKETS)?.install_recorder()?;
+    if let Some(listener) = listener {
+        let exporter = handle.clone();
+        let router = Router::new().route("/metrics", get(move || std::future::ready(exporter.render())));
+        tokio::spawn(async move {
+            if axum::serve(listener, router).await.is_err() {
+                warn!("Metrics exporter stopped.");
+            }
+        });
+    }
+    describe();
+    Ok(handle)
+}
+
+/// Registers the metric descriptions.
+fn describe() {
+    describe_counter!(CEREMONIES_STARTED, "Signing ceremonies started.");
+    describe_counter!(CEREMONIES_COMPLETED, "Signing ceremonies that produced a signed transaction.");
+    describe_counter!(CEREMONIES_FAILED, "Signing ceremonies that failed, by reason.");
+    describe_histogram!(CEREMONY_DURATION, Unit::Seconds, "Duration of completed signing ceremonies.");
+    describe_histogram!(ROUND_DURATION, Unit::Seconds, "Duration of the signing rounds, by round.");
+    describe_counter!(ROUND_TIMEOUTS, "Rounds that timed out, by round.");
+    describe_counter!(MESSAGES_RECEIVED, "Messages received by the coordinator, by participant and type.");
+    describe_counter!(INVALID_SHARES, "Invalid signature shares, by participant.");
+}
+
+/// Label value of a round.
+fn round_label(round: SigningRound) -> &'static str {
+    match round {
+        SigningRound::Commitments => "commitments",
+        SigningRound::Approval => "approval",
+        SigningRound::Shares => "shares",
+    }
+}
+
+/// Label value of a participant, its hex encoded identifier.
+fn participant_label(participant: Identifier) -> String {
+    hex::encode(participant.serialize())
+}
+
+/// Counts a started ceremony.
+pub(crate) fn ceremony_started() {
+    counter!(CEREMONIES_STARTED).increment(1);
+}
+
+/// Counts the outcome of a ceremony, failures by reason and timeouts by round.
+pub(crate) fn ceremony_finished<T>(result: &Result<T, SigningError>, duration: Duration) {
+    match result {
+        Ok(_) => {
+            counter!(CEREMONIES_COMPLETED).increment(1);
+            histogram!(CEREMONY_DURATION).record(duration.as_secs_f64());
+        }
+        Err(e) => {
+            counter!(CEREMONIES_FAILED, "reason" => e.reason()).increment(1);
+            if let SigningError::Timeout { round, .. } = e {
+                counter!(ROUND_TIMEOUTS, "round" => round_label(*round)).increment(1);
+            }
+        }
+    }
+}
+
+/// Records the duration of a completed round.
+pub(crate) fn round_completed(round: SigningRound, duration: Duration) {
+    histogram!(ROUND_DURATION, "round" => round_label(round)).record(duration.as_secs_f64());
+}
+
+/// Counts a message received from a participant.
+pub(crate) fn message_received(message: &SigningMessage) {
+    let participant = participant_label(message.sender());
+    counter!(MESSAGES_RECEIVED, "participant" => participant, "kind" => message.kind()).increment(1);
+}
+
+/// Counts an invalid signature share from a participant.
+pub(crate) fn invalid_share(participant: Identifier) {
+    counter!(INVALID_SHARES, "participant" => participant_label(participant)).increment(1);
+}
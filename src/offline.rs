@@ -0,0 +1,484 @@
+use crate::{
+    approval::{ApprovalDecision, ApprovalQueue, PendingRequest, TransactionSummary},
+    codec::{from_wire, to_wire},
+    envelope::{tagged_hash, Envelope, ReplayGuard, Sealer},
+    errors::{SigningError, StoreError, TransportError},
+    keys::{group_address, participant_index, KeyData},
+    policy::{PolicyEngine, SignerPolicy},
+    signer::{SessionId, SigningMessage, SigningRequest, SigningRound},
+    store::{JournalEntry, SessionRecord, SpendRecord, StateStore},
+    transport::Transport,
+};
+use async_trait::async_trait;
+use bitcoin::{Network, Transaction};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use frost_secp256k1_tr as frost;
+use frost_secp256k1_tr::{
+    keys::{KeyPackage, PublicKeyPackage},
+    Ciphersuite, Identifier, SigningPackage,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tracing::{debug, info, warn};
+use zeroize::Zeroize;
+
+/// Interval an idle receiver checks its inbox for files carried in.
+const INBOX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Domain separation tag of the nonce file key.
+const NONCE_FILE_TAG: &[u8] = b"frost-demo/nonce-file";
+
+/// Signing request carried to an air-gapped signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfflineRequest {
+    pub session_id: SessionId,
+    #[serde(flatten)]
+    pub request: SigningRequest,
+}
+
+/// Extension of message files, wire frames of a `MessageFile`.
+const MESSAGE_EXTENSION: &str = "msg";
+
+/// Message file carried between the signers and the coordinator, addressed to a participant or to everyone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageFile {
+    pub to: Option<Identifier>,
+    pub envelope: Envelope,
+}
+
+/// Writes a message file as a wire frame, an existing file is never overwritten.
+pub fn write_message(path: &Path, message: &MessageFile) -> Result<(), TransportError> {
+    let frame = to_wire(message).map_err(|e| TransportError::Send(e.to_string()))?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| TransportError::Send(format!("{}: {e}", path.display())))?;
+    file.write_all(&frame).map_err(|e| TransportError::Send(e.to_string()))?;
+    file.sync_all().map_err(|e| TransportError::Send(e.to_string()))
+}
+
+pub fn read_message(path: &Path) -> Result<MessageFile, TransportError> {
+    let frame = fs::read(path).map_err(|e| TransportError::Receive(format!("{}: {e}", path.display())))?;
+    from_wire(&frame).map_err(|e| TransportError::Receive(format!("{}: {e}", path.display())))
+}
+
+/// Transport exchanging message files through directories carried across an air gap, e.g. on a USB stick. Sent
+/// messages are written to the outbox, received ones imported from the inbox once each.
+pub struct FileTransport {
+    /// Participant the transport belongs to, the coordinator's has none.
+    owner: Option<Identifier>,
+    outbox: PathBuf,
+    inbox: PathBuf,
+    imported: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl FileTransport {
+    pub fn new(owner: Option<Identifier>, outbox: impl Into<PathBuf>, inbox: impl Into<PathBuf>) -> Self {
+        Self { owner, outbox: outbox.into(), inbox: inbox.into(), imported: Mutex::new(BTreeSet::new()) }
+    }
+
+    fn export(&self, to: Option<Identifier>, envelope: Envelope) -> Result<(), TransportError> {
+        if self.owner.is_none() {
+            return Err(TransportError::Send("The coordinator can't send messages.".into()));
+        }
+        fs::create_dir_all(&self.outbox).map_err(|e| TransportError::Send(e.to_string()))?;
+        let to_name = to.map_or_else(|| "all".to_string(), |id| hex::encode(id.serialize()));
+        let sender = hex::encode(envelope.sender().serialize());
+        let name = format!("{sender}-{}-{to_name}.{MESSAGE_EXTENSION}", envelope.sequence);
+        write_message(&self.outbox.join(name), &MessageFile { to, envelope })
+    }
+}
+
+#[async_trait]
+impl Transport for FileTransport {
+    type Msg = Envelope;
+
+    async fn send(&self, receiver: Identifier, msg: Self::Msg) -> Result<(), TransportError> {
+        self.export(Some(receiver), msg)
+    }
+
+    async fn broadcast(&self, msg: Self::Msg) -> Result<(), TransportError> {
+        self.export(None, msg)
+    }
+
+    async fn receive(&self) -> Result<Option<(Identifier, Self::Msg)>, TransportError> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&self.inbox) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(TransportError::Receive(e.to_string())),
+        };
+        paths.retain(|path| path.extension().is_some_and(|extension| extension == MESSAGE_EXTENSION));
+        paths.sort();
+
+        let mut imported = self.imported.lock().map_err(|e| TransportError::Receive(e.to_string()))?;
+        for path in paths {
+            if imported.contains(&path) {
+                continue;
+            }
+            // A file still being copied is picked up on the next attempt
+            let file = match read_message(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Skipping message file: {e}");
+                    continue;
+                }
+            };
+            imported.insert(path);
+            let sender = file.envelope.sender();
+            if Some(sender) == self.owner || file.to.is_some_and(|to| Some(to) != self.owner) {
+                continue;
+            }
+            return Ok(Some((sender, file.envelope)));
+        }
+        Ok(None)
+    }
+
+    async fn next_message(&self) -> Result<(Identifier, Self::Msg), TransportError> {
+        loop {
+            if let Some(message) = self.receive().await? {
+                return Ok(message);
+            }
+            tokio::time::sleep(INBOX_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Nonces of a signer between the rounds, with the sequence its next envelope continues from.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct NonceState {
+    #[serde_as(as = "Hex")]
+    nonces: Vec<u8>,
+    sequence: u64,
+}
+
+/// Nonce file, encrypted with a key derived from the signer's key share and bound to the session.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct EncryptedNonces {
+    session_id: SessionId,
+    #[serde_as(as = "Hex")]
+    nonce: [u8; 12],
+    #[serde_as(as = "Hex")]
+    ciphertext: Vec<u8>,
+}
+
+fn nonce_cipher(key_package: &KeyPackage) -> ChaCha20Poly1305 {
+    let mut share = key_package.signing_share().serialize();
+    let mut key = tagged_hash(NONCE_FILE_TAG, &share);
+    share.zeroize();
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    cipher
+}
+
+fn store_nonces(
+    key_package: &KeyPackage,
+    path: &Path,
+    session_id: SessionId,
+    state: &NonceState,
+) -> Result<(), SigningError> {
+    let mut plaintext = serde_json::to_vec(state).map_err(|e| StoreError::Io(e.to_string()))?;
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let aad = session_id.to_string();
+    let encrypted = nonce_cipher(key_package)
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: aad.as_bytes() })
+        .map_err(|e| StoreError::Io(e.to_string()));
+    plaintext.zeroize();
+    let file = EncryptedNonces { session_id, nonce, ciphertext: encrypted? };
+
+    let json = serde_json::to_vec(&file).map_err(|e| StoreError::Io(e.to_string()))?;
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| StoreError::Io(format!("{}: {e}", path.display())))?;
+    out.write_all(&json).map_err(|e| StoreError::Io(e.to_string()))?;
+    out.sync_all().map_err(|e| StoreError::Io(e.to_string()))?;
+    Ok(())
+}
+
+fn load_nonces(key_package: &KeyPackage, path: &Path, session_id: SessionId) -> Result<NonceState, SigningError> {
+    let json = fs::read(path).map_err(|e| StoreError::Io(format!("{}: {e}", path.display())))?;
+    let file: EncryptedNonces = serde_json::from_slice(&json).map_err(|e| StoreError::Corrupt(e.to_string()))?;
+    if file.session_id != session_id {
+        return Err(SigningError::InvalidState(format!(
+            "Nonces at {} belong to session {}",
+            path.display(),
+            file.session_id
+        )));
+    }
+    let aad = session_id.to_string();
+    let mut plaintext = nonce_cipher(key_package)
+        .decrypt(Nonce::from_slice(&file.nonce), Payload { msg: &file.ciphertext, aad: aad.as_bytes() })
+        .map_err(|_| StoreError::Corrupt(format!("{} doesn't decrypt with this key share", path.display())))?;
+    let state = serde_json::from_slice(&plaintext).map_err(|e| StoreError::Corrupt(e.to_string()));
+    plaintext.zeroize();
+    Ok(state?)
+}
+
+/// Air-gapped signer, each round runs in its own invocation. Applies the checks of the online signer: the request
+/// must pass the policy and, with an approval queue, be approved by an operator, and the signing package must sign it.
+/// Sessions are journaled in the store, so the nonces of a session are generated and used at most once.
+pub struct OfflineSigner {
+    key_package: KeyPackage,
+    public_key_package: PublicKeyPackage,
+    store: Arc<dyn StateStore>,
+    policy: PolicyEngine,
+    approvals: Option<(Arc<dyn ApprovalQueue>, Duration)>,
+}
+
+impl OfflineSigner {
+    pub fn new(
+        key_package: KeyPackage,
+        public_key_package: PublicKeyPackage,
+        store: Arc<dyn StateStore>,
+    ) -> Result<Self, SigningError> {
+        // The output script of a Taproot key doesn't depend on the network
+        let group_script = group_address(&public_key_package, Network::Bitcoin)?.script_pubkey();
+        Ok(Self {
+            key_package,
+            public_key_package,
+            store,
+            policy: PolicyEngine::permissive(group_script),
+            approvals: None,
+        })
+    }
+
+    pub fn participant_id(&self) -> Identifier {
+        *self.key_package.identifier()
+    }
+
+    /// Sets the policy checked before signing, its allowed destinations must be addresses on `network`.
+    pub fn with_policy(mut self, policy: SignerPolicy, network: Network) -> Result<Self, SigningError> {
+        let group_script = group_address(&self.public_key_package, network)?.script_pubkey();
+        self.policy = PolicyEngine::new(policy, network, group_script)?;
+        Ok(self)
+    }
+
+    /// Sets the queue where signing requests wait for operator approval, for at most `timeout`.
+    pub fn with_approval_queue(mut self, approvals: Arc<dyn ApprovalQueue>, timeout: Duration) -> Self {
+        self.approvals = Some((approvals, timeout));
+        self
+    }
+
+    /// Round 1: checks the request against the policy, submits it for approval, generates the nonces, stores them
+    /// encrypted at `nonce_path` and returns the commitment to carry to the coordinator. Refuses sessions it has
+    /// seen before and to overwrite the nonces of an unfinished session.
+    pub fn round1(&self, request: &OfflineRequest, nonce_path: &Path) -> Result<Envelope, SigningError> {
+        let session_id = request.session_id;
+        if nonce_path.exists() {
+            return Err(SigningError::InvalidState(format!(
+                "Nonces of an unfinished session are stored at {}",
+                nonce_path.display()
+            )));
+        }
+        if !self.store.load(session_id)?.is_empty() {
+            return Err(SigningError::NonceReuse(session_id));
+        }
+        // Operators only get to see requests the signer would sign
+        self.policy.evaluate(&request.request.transaction, &request.request.prev_tx_outs, SystemTime::now())?;
+        let entry = JournalEntry::CollectingCommitments {
+            transaction: request.request.transaction.clone(),
+            prev_tx_outs: request.request.prev_tx_outs.clone(),
+        };
+        self.store.append(session_id, &entry)?;
+        if let Some((approvals, timeout)) = &self.approvals {
+            let summary = TransactionSummary::new(&request.request)?;
+            let participant = *self.key_package.identifier();
+            let index = participant_index(&self.public_key_package, participant);
+            let deadline = SystemTime::now() + *timeout;
+            approvals.submit(&PendingRequest { session_id, participant, index, summary, deadline })?;
+            info!(%session_id, "Signing request is awaiting operator approval.");
+        }
+
+        let (mut nonces, commitments) = frost::round1::commit(self.key_package.signing_share(), &mut OsRng);
+        let sealer = Sealer::new(&self.key_package, &self.public_key_package)?;
+        let message =
+            SigningMessage::NonceCommitment(session_id, *self.key_package.identifier(), Box::new(commitments));
+        let envelope = sealer.seal(message)?;
+
+        let serialized = nonces.serialize();
+        nonces.zeroize();
+        let mut state = NonceState { nonces: serialized?, sequence: sealer.next_sequence() };
+        let stored = store_nonces(&self.key_package, nonce_path, session_id, &state);
+        state.nonces.zeroize();
+        stored?;
+        debug!(%session_id, "Stored encrypted nonces for round 2.");
+        Ok(envelope)
+    }
+
+    /// Round 2: checks the signing package against the request of round 1, waits for the operator's approval and
+    /// records the paid out amount towards the policy's daily limit, then signs with the stored nonces and returns the
+    /// share to carry to the coordinator. Returns `None` while the request awaits approval, run it again once it was
+    /// decided. The session is journaled as signed and the nonce file removed before signing, so the nonces are never
+    /// used twice. A rejected, expired or disallowed request fails the session and deletes its nonces.
+    pub fn round2(
+        &mut self,
+        request: &OfflineRequest,
+        signing_package: &SigningPackage,
+        nonce_path: &Path,
+    ) -> Result<Option<Envelope>, SigningError> {
+        let session_id = request.session_id;
+        let mut state = load_nonces(&self.key_package, nonce_path, session_id)?;
+        let record = SessionRecord::replay(self.store.load(session_id)?);
+        if record.share_signed || record.is_finished() {
+            state.nonces.zeroize();
+            return Err(SigningError::NonceReuse(session_id));
+        }
+        if record.transaction.as_ref() != Some(&request.request.transaction) {
+            state.nonces.zeroize();
+            return Err(SigningError::InvalidState(format!("Round 1 of session {session_id} signed another request")));
+        }
+        if let Err(e) = request.request.verify_signing_package(signing_package) {
+            state.nonces.zeroize();
+            return Err(e);
+        }
+
+        match self.approve_request(session_id, &request.request) {
+            Ok(true) => {}
+            Ok(false) => {
+                state.nonces.zeroize();
+                return Ok(None);
+            }
+            Err(e) => {
+                state.nonces.zeroize();
+                self.fail_session(session_id, nonce_path, &e)?;
+                return Err(e);
+            }
+        }
+
+        let journal_entry = JournalEntry::CollectingShares { signing_package: signing_package.clone() };
+        self.store.append(session_id, &journal_entry)?;
+        self.store.append(session_id, &JournalEntry::ShareSigned)?;
+        fs::remove_file(nonce_path).map_err(|e| StoreError::Io(format!("{}: {e}", nonce_path.display())))?;
+        let nonces = frost::round1::SigningNonces::deserialize(&state.nonces);
+        state.nonces.zeroize();
+        let mut nonces = nonces?;
+
+        let share = frost::round2::sign_with_tweak(signing_package, &nonces, &self.key_package, None);
+        nonces.zeroize();
+        let sealer = Sealer::new(&self.key_package, &self.public_key_package)?.with_sequence(state.sequence);
+        let envelope =
+            sealer.seal(SigningMessage::SignatureShare(session_id, *self.key_package.identifier(), share?))?;
+        Ok(Some(envelope))
+    }
+
+    /// Checks the operator decision and the policy, like the online signer. Returns whether the signer may sign, the
+    /// paid out amount is recorded in the store once it may.
+    fn approve_request(&mut self, session_id: SessionId, request: &SigningRequest) -> Result<bool, SigningError> {
+        if let Some((approvals, _)) = &self.approvals {
+            let participant = *self.key_package.identifier();
+            match approvals.decision(session_id, participant)? {
+                Some(ApprovalDecision::Approved) => info!(%session_id, "Signing request approved."),
+                Some(ApprovalDecision::Rejected { reason }) => {
+                    return Err(SigningError::ApprovalRejected { session_id, reason });
+                }
+                None if approvals
+                    .pending()?
+                    .iter()
+                    .any(|p| p.session_id == session_id && p.participant == participant) =>
+                {
+                    info!(%session_id, "Signing request is still awaiting operator approval.");
+                    return Ok(false);
+                }
+                None => {
+                    return Err(SigningError::Timeout { round: SigningRound::Approval, missing: vec![participant] })
+                }
+            }
+        }
+
+        let now = SystemTime::now();
+        self.policy.load_history(self.store.spends()?.into_iter().map(|spend| (spend.at, spend.amount)));
+        let amount = self.policy.evaluate(&request.transaction, &request.prev_tx_outs, now)?;
+        self.store.record_spend(SpendRecord { at: now, amount })?;
+        Ok(true)
+    }
+
+    /// Journals the failure of the session and deletes its nonces.
+    fn fail_session(&self, session_id: SessionId, nonce_path: &Path, error: &SigningError) -> Result<(), SigningError> {
+        warn!(%session_id, "Signing session failed: {error}");
+        self.store.append(session_id, &JournalEntry::Failed { error: error.to_string() })?;
+        fs::remove_file(nonce_path).map_err(|e| StoreError::Io(format!("{}: {e}", nonce_path.display())))?;
+        Ok(())
+    }
+}
+
+/// Coordinator side of round 1: checks the commitments carried in from the signers and builds the signing package.
+/// Envelopes of other sessions or rounds are skipped, as are those that don't open.
+pub fn collect_commitments(
+    key_data: &KeyData,
+    request: &OfflineRequest,
+    envelopes: impl IntoIterator<Item = Envelope>,
+) -> Result<SigningPackage, SigningError> {
+    let guard = ReplayGuard::new(&key_data.public)?;
+    let mut commitments = BTreeMap::new();
+    for envelope in envelopes {
+        if let Err(e) = guard.open(&envelope, 1) {
+            warn!("Skipping message of {:?}: {e}", envelope.sender());
+            continue;
+        }
+        match envelope.message {
+            SigningMessage::NonceCommitment(session_id, sender, commitment) if session_id == request.session_id => {
+                commitments.insert(sender, *commitment);
+            }
+            message => debug!("Skipping {} of another session.", message.kind()),
+        }
+    }
+    if commitments.len() < key_data.threshold as usize {
+        return Err(SigningError::NotEnoughSigners);
+    }
+    Ok(SigningPackage::new(commitments, &request.request.sighash()?))
+}
+
+/// Coordinator side of round 2: aggregates the signature shares carried in from the signers of the signing package
+/// and returns the signed transaction.
+pub fn aggregate_shares(
+    key_data: &KeyData,
+    request: &OfflineRequest,
+    signing_package: &SigningPackage,
+    envelopes: impl IntoIterator<Item = Envelope>,
+) -> Result<Transaction, SigningError> {
+    request.request.verify_signing_package(signing_package)?;
+    let guard = ReplayGuard::new(&key_data.public)?;
+    let signers = signing_package.signing_commitments();
+    let mut shares = BTreeMap::new();
+    for envelope in envelopes {
+        if let Err(e) = guard.open(&envelope, 2) {
+            warn!("Skipping message of {:?}: {e}", envelope.sender());
+            continue;
+        }
+        match envelope.message {
+            SigningMessage::SignatureShare(session_id, sender, share)
+                if session_id == request.session_id && signers.contains_key(&sender) =>
+            {
+                shares.insert(sender, share);
+            }
+            message => debug!("Skipping {} of another session.", message.kind()),
+        }
+    }
+    let missing: Vec<Identifier> = signers.keys().filter(|id| !shares.contains_key(id)).cloned().collect();
+    if !missing.is_empty() {
+        return Err(SigningError::Timeout { round: SigningRound::Shares, missing });
+    }
+
+    let signature = frost::aggregate_with_tweak(signing_package, &shares, &key_data.public, None)
+        .map_err(|e| e.culprit().map_or_else(|| e.into(), SigningError::InvalidSignatureShare))?;
+    let mut transaction = request.request.transaction.clone();
+    transaction.input[0].witness.push(frost::Secp256K1Sha256TR::serialize_signature(&signature)?);
+    Ok(transaction)
+}
@@ -0,0 +1,160 @@
+use crate::errors::SigningError;
+use bitcoin::{address::NetworkUnchecked, Address, Network, ScriptBuf, Transaction, TxOut};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+/// Window of the rolling daily limit.
+const DAILY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Weight added by the marker, flag and a Taproot key path witness per input once the transaction is signed.
+const SEGWIT_MARKER_WEIGHT: u64 = 2;
+const KEY_PATH_WITNESS_WEIGHT: u64 = 66;
+
+/// Local signing policy of a signer, rules that are not set are not enforced.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignerPolicy {
+    /// Addresses the signer is allowed to pay to, change back to the group is always allowed.
+    pub allowed_destinations: Option<Vec<Address<NetworkUnchecked>>>,
+
+    /// Maximum amount in satoshis paid out by a single transaction.
+    pub max_amount_per_tx: Option<u64>,
+
+    /// Maximum amount in satoshis paid out within any 24 hour window.
+    pub daily_limit: Option<u64>,
+
+    /// Maximum fee rate in sat/vB.
+    pub max_fee_rate: Option<u64>,
+
+    /// Require an output returning change back to the group.
+    #[serde(default)]
+    pub require_change: bool,
+}
+
+/// Evaluates transactions against a signer policy and tracks the amounts signed for the daily limit.
+#[derive(Debug, Clone)]
+pub struct PolicyEngine {
+    policy: SignerPolicy,
+    group_script: ScriptBuf,
+    allowed_scripts: Option<BTreeSet<ScriptBuf>>,
+    history: VecDeque<(SystemTime, u64)>,
+}
+
+impl PolicyEngine {
+    /// Engine for the group paying change to `group_script`, the allowed destinations must be addresses on `network`.
+    pub fn new(policy: SignerPolicy, network: Network, group_script: ScriptBuf) -> Result<Self, SigningError> {
+        let allowed_scripts = match &policy.allowed_destinations {
+            Some(addresses) => Some(
+                addresses
+                    .iter()
+                    .map(|address| {
+                        let address = address
+                            .clone()
+                            .require_network(network)
+                            .map_err(|e| violation(&format!("allowed destination is not a {network} address: {e}")))?;
+                        Ok(address.script_pubkey())
+                    })
+                    .collect::<Result<_, SigningError>>()?,
+            ),
+            None => None,
+        };
+        Ok(Self { policy, group_script, allowed_scripts, history: VecDeque::new() })
+    }
+
+    /// Engine without rules, only telling change back to the group from paid out amounts.
+    pub fn permissive(group_script: ScriptBuf) -> Self {
+        Self { policy: SignerPolicy::default(), group_script, allowed_scripts: None, history: VecDeque::new() }
+    }
+
+    /// Whether the policy limits the amount paid out per day.
+    pub fn has_daily_limit(&self) -> bool {
+        self.policy.daily_limit.is_some()
+    }
+
+    /// Checks a transaction against the policy, returns the amount paid out by it.
+    pub fn evaluate(
+        &self,
+        transaction: &Transaction,
+        prev_tx_outs: &[TxOut],
+        now: SystemTime,
+    ) -> Result<u64, SigningError> {
+        if prev_tx_outs.is_empty() || prev_tx_outs.len() != transaction.input.len() {
+            return Err(violation("prevouts do not match the transaction inputs"));
+        }
+
+        // Outputs back to the group address are change, everything else is paid out
+        let (change, payments): (Vec<&TxOut>, Vec<&TxOut>) =
+            transaction.output.iter().partition(|output| output.script_pubkey == self.group_script);
+        let amount: u64 = payments.iter().map(|output| output.value.to_sat()).sum();
+
+        if let Some(allowed) = &self.allowed_scripts {
+            if let Some(output) = payments.iter().find(|output| !allowed.contains(&output.script_pubkey)) {
+                return Err(violation(&format!("destination {} is not allowed", output.script_pubkey)));
+            }
+        }
+
+        if let Some(max_amount) = self.policy.max_amount_per_tx {
+            if amount > max_amount {
+                return Err(violation(&format!(
+                    "amount {amount} sat exceeds the per transaction limit {max_amount} sat"
+                )));
+            }
+        }
+
+        if let Some(daily_limit) = self.policy.daily_limit {
+            let spent = self.spent_since(now.checked_sub(DAILY_WINDOW).unwrap_or(SystemTime::UNIX_EPOCH));
+            if spent + amount > daily_limit {
+                return Err(violation(&format!(
+                    "amount {amount} sat exceeds the daily limit {daily_limit} sat, {spent} sat already signed"
+                )));
+            }
+        }
+
+        if let Some(max_fee_rate) = self.policy.max_fee_rate {
+            let input_value: u64 = prev_tx_outs.iter().map(|output| output.value.to_sat()).sum();
+            let output_value: u64 = transaction.output.iter().map(|output| output.value.to_sat()).sum();
+            let fee = input_value
+                .checked_sub(output_value)
+                .ok_or_else(|| violation("outputs exceed the value of the inputs"))?;
+            // Rounded up, so a fee rate just above the maximum doesn't pass
+            let fee_rate = (fee * 4).div_ceil(estimated_signed_weight(transaction));
+            if fee_rate > max_fee_rate {
+                return Err(violation(&format!("fee rate {fee_rate} sat/vB exceeds {max_fee_rate} sat/vB")));
+            }
+        }
+
+        if self.policy.require_change && change.is_empty() {
+            return Err(violation("transaction does not return change to the group"));
+        }
+
+        Ok(amount)
+    }
+
+    /// Records an amount signed at the given time, counted towards the daily limit.
+    pub fn record_spend(&mut self, amount: u64, at: SystemTime) {
+        let window_start = at.checked_sub(DAILY_WINDOW).unwrap_or(SystemTime::UNIX_EPOCH);
+        self.history.retain(|(time, _)| *time > window_start);
+        self.history.push_back((at, amount));
+    }
+
+    /// Replaces the recorded amounts, e.g. with those persisted by an earlier run.
+    pub fn load_history(&mut self, history: impl IntoIterator<Item = (SystemTime, u64)>) {
+        self.history = history.into_iter().collect();
+    }
+
+    fn spent_since(&self, since: SystemTime) -> u64 {
+        self.history.iter().filter(|(time, _)| *time > since).map(|(_, amount)| amount).sum()
+    }
+}
+
+/// Weight of the transaction once every input carries a Taproot key path signature.
+pub(crate) fn estimated_signed_weight(transaction: &Transaction) -> u64 {
+    let witness_weight = SEGWIT_MARKER_WEIGHT + KEY_PATH_WITNESS_WEIGHT * transaction.input.len() as u64;
+    transaction.weight().to_wu() + witness_weight
+}
+
+fn violation(reason: &str) -> SigningError {
+    SigningError::PolicyViolation(reason.to_string())
+}
@@ -0,0 +1,141 @@
+use crate::errors::SigningError;
+use frost_secp256k1_tr::{
+    keys::SigningShare,
+    round1::{self, SigningCommitments, SigningNonces},
+    Identifier,
+};
+use rand::{rngs::OsRng, RngCore};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Identifier of a preprocessed nonce commitment, unique per signer: the epoch of the pool that generated it in the
+/// high 32 bits and a counter in the low 32 bits.
+pub type CommitmentId = u64;
+
+/// Epoch of the nonce pool a commitment was generated by.
+pub fn commitment_epoch(id: CommitmentId) -> u32 {
+    (id >> 32) as u32
+}
+
+/// Default number of preprocessed nonces a signer keeps at most.
+pub const DEFAULT_NONCE_POOL_SIZE: usize = 100;
+
+/// Signer side pool of pre-generated nonces, kept in memory only. Each nonce is handed out at most once. Every pool
+/// draws a random epoch, so identifiers aren't reissued after a restart and the coordinator can tell that commitments
+/// of an earlier epoch lost their nonces.
+pub struct NoncePool {
+    epoch: u32,
+    next_index: u32,
+    nonces: BTreeMap<CommitmentId, SigningNonces>,
+    max_size: usize,
+}
+
+impl NoncePool {
+    pub fn new(max_size: usize) -> Self {
+        Self { epoch: OsRng.next_u32(), next_index: 0, nonces: BTreeMap::new(), max_size }
+    }
+
+    /// Epoch of the pool.
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Generates up to `count` new nonces, bounded by the pool size and the identifiers left in the epoch, and returns
+    /// their commitments.
+    pub fn generate(&mut self, signing_share: &SigningShare, count: usize) -> Vec<(CommitmentId, SigningCommitments)> {
+        let left = (u32::MAX - self.next_index) as usize;
+        let count = count.min(self.max_size.saturating_sub(self.nonces.len())).min(left);
+        (0..count)
+            .map(|_| {
+                let (nonces, commitments) = round1::commit(signing_share, &mut OsRng);
+                let id = (CommitmentId::from(self.epoch) << 32) | CommitmentId::from(self.next_index);
+                self.next_index += 1;
+                self.nonces.insert(id, nonces);
+                (id, commitments)
+            })
+            .collect()
+    }
+
+    /// Removes the nonces for a commitment from the pool, they can't be taken again.
+    pub fn take(&mut self, id: CommitmentId) -> Option<SigningNonces> {
+        self.nonces.remove(&id)
+    }
+
+    /// Number of unused nonces.
+    pub fn len(&self) -> usize {
+        self.nonces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+}
+
+impl Default for NoncePool {
+    fn default() -> Self {
+        Self::new(DEFAULT_NONCE_POOL_SIZE)
+    }
+}
+
+/// Coordinator side pool of commitments published by the signers ahead of signing.
+#[derive(Debug, Default)]
+pub struct CommitmentPool {
+    available: BTreeMap<Identifier, BTreeMap<CommitmentId, SigningCommitments>>,
+    spent: BTreeSet<(Identifier, CommitmentId)>,
+    low_watermark: usize,
+}
+
+impl CommitmentPool {
+    /// Creates a pool that asks for replenishment once a participant has fewer than `low_watermark` commitments.
+    pub fn new(low_watermark: usize) -> Self {
+        Self { low_watermark, ..Default::default() }
+    }
+
+    /// Adds a batch of published commitments, commitments that were already used are ignored. A batch of a new epoch
+    /// means the participant restarted and lost its nonces, its commitments of other epochs are discarded.
+    pub fn add(&mut self, participant: Identifier, batch: Vec<(CommitmentId, SigningCommitments)>) {
+        if let Some((id, _)) = batch.last() {
+            let epoch = commitment_epoch(*id);
+            let stale = self
+                .available
+                .get(&participant)
+                .is_some_and(|available| available.keys().any(|id| commitment_epoch(*id) != epoch));
+            if stale {
+                self.discard(participant);
+            }
+        }
+        let available = self.available.entry(participant).or_default();
+        for (id, commitments) in batch {
+            if !self.spent.contains(&(participant, id)) {
+                available.insert(id, commitments);
+            }
+        }
+    }
+
+    /// Number of unused commitments of a participant.
+    pub fn available(&self, participant: Identifier) -> usize {
+        self.available.get(&participant).map_or(0, BTreeMap::len)
+    }
+
+    /// Takes the oldest unused commitment of a participant, it is never handed out again.
+    pub fn take(&mut self, participant: Identifier) -> Result<(CommitmentId, SigningCommitments), SigningError> {
+        let (id, commitments) = self
+            .available
+            .get_mut(&participant)
+            .and_then(BTreeMap::pop_first)
+            .ok_or(SigningError::NoncePoolExhausted(participant))?;
+        self.spent.insert((participant, id));
+        Ok((id, commitments))
+    }
+
+    /// Whether a participant is running low on commitments.
+    pub fn needs_replenishment(&self, participant: Identifier) -> bool {
+        self.available(participant) < self.low_watermark
+    }
+
+    /// Drops all commitments of a participant, e.g. after it restarted and lost the matching nonces.
+    pub fn discard(&mut self, participant: Identifier) {
+        if let Some(available) = self.available.remove(&participant) {
+            self.spent.extend(available.into_keys().map(|id| (participant, id)));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    codec::{from_wire, to_wire},
+    errors::QrError,
+    offline::{MessageFile, OfflineRequest},
+};
+use frost_secp256k1_tr::SigningPackage;
+use qrcode::{render::unicode, EcLevel, QrCode};
+
+/// UR types of the payloads, a scanner tells them apart by their type.
+const REQUEST_TYPE: &str = "frost-request";
+const PACKAGE_TYPE: &str = "frost-package";
+const MESSAGE_TYPE: &str = "frost-message";
+
+/// Default bytes per fragment, a part then fits a QR code phones and webcams scan reliably.
+pub const DEFAULT_FRAGMENT_LEN: usize = 200;
+
+/// What is carried across the air gap in a UR sequence: requests and signing packages to the signers, their
+/// commitments and shares back to the coordinator.
+#[derive(Debug, Clone)]
+pub enum QrPayload {
+    Request(OfflineRequest),
+    Package(SigningPackage),
+    Message(MessageFile),
+}
+
+impl QrPayload {
+    fn ur_type(&self) -> &'static str {
+        match self {
+            QrPayload::Request(_) => REQUEST_TYPE,
+            QrPayload::Package(_) => PACKAGE_TYPE,
+            QrPayload::Message(_) => MESSAGE_TYPE,
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, QrError> {
+        Ok(match self {
+            QrPayload::Request(request) => to_wire(request)?,
+            QrPayload::Package(package) => to_wire(package)?,
+            QrPayload::Message(message) => to_wire(message)?,
+        })
+    }
+
+    fn from_bytes(ur_type: &str, bytes: &[u8]) -> Result<Self, QrError> {
+        match ur_type {
+            REQUEST_TYPE => Ok(QrPayload::Request(from_wire(bytes)?)),
+            PACKAGE_TYPE => Ok(QrPayload::Package(from_wire(bytes)?)),
+            MESSAGE_TYPE => Ok(QrPayload::Message(from_wire(bytes)?)),
+            _ => Err(QrError::UnsupportedType(ur_type.to_string())),
+        }
+    }
+}
+
+/// Fountain encoder of a payload into UR parts. The first `fragment_count` parts are the plain fragments, later ones
+/// mix several of them, so a scanner that missed some parts completes from whichever it sees next.
+pub struct UrEncoder {
+    encoder: ur::Encoder<'static>,
+}
+
+impl UrEncoder {
+    pub fn new(payload: &QrPayload, max_fragment_len: usize) -> Result<Self, QrError> {
+        let encoder = ur::Encoder::new(&payload.to_bytes()?, max_fragment_len, payload.ur_type())
+            .map_err(|e| QrError::Ur(e.to_string()))?;
+        Ok(Self { encoder })
+    }
+
+    pub fn fragment_count(&self) -> usize {
+        self.encoder.fragment_count()
+    }
+
+    pub fn next_part(&mut self) -> Result<String, QrError> {
+        self.encoder.next_part().map_err(|e| QrError::Ur(e.to_string()))
+    }
+}
+
+/// Collects scanned UR parts, in any order and with duplicates, until the payload is complete.
+#[derive(Default)]
+pub struct UrDecoder {
+    decoder: ur::Decoder,
+    ur_type: Option<String>,
+}
+
+impl UrDecoder {
+    pub fn receive(&mut self, part: &str) -> Result<(), QrError> {
+        // QR codes carry the parts upper case, which is denser in alphanumeric mode
+        let part = part.trim().to_lowercase();
+        let ur_type = part
+            .strip_prefix("ur:")
+            .and_then(|rest| rest.split('/').next())
+            .ok_or_else(|| QrError::Ur(format!("Not a UR: {part}")))?;
+        match &self.ur_type {
+            Some(expected) if expected != ur_type => {
+                return Err(QrError::Ur(format!("Part of a {ur_type} sequence while scanning a {expected}")));
+            }
+            Some(_) => {}
+            None => self.ur_type = Some(ur_type.to_string()),
+        }
+        self.decoder.receive(&part).map_err(|e| QrError::Ur(e.to_string()))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.decoder.complete()
+    }
+
+    pub fn payload(&self) -> Result<QrPayload, QrError> {
+        let bytes = self.decoder.message().map_err(|e| QrError::Ur(e.to_string()))?.ok_or(QrError::Incomplete)?;
+        QrPayload::from_bytes(self.ur_type.as_deref().unwrap_or_default(), &bytes)
+    }
+}
+
+/// Renders a UR part as a QR code of unicode half blocks for a terminal, light on dark.
+pub fn render(part: &str) -> Result<String, QrError> {
+    let code =
+        QrCode::with_error_correction_level(part.to_uppercase(), EcLevel::L).map_err(|e| QrError::Qr(e.to_string()))?;
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
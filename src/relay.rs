@@ -0,0 +1,369 @@
+use crate::{
+    envelope::{identity_keys, tagged_hash, GroupId},
+    errors::TransportError,
+    signer::SessionId,
+};
+use bitcoin::secp256k1::{schnorr, Keypair, Message as SignedMessage, Secp256k1, XOnlyPublicKey};
+use frost_secp256k1_tr::{keys::PublicKeyPackage, Identifier};
+use futures::{SinkExt, StreamExt};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+use std::{
+    collections::{hash_map::Entry, BTreeSet, HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, error::TrySendError},
+};
+use tokio_tungstenite::{
+    accept_async_with_config,
+    tungstenite::{protocol::WebSocketConfig, Message},
+};
+use tracing::{debug, info, warn};
+
+/// Frames kept for a peer that is not connected yet, the oldest are dropped first. Also the queue of a connected peer,
+/// frames for a peer that doesn't keep up are dropped.
+const PENDING_FRAMES_CAPACITY: usize = 1024;
+
+/// Frames kept for all peers that are not connected yet, further frames are dropped.
+const MAX_PENDING_FRAMES: usize = 64 * 1024;
+
+/// Frames kept for the peers of a topic that are not connected yet, further frames of the topic are dropped.
+const MAX_PENDING_FRAMES_PER_TOPIC: usize = 4 * 1024;
+
+/// Frames a peer has waiting in its group, further frames of the peer are dropped. Below the capacity of a queue, so a
+/// single peer can't push the frames of the others out of it.
+const MAX_PENDING_FRAMES_PER_SENDER: usize = 512;
+
+/// Largest WebSocket message the relay and its clients accept, a hex encoded wire frame fits with room to spare.
+pub const MAX_RELAY_MESSAGE_SIZE: usize = 512 * 1024;
+
+/// Time a client has to answer the challenge.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Domain separation tag of the signed hello.
+const HELLO_TAG: &[u8] = b"frost-demo/relay-hello";
+
+/// Channel of a signing group the relay forwards frames within, narrowed to a single session if given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Topic {
+    pub group_id: GroupId,
+    pub session_id: Option<SessionId>,
+}
+
+/// Endpoint of a topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Peer {
+    Participant(Identifier),
+    Coordinator,
+}
+
+/// Messages between the relay and its clients, sent as JSON text frames.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RelayMessage {
+    /// First message of the relay, the client signs the nonce in its hello.
+    Challenge {
+        #[serde_as(as = "Hex")]
+        nonce: [u8; 32],
+    },
+
+    /// First message of a client, subscribes it to the frames addressed to it in the topic. Participants sign with the
+    /// identity key of their share in the group's public key package, the coordinator with the key the relay was
+    /// configured with for the group.
+    Hello {
+        topic: Topic,
+        peer: Peer,
+        public_key: XOnlyPublicKey,
+        #[serde_as(as = "Option<Hex>")]
+        public_key_package: Option<Vec<u8>>,
+        signature: schnorr::Signature,
+    },
+
+    /// Encrypted payload for a peer. The relay sets `from` to the peer of the sending connection.
+    Frame {
+        from: Peer,
+        to: Peer,
+        #[serde_as(as = "Hex")]
+        payload: Vec<u8>,
+    },
+}
+
+impl RelayMessage {
+    /// Hello answering the relay's challenge, signed with the peer's key. Participants pass the group's public key
+    /// package, so the relay can derive their identity key.
+    pub fn hello(
+        nonce: &[u8; 32],
+        topic: Topic,
+        peer: Peer,
+        keypair: &Keypair,
+        public_key_package: Option<&PublicKeyPackage>,
+    ) -> Result<Self, TransportError> {
+        let public_key = keypair.x_only_public_key().0;
+        let digest = hello_digest(nonce, &topic, peer, &public_key)?;
+        let signature = Secp256k1::new().sign_schnorr_no_aux_rand(&digest, keypair);
+        let public_key_package = public_key_package
+            .map(|package| package.serialize())
+            .transpose()
+            .map_err(|e| TransportError::Connect(e.to_string()))?;
+        Ok(Self::Hello { topic, peer, public_key, public_key_package, signature })
+    }
+}
+
+/// Message signed in a hello: the challenge, the topic and peer subscribed to and the key signing it.
+fn hello_digest(
+    nonce: &[u8; 32],
+    topic: &Topic,
+    peer: Peer,
+    public_key: &XOnlyPublicKey,
+) -> Result<SignedMessage, TransportError> {
+    let fields = serde_json::to_vec(&(topic, peer, public_key)).map_err(|e| TransportError::Connect(e.to_string()))?;
+    Ok(SignedMessage::from_digest(tagged_hash(HELLO_TAG, &[nonce.as_slice(), &fields].concat())))
+}
+
+/// WebSocket limits of the relay connections.
+pub fn websocket_config() -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(MAX_RELAY_MESSAGE_SIZE),
+        max_frame_size: Some(MAX_RELAY_MESSAGE_SIZE),
+        ..Default::default()
+    }
+}
+
+/// Route of a frame, the topic and the addressee.
+type Route = (Topic, Peer);
+
+/// Number of frames waiting in total, per topic and per sender in its group.
+#[derive(Default)]
+struct PendingCounts {
+    total: usize,
+    topics: HashMap<Topic, usize>,
+    senders: HashMap<(GroupId, Peer), usize>,
+}
+
+impl PendingCounts {
+    fn is_full(&self, topic: &Topic, from: Peer) -> bool {
+        self.total >= MAX_PENDING_FRAMES
+            || self.topics.get(topic).is_some_and(|n| *n >= MAX_PENDING_FRAMES_PER_TOPIC)
+            || self.senders.get(&(topic.group_id, from)).is_some_and(|n| *n >= MAX_PENDING_FRAMES_PER_SENDER)
+    }
+
+    fn add(&mut self, topic: Topic, from: Peer) {
+        self.total += 1;
+        *self.topics.entry(topic).or_default() += 1;
+        *self.senders.entry((topic.group_id, from)).or_default() += 1;
+    }
+
+    fn remove(&mut self, topic: Topic, from: Peer) {
+        fn decrement<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, usize>, key: K) {
+            if let Entry::Occupied(mut count) = counts.entry(key) {
+                *count.get_mut() -= 1;
+                if *count.get() == 0 {
+                    count.remove();
+                }
+            }
+        }
+        self.total -= 1;
+        decrement(&mut self.topics, topic);
+        decrement(&mut self.senders, (topic.group_id, from));
+    }
+}
+
+/// Connected peers, the frames waiting for peers that are not connected yet with their senders, the members of the
+/// groups learned from the participants' hellos and the coordinator keys.
+struct Relay {
+    connected: HashMap<Route, mpsc::Sender<Message>>,
+    pending: HashMap<Route, VecDeque<(Peer, Message)>>,
+    pending_counts: PendingCounts,
+    members: HashMap<GroupId, BTreeSet<Identifier>>,
+    coordinators: HashMap<GroupId, XOnlyPublicKey>,
+}
+
+impl Relay {
+    fn new(coordinators: HashMap<GroupId, XOnlyPublicKey>) -> Self {
+        Self {
+            connected: HashMap::new(),
+            pending: HashMap::new(),
+            pending_counts: PendingCounts::default(),
+            members: HashMap::new(),
+            coordinators,
+        }
+    }
+
+    /// Checks that the hello is signed for the challenge by the key of the peer it claims to be.
+    fn authenticate(&mut self, nonce: &[u8; 32], hello: RelayMessage) -> Result<Route, TransportError> {
+        let RelayMessage::Hello { topic, peer, public_key, public_key_package, signature } = hello else {
+            return Err(TransportError::Connect("Expected a hello from the client".into()));
+        };
+        let digest = hello_digest(nonce, &topic, peer, &public_key)?;
+        Secp256k1::verification_only()
+            .verify_schnorr(&signature, &digest, &public_key)
+            .map_err(|_| TransportError::Connect(format!("Invalid hello signature of {peer:?}")))?;
+
+        match peer {
+            Peer::Participant(id) => {
+                let bytes = public_key_package
+                    .ok_or_else(|| TransportError::Connect("Hello without the group's public key package".into()))?;
+                let package =
+                    PublicKeyPackage::deserialize(&bytes).map_err(|e| TransportError::Connect(e.to_string()))?;
+                if GroupId::new(&package).ok() != Some(topic.group_id) {
+                    return Err(TransportError::Connect("Public key package of another group".into()));
+                }
+                let identity = identity_keys(&package).map_err(|e| TransportError::Connect(e.to_string()))?;
+                if identity.get(&id).map(|key| key.x_only_public_key().0) != Some(public_key) {
+                    return Err(TransportError::UnknownParticipant(id));
+                }
+                self.members.entry(topic.group_id).or_insert_with(|| identity.into_keys().collect());
+            }
+            Peer::Coordinator => match self.coordinators.get(&topic.group_id) {
+                Some(key) if *key == public_key => {}
+                Some(_) => {
+                    return Err(TransportError::Connect("Coordinator key differs from the configured one".into()))
+                }
+                None => {
+                    return Err(TransportError::Connect(format!("No coordinator key for group {}", topic.group_id)))
+                }
+            },
+        }
+        Ok((topic, peer))
+    }
+
+    /// Registers an authenticated connection, a reconnection of the same peer replaces its earlier connection.
+    fn connect(&mut self, route: Route, connection: mpsc::Sender<Message>) {
+        let pending = self.pending.remove(&route).unwrap_or_default();
+        for (from, frame) in pending {
+            self.pending_counts.remove(route.0, from);
+            let _ = connection.try_send(frame);
+        }
+        self.connected.insert(route, connection);
+    }
+
+    fn disconnect(&mut self, route: &Route, connection: &mpsc::Sender<Message>) {
+        if self.connected.get(route).is_some_and(|c| c.same_channel(connection)) {
+            self.connected.remove(route);
+        }
+    }
+
+    /// Whether the peer can receive frames in the group: a member other than the sender, or its configured coordinator.
+    fn is_addressee(&self, group_id: &GroupId, from: Peer, to: Peer) -> bool {
+        match to {
+            _ if to == from => false,
+            Peer::Participant(id) => self.members.get(group_id).is_some_and(|members| members.contains(&id)),
+            Peer::Coordinator => self.coordinators.contains_key(group_id),
+        }
+    }
+
+    /// Hands a frame of `from` to its addressee in the sender's topic, or queues it until the addressee connects.
+    fn forward(&mut self, from: Peer, route: Route, frame: Message) {
+        if !self.is_addressee(&route.0.group_id, from, route.1) {
+            warn!(?from, to = ?route.1, "Dropping frame for a peer outside the group.");
+            return;
+        }
+        let frame = match self.connected.get(&route) {
+            Some(connection) => match connection.try_send(frame) {
+                Ok(()) => return,
+                Err(TrySendError::Full(_)) => {
+                    warn!(peer = ?route.1, "Dropping frame for a peer that doesn't keep up.");
+                    return;
+                }
+                Err(TrySendError::Closed(frame)) => {
+                    self.connected.remove(&route);
+                    frame
+                }
+            },
+            None => frame,
+        };
+        if self.pending_counts.is_full(&route.0, from) {
+            warn!(?from, peer = ?route.1, "Relay queue is full, dropping frame.");
+            return;
+        }
+        let pending = self.pending.entry(route).or_default();
+        if pending.len() == PENDING_FRAMES_CAPACITY {
+            if let Some((dropped_from, _)) = pending.pop_front() {
+                self.pending_counts.remove(route.0, dropped_from);
+            }
+        }
+        pending.push_back((from, frame));
+        self.pending_counts.add(route.0, from);
+    }
+}
+
+fn lock(relay: &Mutex<Relay>) -> MutexGuard<'_, Relay> {
+    relay.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Runs the relay on the listener: forwards the frames of every topic to their addressees. Payloads are end-to-end
+/// encrypted between the peers, the relay only sees who talks to whom. Only groups with a key in `coordinators` have a
+/// coordinator, it must sign its hello with that key.
+pub async fn serve(listener: TcpListener, coordinators: HashMap<GroupId, XOnlyPublicKey>) -> std::io::Result<()> {
+    info!(addr = %listener.local_addr()?, coordinators = coordinators.len(), "Relay listening.");
+    let relay = Arc::new(Mutex::new(Relay::new(coordinators)));
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let relay = relay.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(relay, stream).await {
+                debug!(%addr, "Connection closed: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_connection(relay: Arc<Mutex<Relay>>, stream: TcpStream) -> Result<(), TransportError> {
+    let websocket = accept_async_with_config(stream, Some(websocket_config()))
+        .await
+        .map_err(|e| TransportError::Connect(e.to_string()))?;
+    let (mut sink, mut stream) = websocket.split();
+
+    let mut nonce = [0u8; 32];
+    OsRng.fill_bytes(&mut nonce);
+    let challenge =
+        serde_json::to_string(&RelayMessage::Challenge { nonce }).map_err(|e| TransportError::Send(e.to_string()))?;
+    sink.send(Message::Text(challenge)).await.map_err(|e| TransportError::Send(e.to_string()))?;
+    let hello = match tokio::time::timeout(HELLO_TIMEOUT, stream.next()).await {
+        Ok(Some(Ok(Message::Text(text)))) => {
+            serde_json::from_str(&text).map_err(|e| TransportError::Receive(format!("Malformed hello: {e}")))?
+        }
+        Ok(_) => return Err(TransportError::Receive("Connection closed before the hello".into())),
+        Err(_) => return Err(TransportError::Receive("No hello before the deadline".into())),
+    };
+    let (topic, peer) = lock(&relay).authenticate(&nonce, hello)?;
+    debug!(?topic, ?peer, "Peer connected.");
+
+    let (connection, mut frames) = mpsc::channel(PENDING_FRAMES_CAPACITY);
+    lock(&relay).connect((topic, peer), connection.clone());
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = frames.recv().await {
+            if sink.send(frame).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(message) = stream.next().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        match serde_json::from_str(&text) {
+            Ok(RelayMessage::Frame { to, payload, .. }) => {
+                // The sender is the peer of the connection, whatever the frame claims
+                let frame = serde_json::to_string(&RelayMessage::Frame { from: peer, to, payload })
+                    .map_err(|e| TransportError::Send(e.to_string()))?;
+                lock(&relay).forward(peer, (topic, to), Message::Text(frame));
+            }
+            _ => warn!(?peer, "Dropping unexpected relay message."),
+        }
+    }
+
+    lock(&relay).disconnect(&(topic, peer), &connection);
+    writer.abort();
+    debug!(?topic, ?peer, "Peer disconnected.");
+    Ok(())
+}
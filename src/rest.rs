@@ -0,0 +1,279 @@
+use crate::{
+    chain::Utxo,
+    errors::{ApiError, BitcoinError, SigningError},
+    service::{ApiToken, GroupInfo, SessionStatus, SigningService, SpendRequest},
+    signer::SessionId,
+};
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+use std::str::FromStr;
+use tokio::net::TcpListener;
+use tracing::info;
+use utoipa::{
+    openapi::{
+        self,
+        security::{Http, HttpAuthScheme, SecurityScheme},
+    },
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+
+/// OpenAPI document of the REST API.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "FROST signer API", description = "Threshold signing of Bitcoin spends by the group."),
+    paths(group_info, address, list_utxos, create_spend, sign_psbt, session_status, broadcast),
+    modifiers(&BearerAuth),
+    security(("bearer" = []))
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AddressQuery {
+    /// Network of the address, the served network if not given.
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressResponse {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateSpendRequest {
+    /// Spent group UTXO as "txid:vout", its value and script are fetched from the node.
+    pub utxo: String,
+    pub to: String,
+    /// Amount in satoshis, the change goes back to the group address.
+    pub amount: u64,
+    /// Broadcasts the transaction once signed.
+    #[serde(default)]
+    pub broadcast: bool,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SignPsbtRequest {
+    /// Hex encoded PSBT with a single input spending from the group address.
+    #[serde_as(as = "Hex")]
+    #[schema(value_type = String)]
+    pub psbt: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SessionCreated {
+    #[schema(value_type = String)]
+    pub session_id: SessionId,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BroadcastResponse {
+    pub txid: String,
+}
+
+fn status_code(error: &ApiError) -> StatusCode {
+    match error {
+        ApiError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        ApiError::UnknownSession(_) => StatusCode::NOT_FOUND,
+        ApiError::NotSigned(_) => StatusCode::CONFLICT,
+        ApiError::NoBackend => StatusCode::SERVICE_UNAVAILABLE,
+        ApiError::Signing(error) => match error {
+            SigningError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            SigningError::NotEnoughSigners => StatusCode::SERVICE_UNAVAILABLE,
+            SigningError::PolicyViolation(_) | SigningError::ApprovalRejected { .. } => StatusCode::FORBIDDEN,
+            SigningError::TooManySessions(_) | SigningError::NoncePoolExhausted(_) => StatusCode::TOO_MANY_REQUESTS,
+            SigningError::Aborted { .. }
+            | SigningError::SighashMismatch { .. }
+            | SigningError::InvalidState(_)
+            | SigningError::InvalidTransition { .. }
+            | SigningError::NonceReuse(_) => StatusCode::CONFLICT,
+            SigningError::Bitcoin(error) => bitcoin_status_code(error),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+        ApiError::Bitcoin(error) => bitcoin_status_code(error),
+        ApiError::KeyData(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn bitcoin_status_code(error: &BitcoinError) -> StatusCode {
+    match error {
+        BitcoinError::Address(_) | BitcoinError::Utxo(_) | BitcoinError::Spend(_) => StatusCode::BAD_REQUEST,
+        BitcoinError::Client(_) => StatusCode::BAD_GATEWAY,
+        BitcoinError::Sighash(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        error_response(status_code(&self), self.to_string())
+    }
+}
+
+fn parse_session_id(id: &str) -> Result<SessionId, ApiError> {
+    SessionId::from_str(id).map_err(|e| ApiError::InvalidRequest(format!("Invalid session id: {e}")))
+}
+
+/// Threshold, participants and public key of the group.
+#[utoipa::path(get, path = "/v1/group", responses(
+    (status = 200, body = GroupInfo),
+    (status = 401, body = ErrorResponse),
+))]
+async fn group_info(State(service): State<SigningService>) -> Result<Json<GroupInfo>, ApiError> {
+    Ok(Json(service.group_info()?))
+}
+
+/// Group address on a network.
+#[utoipa::path(get, path = "/v1/address", params(AddressQuery), responses(
+    (status = 200, body = AddressResponse),
+    (status = 400, body = ErrorResponse),
+    (status = 401, body = ErrorResponse),
+))]
+async fn address(
+    State(service): State<SigningService>,
+    Query(query): Query<AddressQuery>,
+) -> Result<Json<AddressResponse>, ApiError> {
+    let address = service.address(query.network.as_deref())?;
+    Ok(Json(AddressResponse { address: address.to_string() }))
+}
+
+/// Confirmed unspent outputs of the group address.
+#[utoipa::path(get, path = "/v1/utxos", responses(
+    (status = 200, body = Vec<Utxo>),
+    (status = 401, body = ErrorResponse),
+    (status = 502, body = ErrorResponse),
+    (status = 503, body = ErrorResponse),
+))]
+async fn list_utxos(State(service): State<SigningService>) -> Result<Json<Vec<Utxo>>, ApiError> {
+    Ok(Json(service.list_unspent().await?))
+}
+
+/// Starts signing a spend of a group UTXO.
+#[utoipa::path(post, path = "/v1/spends", request_body = CreateSpendRequest, responses(
+    (status = 202, body = SessionCreated),
+    (status = 400, body = ErrorResponse),
+    (status = 401, body = ErrorResponse),
+    (status = 429, body = ErrorResponse),
+    (status = 503, body = ErrorResponse),
+))]
+async fn create_spend(
+    State(service): State<SigningService>,
+    Json(request): Json<CreateSpendRequest>,
+) -> Result<(StatusCode, Json<SessionCreated>), ApiError> {
+    let request =
+        SpendRequest { utxo: request.utxo, to: request.to, amount: request.amount, broadcast: request.broadcast };
+    let session_id = service.create_spend(request).await?;
+    Ok((StatusCode::ACCEPTED, Json(SessionCreated { session_id })))
+}
+
+/// Starts signing the single input of a PSBT.
+#[utoipa::path(post, path = "/v1/psbts", request_body = SignPsbtRequest, responses(
+    (status = 202, body = SessionCreated),
+    (status = 400, body = ErrorResponse),
+    (status = 401, body = ErrorResponse),
+    (status = 429, body = ErrorResponse),
+))]
+async fn sign_psbt(
+    State(service): State<SigningService>,
+    Json(request): Json<SignPsbtRequest>,
+) -> Result<(StatusCode, Json<SessionCreated>), ApiError> {
+    let session_id = service.sign_psbt(&request.psbt)?;
+    Ok((StatusCode::ACCEPTED, Json(SessionCreated { session_id })))
+}
+
+/// Status of a session, with the signed transaction once complete.
+#[utoipa::path(get, path = "/v1/sessions/{id}", params(("id" = String, Path, description = "Hex encoded session id")),
+    responses(
+        (status = 200, body = SessionStatus),
+        (status = 400, body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+async fn session_status(
+    State(service): State<SigningService>,
+    Path(id): Path<String>,
+) -> Result<Json<SessionStatus>, ApiError> {
+    Ok(Json(service.status(parse_session_id(&id)?)?))
+}
+
+/// Broadcasts the signed transaction of a complete session.
+#[utoipa::path(post, path = "/v1/sessions/{id}/broadcast", params(("id" = String, Path, description = "Hex encoded session id")),
+    responses(
+        (status = 200, body = BroadcastResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+        (status = 502, body = ErrorResponse),
+        (status = 503, body = ErrorResponse),
+    )
+)]
+async fn broadcast(
+    State(service): State<SigningService>,
+    Path(id): Path<String>,
+) -> Result<Json<BroadcastResponse>, ApiError> {
+    let session_id = parse_session_id(&id)?;
+    let txid = service.broadcast(session_id).await?;
+    Ok(Json(BroadcastResponse { txid: txid.to_string() }))
+}
+
+async fn openapi_document() -> Json<openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Rejects requests without the bearer token.
+async fn authorize(State(token): State<ApiToken>, request: Request, next: Next) -> Response {
+    let authorization = request.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    if !token.authorizes(authorization) {
+        return error_response(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token".into());
+    }
+    next.run(request).await
+}
+
+/// Routes of the API, all but the OpenAPI document require the bearer token.
+pub fn router(service: SigningService, token: ApiToken) -> Router {
+    let api = Router::new()
+        .route("/v1/group", get(group_info))
+        .route("/v1/address", get(address))
+        .route("/v1/utxos", get(list_utxos))
+        .route("/v1/spends", post(create_spend))
+        .route("/v1/psbts", post(sign_psbt))
+        .route("/v1/sessions/:id", get(session_status))
+        .route("/v1/sessions/:id/broadcast", post(broadcast))
+        .route_layer(middleware::from_fn_with_state(token, authorize))
+        .with_state(service);
+    Router::new().route("/openapi.json", get(openapi_document)).merge(api)
+}
+
+/// Serves the REST API on the listener.
+pub async fn serve(listener: TcpListener, service: SigningService, token: ApiToken) -> std::io::Result<()> {
+    if let Ok(addr) = listener.local_addr() {
+        info!(%addr, "REST API listening.");
+    }
+    axum::serve(listener, router(service, token)).await
+}
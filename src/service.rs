@@ -0,0 +1,416 @@
+use crate::{
+    bitcoin::{create_unsigned_transaction, create_unsigned_transaction_with_fee, parse_utxo},
+    chain::{estimate_spend_fee, ChainBackend, Utxo},
+    envelope::GroupId,
+    errors::{ApiError, BitcoinError, SigningError},
+    keys::KeyData,
+    signer::{
+        run_local_session, setup_configured_signers, CeremonyConfig, LocalSigners, SessionId, SigningPhase, Transition,
+    },
+    transport::SessionRouter,
+};
+use bitcoin::{
+    consensus,
+    hashes::{sha256, Hash},
+    psbt::Psbt,
+    taproot, Address, Amount, FeeRate, Network, Transaction, TxOut, Txid,
+};
+use futures::{future, stream::select_all, FutureExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Session events buffered per subscriber, slower subscribers miss the oldest ones.
+pub const SESSION_EVENTS_CAPACITY: usize = 256;
+
+/// Sessions signing at the same time, more are refused until one finishes.
+pub const MAX_RUNNING_SESSIONS: usize = 16;
+
+/// How long the status of a finished session is kept.
+pub const FINISHED_SESSION_TTL: Duration = Duration::from_secs(3600);
+
+/// Shortest bearer token the APIs accept.
+pub const MIN_TOKEN_LEN: usize = 16;
+
+/// Bearer token of the gRPC and REST APIs, kept as its hash so a comparison leaks nothing about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiToken(sha256::Hash);
+
+impl ApiToken {
+    pub fn new(token: &str) -> Result<Self, ApiError> {
+        if token.trim().len() < MIN_TOKEN_LEN {
+            return Err(ApiError::InvalidRequest(format!(
+                "The API token must have at least {MIN_TOKEN_LEN} characters"
+            )));
+        }
+        Ok(Self(sha256::Hash::hash(token.as_bytes())))
+    }
+
+    /// Whether the value of an `Authorization` header carries the token.
+    pub fn authorizes(&self, authorization: Option<&str>) -> bool {
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| sha256::Hash::hash(given.as_bytes()) == self.0)
+    }
+}
+
+/// Threshold, participants and public key of the group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GroupInfo {
+    /// Hex encoded hash of the public key package.
+    pub group_id: String,
+    pub threshold: u16,
+    pub total: u16,
+    /// Hex encoded compressed group verifying key.
+    pub verifying_key: String,
+    /// Hex encoded participant identifiers.
+    pub participants: Vec<String>,
+}
+
+/// Spend of a group UTXO, paying an amount to an address and the change back to the group.
+#[derive(Debug, Clone)]
+pub struct SpendRequest {
+    /// Spent group UTXO, its value and script are fetched from the backend so a caller can't make them up.
+    pub utxo: String,
+    pub to: String,
+    pub amount: u64,
+    /// Broadcasts the signed transaction through the backend.
+    pub broadcast: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    Running,
+    Complete,
+    Failed,
+}
+
+/// Status of a session started through the service.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SessionStatus {
+    #[schema(value_type = String)]
+    pub session_id: SessionId,
+    pub state: SessionState,
+    /// Latest phase a signer of the session reached.
+    #[schema(value_type = Option<String>)]
+    pub phase: Option<SigningPhase>,
+    /// Hex encoded signed transaction, once complete.
+    #[serde_as(as = "Option<Hex>")]
+    #[schema(value_type = Option<String>)]
+    pub signed_transaction: Option<Vec<u8>>,
+    #[schema(value_type = Option<String>)]
+    pub txid: Option<Txid>,
+    /// Hex encoded finalized PSBT of a PSBT session, once complete.
+    #[serde_as(as = "Option<Hex>")]
+    #[schema(value_type = Option<String>)]
+    pub psbt: Option<Vec<u8>>,
+    pub error: Option<String>,
+}
+
+impl SessionStatus {
+    fn running(session_id: SessionId) -> Self {
+        Self {
+            session_id,
+            state: SessionState::Running,
+            phase: None,
+            signed_transaction: None,
+            txid: None,
+            psbt: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    Transition(Transition),
+    Finished(SessionStatus),
+}
+
+impl SessionEvent {
+    pub fn session_id(&self) -> SessionId {
+        match self {
+            SessionEvent::Transition(transition) => transition.session_id,
+            SessionEvent::Finished(status) => status.session_id,
+        }
+    }
+}
+
+/// Status of a session and when it finished, none while running.
+struct TrackedSession {
+    status: SessionStatus,
+    finished: Option<Instant>,
+}
+
+/// Coordinator behind the APIs: signs spends and PSBTs with local in-memory signers in the background and keeps the
+/// status of every session.
+#[derive(Clone)]
+pub struct SigningService {
+    key_data: Arc<KeyData>,
+    network: Network,
+    config: CeremonyConfig,
+    /// Signers shared by all sessions, so they share their state store and policy.
+    signers: Arc<LocalSigners>,
+    router: SessionRouter,
+    backend: Option<Arc<dyn ChainBackend>>,
+    estimate_fee: bool,
+    sessions: Arc<Mutex<HashMap<SessionId, TrackedSession>>>,
+    max_running: usize,
+    finished_ttl: Duration,
+    events: broadcast::Sender<SessionEvent>,
+}
+
+impl SigningService {
+    /// Sets up the signers of the group, recovering the sessions journaled in the state directory.
+    pub fn new(key_data: KeyData, network: Network, config: CeremonyConfig) -> Result<Self, SigningError> {
+        let config = CeremonyConfig { network, ..config };
+        let (signers, transport) = setup_configured_signers(&key_data, &config)?;
+        Ok(Self {
+            key_data: Arc::new(key_data),
+            network,
+            signers: Arc::new(LocalSigners::spawn(signers, &config)),
+            router: SessionRouter::spawn(transport),
+            config,
+            backend: None,
+            estimate_fee: false,
+            sessions: Arc::default(),
+            max_running: MAX_RUNNING_SESSIONS,
+            finished_ttl: FINISHED_SESSION_TTL,
+            events: broadcast::channel(SESSION_EVENTS_CAPACITY).0,
+        })
+    }
+
+    /// Pays the backend's fee estimate on spends, capped by the policy's max fee rate, instead of the default fee.
+    pub fn with_fee_estimation(mut self) -> Self {
+        self.estimate_fee = true;
+        self
+    }
+
+    /// Caps the sessions signing at the same time and sets how long finished ones are kept.
+    pub fn with_session_limits(mut self, max_running: usize, finished_ttl: Duration) -> Self {
+        self.max_running = max_running;
+        self.finished_ttl = finished_ttl;
+        self
+    }
+
+    /// Backend the spent outputs are fetched from and signed transactions are broadcast to.
+    pub fn with_backend(mut self, backend: Arc<dyn ChainBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, HashMap<SessionId, TrackedSession>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn backend(&self) -> Result<&dyn ChainBackend, ApiError> {
+        self.backend.as_deref().ok_or(ApiError::NoBackend)
+    }
+
+    pub fn group_info(&self) -> Result<GroupInfo, ApiError> {
+        let public = &self.key_data.public;
+        let verifying_key = public.verifying_key().serialize().map_err(SigningError::from)?;
+        Ok(GroupInfo {
+            group_id: GroupId::new(public)?.to_string(),
+            threshold: self.key_data.threshold,
+            total: self.key_data.total,
+            verifying_key: hex::encode(verifying_key),
+            participants: public.verifying_shares().keys().map(|id| hex::encode(id.serialize())).collect(),
+        })
+    }
+
+    /// Group address on the network, the service's network if none is given.
+    pub fn address(&self, network: Option<&str>) -> Result<Address, ApiError> {
+        let network = match network {
+            None | Some("") => self.network,
+            Some(network) => Network::from_str(network).map_err(|e| ApiError::InvalidRequest(e.to_string()))?,
+        };
+        Ok(self.key_data.address(network)?)
+    }
+
+    /// Unspent outputs of the group address, as seen by the backend.
+    pub async fn list_unspent(&self) -> Result<Vec<Utxo>, ApiError> {
+        Ok(self.backend()?.list_unspent(&self.address(None)?.script_pubkey()).await?)
+    }
+
+    /// Starts signing a spend, paying the backend's fee estimate if enabled.
+    pub async fn create_spend(&self, request: SpendRequest) -> Result<SessionId, ApiError> {
+        let utxo = parse_utxo(&request.utxo)?;
+        let to = Address::from_str(&request.to)
+            .and_then(|address| address.require_network(self.network))
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid destination address: {e}")))?;
+        let prevout = self.backend()?.get_tx_out(&utxo).await?;
+        let change = self.address(None)?;
+        let amount = Amount::from_sat(request.amount);
+        let transaction = match self.backend.as_deref() {
+            Some(backend) if self.estimate_fee => {
+                let max_fee_rate = self.config.policy.as_ref().and_then(|policy| policy.max_fee_rate);
+                let fee = estimate_spend_fee(backend, max_fee_rate.and_then(FeeRate::from_sat_per_vb)).await;
+                create_unsigned_transaction_with_fee(utxo, &prevout, to, amount, change, fee)?
+            }
+            _ => create_unsigned_transaction(utxo, &prevout, to, amount, change)?,
+        };
+        self.start(transaction, vec![prevout], None, request.broadcast)
+    }
+
+    pub fn sign_psbt(&self, psbt: &[u8]) -> Result<SessionId, ApiError> {
+        let psbt = Psbt::deserialize(psbt).map_err(|e| ApiError::InvalidRequest(format!("Invalid PSBT: {e}")))?;
+        // The ceremony signs a single key path input
+        let [input] = psbt.inputs.as_slice() else {
+            return Err(ApiError::InvalidRequest("The PSBT must have exactly one input".into()));
+        };
+        let prevout = input
+            .witness_utxo
+            .clone()
+            .ok_or_else(|| ApiError::InvalidRequest("The input has no witness UTXO".into()))?;
+        if prevout.script_pubkey != self.address(None)?.script_pubkey() {
+            return Err(ApiError::InvalidRequest("The input doesn't spend from the group address".into()));
+        }
+        self.start(psbt.unsigned_tx.clone(), vec![prevout], Some(psbt), false)
+    }
+
+    pub fn status(&self, session_id: SessionId) -> Result<SessionStatus, ApiError> {
+        self.sessions()
+            .get(&session_id)
+            .map(|session| session.status.clone())
+            .ok_or(ApiError::UnknownSession(session_id))
+    }
+
+    /// Broadcasts the signed transaction of a complete session.
+    pub async fn broadcast(&self, session_id: SessionId) -> Result<Txid, ApiError> {
+        let status = self.status(session_id)?;
+        let signed = status.signed_transaction.ok_or(ApiError::NotSigned(session_id))?;
+        let transaction: Transaction =
+            consensus::deserialize(&signed).map_err(|e| ApiError::Bitcoin(BitcoinError::Spend(e.to_string())))?;
+        Ok(self.backend()?.broadcast(&transaction).await?)
+    }
+
+    /// Subscribes to the events of all sessions.
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Starts a session signing the transaction in the background, its status is kept and its events published.
+    fn start(
+        &self,
+        transaction: Transaction,
+        prev_tx_outs: Vec<TxOut>,
+        psbt: Option<Psbt>,
+        broadcast: bool,
+    ) -> Result<SessionId, ApiError> {
+        let session_id = SessionId::random();
+        {
+            let mut sessions = self.sessions();
+            let ttl = self.finished_ttl;
+            sessions.retain(|_, session| !session.finished.is_some_and(|finished| finished.elapsed() >= ttl));
+            if sessions.values().filter(|session| session.finished.is_none()).count() >= self.max_running {
+                return Err(SigningError::TooManySessions(self.max_running).into());
+            }
+            sessions.insert(session_id, TrackedSession { status: SessionStatus::running(session_id), finished: None });
+        }
+        let transport = Arc::new(self.router.open(session_id));
+
+        // Subscribed before the session starts, so no transition is missed
+        let signers = self.signers.signers().values();
+        let mut transitions = select_all(signers.map(|signer| BroadcastStream::new(signer.subscribe())))
+            .filter_map(move |received| future::ready(received.ok().filter(|t| t.session_id == session_id)));
+        let service = self.clone();
+        tokio::spawn(async move {
+            let ceremony = run_local_session(
+                &service.key_data,
+                &service.signers,
+                transport,
+                session_id,
+                transaction,
+                &prev_tx_outs,
+                &service.config,
+            );
+            tokio::pin!(ceremony);
+            let result = loop {
+                tokio::select! {
+                    result = &mut ceremony => break result,
+                    Some(transition) = transitions.next() => service.publish_transition(transition),
+                }
+            };
+            while let Some(Some(transition)) = transitions.next().now_or_never() {
+                service.publish_transition(transition);
+            }
+            service.finish(session_id, result, psbt, broadcast).await;
+        });
+        info!(%session_id, "Started signing session.");
+        Ok(session_id)
+    }
+
+    fn publish_transition(&self, transition: Transition) {
+        if let Some(session) = self.sessions().get_mut(&transition.session_id) {
+            session.status.phase = Some(transition.to);
+        }
+        // Sending only fails without subscribers
+        let _ = self.events.send(SessionEvent::Transition(transition));
+    }
+
+    async fn finish(
+        &self,
+        session_id: SessionId,
+        result: Result<Transaction, SigningError>,
+        psbt: Option<Psbt>,
+        broadcast: bool,
+    ) {
+        let mut status = SessionStatus::running(session_id);
+        let result = match result {
+            Ok(signed) => self.complete(&mut status, signed, psbt, broadcast).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(()) => status.state = SessionState::Complete,
+            Err(e) => {
+                warn!(%session_id, "Signing session failed: {e}");
+                status.state = SessionState::Failed;
+                status.error = Some(e.to_string());
+            }
+        }
+        {
+            let mut sessions = self.sessions();
+            status.phase = sessions.get(&session_id).and_then(|running| running.status.phase);
+            sessions.insert(session_id, TrackedSession { status: status.clone(), finished: Some(Instant::now()) });
+        }
+        let _ = self.events.send(SessionEvent::Finished(status));
+    }
+
+    /// Fills in the signed transaction and the finalized PSBT, broadcasting the transaction if asked to.
+    async fn complete(
+        &self,
+        status: &mut SessionStatus,
+        signed: Transaction,
+        psbt: Option<Psbt>,
+        broadcast: bool,
+    ) -> Result<(), ApiError> {
+        status.signed_transaction = Some(consensus::serialize(&signed));
+        status.txid = Some(signed.compute_txid());
+        if let Some(mut psbt) = psbt {
+            let witness = signed.input[0].witness.clone();
+            psbt.inputs[0].tap_key_sig = witness.nth(0).and_then(|sig| taproot::Signature::from_slice(sig).ok());
+            psbt.inputs[0].final_script_witness = Some(witness);
+            status.psbt = Some(psbt.serialize());
+        }
+        if broadcast {
+            self.backend()?.broadcast(&signed).await?;
+        }
+        Ok(())
+    }
+}
@@ -1,328 +1,2048 @@
 use crate::{
+    approval::{ApprovalDecision, ApprovalQueue, FileApprovalQueue, PendingRequest, TransactionSummary},
+    audit::{AuditEvent, AuditLog},
     bitcoin::compute_sighash,
-    errors::SigningError,
-    keys::KeyData,
+    envelope::{Envelope, ReplayGuard, Sealer},
+    errors::{EnvelopeError, SigningError},
+    keys::{group_address, participant_index, KeyData},
+    metrics,
+    offline::OfflineRequest,
+    policy::{PolicyEngine, SignerPolicy},
+    preprocess::{CommitmentId, CommitmentPool, NoncePool},
+    store::{FileJournal, JournalEntry, SessionRecord, SpendRecord, StateStore},
     transport::{InMemoryTransport, Transport},
+    websocket::{CoordinatorMessage, WebSocketTransport},
+};
+use bitcoin::{
+    hashes::{sha256, Hash},
+    Network, ScriptBuf, Transaction, TxOut,
 };
-use bitcoin::{Transaction, TxOut};
 use frost_secp256k1_tr as frost;
 use frost_secp256k1_tr::{Ciphersuite, Identifier, SigningPackage};
-use rand::rngs::OsRng;
+use futures::future::try_join_all;
+use hex::FromHex;
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
 use std::{
-    collections::{BTreeMap, HashMap},
-    ops::DerefMut,
-    sync::{Arc, Mutex},
-    time::{Duration, Instant},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot},
+    time::timeout,
 };
-use tokio::time::timeout;
 use tracing::{debug, info, instrument, warn};
+use zeroize::Zeroize;
+
+/// 256-bit random session identifier.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SessionId(#[serde_as(as = "Hex")] [u8; 32]);
+
+impl SessionId {
+    /// Generates a new random session identifier.
+    pub fn random() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for SessionId {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for SessionId {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <[u8; 32]>::from_hex(s).map(Self)
+    }
+}
+
+/// Interval at which signers check for operator decisions.
+const APPROVAL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Interval at which signers collect finished and expired sessions.
+const GC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Message transmitted between participants.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SigningMessage {
+    NonceCommitment(SessionId, Identifier, Box<frost::round1::SigningCommitments>),
+    SignatureShare(SessionId, Identifier, frost::round2::SignatureShare),
+    /// Batch of commitments to preprocessed nonces, published ahead of any session.
+    PreprocessedCommitments(Identifier, Vec<(CommitmentId, frost::round1::SigningCommitments)>),
+    /// Sender gave up on the session for the given reason.
+    Abort(SessionId, Identifier, String),
+}
+
+impl SigningMessage {
+    /// Session the message belongs to, preprocessed commitments are not bound to a session.
+    pub fn session_id(&self) -> Option<SessionId> {
+        match self {
+            SigningMessage::NonceCommitment(session_id, ..)
+            | SigningMessage::SignatureShare(session_id, ..)
+            | SigningMessage::Abort(session_id, ..) => Some(*session_id),
+            SigningMessage::PreprocessedCommitments(..) => None,
+        }
+    }
+
+    /// Participant that sent the message.
+    pub fn sender(&self) -> Identifier {
+        match self {
+            SigningMessage::NonceCommitment(_, sender, _)
+            | SigningMessage::SignatureShare(_, sender, _)
+            | SigningMessage::PreprocessedCommitments(sender, _)
+            | SigningMessage::Abort(_, sender, _) => *sender,
+        }
+    }
+
+    /// Protocol round of the message, messages sent outside of a session and aborts, which may be sent in any round,
+    /// belong to round 0.
+    pub fn round(&self) -> u8 {
+        match self {
+            SigningMessage::PreprocessedCommitments(..) | SigningMessage::Abort(..) => 0,
+            SigningMessage::NonceCommitment(..) => 1,
+            SigningMessage::SignatureShare(..) => 2,
+        }
+    }
+
+    /// Short name of the message type.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SigningMessage::NonceCommitment(..) => "nonce_commitment",
+            SigningMessage::SignatureShare(..) => "signature_share",
+            SigningMessage::PreprocessedCommitments(..) => "preprocessed_commitments",
+            SigningMessage::Abort(..) => "abort",
+        }
+    }
+}
+
+/// Unsigned transaction and the outputs it spends, given to every signer so it computes the sighash itself instead of
+/// signing a digest chosen by the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningRequest {
+    pub transaction: Transaction,
+    pub prev_tx_outs: Vec<TxOut>,
+}
+
+impl SigningRequest {
+    pub fn new(transaction: Transaction, prev_tx_outs: &[TxOut]) -> Self {
+        Self { transaction, prev_tx_outs: prev_tx_outs.to_vec() }
+    }
+
+    /// BIP-341 key path sighash of the transaction.
+    pub fn sighash(&self) -> Result<[u8; 32], SigningError> {
+        let sighash = compute_sighash(&mut self.transaction.clone(), &self.prev_tx_outs)?;
+        let digest: &[u8; 32] = sighash.as_ref();
+        Ok(*digest)
+    }
+
+    /// Checks that the signing package signs exactly this transaction.
+    pub fn verify_signing_package(&self, signing_package: &SigningPackage) -> Result<(), SigningError> {
+        let sighash = self.sighash()?;
+        if signing_package.message()[..] != sighash[..] {
+            return Err(SigningError::SighashMismatch {
+                expected: hex::encode(sighash),
+                received: hex::encode(signing_package.message()),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Signing protocol rounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningRound {
+    /// Round 1: nonce commitments.
+    Commitments,
+
+    /// Operator approval of the signing request.
+    Approval,
+
+    /// Round 2: signature shares.
+    Shares,
+}
+
+impl SigningRound {
+    /// Protocol round number of the messages exchanged in or after this round.
+    pub fn number(&self) -> u8 {
+        match self {
+            SigningRound::Commitments => 1,
+            SigningRound::Approval | SigningRound::Shares => 2,
+        }
+    }
+}
+
+impl fmt::Display for SigningRound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningRound::Commitments => write!(f, "round 1 (commitments)"),
+            SigningRound::Approval => write!(f, "operator approval"),
+            SigningRound::Shares => write!(f, "round 2 (shares)"),
+        }
+    }
+}
+
+/// Signing ceremony configuration.
+#[derive(Debug, Clone)]
+pub struct CeremonyConfig {
+    /// Time allowed for collecting nonce commitments.
+    pub round1_timeout: Duration,
+
+    /// Time allowed for collecting signature shares.
+    pub round2_timeout: Duration,
+
+    /// Overall deadline for the whole ceremony, measured from its start.
+    pub ceremony_timeout: Duration,
+
+    /// Stop collecting a round as soon as enough participants responded, instead of waiting for all of them.
+    pub early_completion: bool,
+
+    /// Participants to sign with.
+    pub selection: SignerSelection,
+
+    /// Directory for the signers' session journals, state is kept in memory only if not set.
+    pub state_dir: Option<PathBuf>,
+
+    /// Policy the local signers evaluate before releasing a share, a daily limit needs the state directory to keep
+    /// the signed amounts across ceremonies.
+    pub policy: Option<SignerPolicy>,
+
+    /// Network the destinations allowed by the policy must belong to.
+    pub network: Network,
+
+    /// Directory of the queue where signing requests wait for operator approval, requests are signed without
+    /// approval if not set.
+    pub approval_dir: Option<PathBuf>,
+
+    /// Time an operator has to decide on a signing request, not counted towards the ceremony timeout.
+    pub approval_timeout: Duration,
+
+    /// Audit log the coordinator records the ceremony to.
+    pub audit: Option<Arc<AuditLog>>,
+
+    /// Sessions a signer keeps at once, finished and expired sessions are collected when the cap is reached and
+    /// periodically by the signer loop.
+    pub max_sessions: usize,
+}
+
+impl Default for CeremonyConfig {
+    fn default() -> Self {
+        Self {
+            round1_timeout: Duration::from_secs(60),
+            round2_timeout: Duration::from_secs(60),
+            ceremony_timeout: Duration::from_secs(120),
+            early_completion: true,
+            selection: SignerSelection::default(),
+            state_dir: None,
+            policy: None,
+            network: Network::Signet,
+            approval_dir: None,
+            approval_timeout: Duration::from_secs(600),
+            audit: None,
+            max_sessions: 64,
+        }
+    }
+}
+
+/// Selects which participants take part in a signing ceremony.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SignerSelection {
+    /// Every available participant commits, the first `threshold` commitments received are used for signing.
+    #[default]
+    FirstResponsive,
+
+    /// Only the given participants commit and sign.
+    Explicit(BTreeSet<Identifier>),
+}
+
+impl SignerSelection {
+    /// Builds a selection from 1-based participant indices, an empty list selects automatically.
+    pub fn from_indices(indices: &[u16]) -> Result<Self, SigningError> {
+        if indices.is_empty() {
+            return Ok(SignerSelection::FirstResponsive);
+        }
+        let identifiers = indices.iter().map(|index| Identifier::try_from(*index)).collect::<Result<_, _>>()?;
+        Ok(SignerSelection::Explicit(identifiers))
+    }
+
+    /// Participants asked to take part in round 1.
+    fn candidates(&self, key_data: &KeyData) -> Result<BTreeSet<Identifier>, SigningError> {
+        match self {
+            SignerSelection::FirstResponsive => Ok(key_data.key_packages.keys().cloned().collect()),
+            SignerSelection::Explicit(identifiers) => {
+                if let Some(unknown) = identifiers.iter().find(|id| !key_data.key_packages.contains_key(*id)) {
+                    return Err(SigningError::UnknownSigner(*unknown));
+                }
+                if identifiers.len() < key_data.threshold as usize {
+                    return Err(SigningError::NotEnoughSigners);
+                }
+                Ok(identifiers.clone())
+            }
+        }
+    }
+
+    /// Number of round 1 responses needed before signing can continue.
+    fn required(&self, threshold: usize) -> usize {
+        match self {
+            SignerSelection::FirstResponsive => threshold,
+            SignerSelection::Explicit(identifiers) => identifiers.len(),
+        }
+    }
+
+    /// Picks the signing set from the participants that responded in round 1, in order of arrival.
+    fn choose(&self, responders: &[Identifier], threshold: usize) -> Result<BTreeSet<Identifier>, SigningError> {
+        match self {
+            SignerSelection::FirstResponsive if responders.len() >= threshold => {
+                Ok(responders[..threshold].iter().cloned().collect())
+            }
+            SignerSelection::Explicit(identifiers) if identifiers.iter().all(|id| responders.contains(id)) => {
+                Ok(identifiers.clone())
+            }
+            _ => Err(SigningError::NotEnoughSigners),
+        }
+    }
+}
+
+/// FROST state machine states
+#[derive(Debug, Clone)]
+pub enum SigningState {
+    /// Idle state
+    Idle,
+
+    /// Round 1: All participants generate and broadcast commitments.
+    CollectingCommitments {
+        session_id: SessionId,
+        request: SigningRequest,
+        nonces: frost::round1::SigningNonces,
+        commitments: BTreeMap<Identifier, frost::round1::SigningCommitments>,
+        deadline: Instant,
+    },
+
+    /// Signing request waits for an operator to approve it before the share is generated.
+    AwaitingApproval {
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        nonces: frost::round1::SigningNonces,
+        deadline: Instant,
+    },
+
+    /// Round 2: Participants generate and broadcast signature shares.
+    CollectingShares {
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        /// Nonces are taken once the own share is signed, so they can never be used twice.
+        nonces: Option<frost::round1::SigningNonces>,
+        shares: BTreeMap<Identifier, frost::round2::SignatureShare>,
+        deadline: Instant,
+    },
+
+    /// Finalize the transaction
+    Complete { signed_transaction: Transaction },
+
+    /// Failed state
+    Failed { error: SigningError },
+}
+
+impl SigningState {
+    /// Phase of the state.
+    pub fn phase(&self) -> SigningPhase {
+        match self {
+            SigningState::Idle => SigningPhase::Idle,
+            SigningState::CollectingCommitments { .. } => SigningPhase::CollectingCommitments,
+            SigningState::AwaitingApproval { .. } => SigningPhase::AwaitingApproval,
+            SigningState::CollectingShares { .. } => SigningPhase::CollectingShares,
+            SigningState::Complete { .. } => SigningPhase::Complete,
+            SigningState::Failed { .. } => SigningPhase::Failed,
+        }
+    }
+
+    /// Session in progress, if any.
+    pub fn active_session(&self) -> Option<SessionId> {
+        match self {
+            SigningState::CollectingCommitments { session_id, .. }
+            | SigningState::AwaitingApproval { session_id, .. }
+            | SigningState::CollectingShares { session_id, .. } => Some(*session_id),
+            _ => None,
+        }
+    }
+
+    /// Round the session is in, if any.
+    pub fn round(&self) -> Option<SigningRound> {
+        match self {
+            SigningState::CollectingCommitments { .. } => Some(SigningRound::Commitments),
+            SigningState::AwaitingApproval { .. } => Some(SigningRound::Approval),
+            SigningState::CollectingShares { .. } => Some(SigningRound::Shares),
+            _ => None,
+        }
+    }
+
+    /// Deadline of the current round, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        match self {
+            SigningState::CollectingCommitments { deadline, .. }
+            | SigningState::AwaitingApproval { deadline, .. }
+            | SigningState::CollectingShares { deadline, .. } => Some(*deadline),
+            _ => None,
+        }
+    }
+
+    /// Overwrites the nonces of the session, so they can't leak once it is given up.
+    fn zeroize_nonces(&mut self) {
+        match self {
+            SigningState::CollectingCommitments { nonces, .. } | SigningState::AwaitingApproval { nonces, .. } => {
+                nonces.zeroize()
+            }
+            SigningState::CollectingShares { nonces: Some(nonces), .. } => nonces.zeroize(),
+            _ => {}
+        }
+    }
+}
+
+/// State of a session as reported outside of the signer, a [`SigningState`] without the secret nonces.
+#[derive(Debug, Clone)]
+pub enum SessionView {
+    Idle,
+    CollectingCommitments {
+        session_id: SessionId,
+        request: SigningRequest,
+        commitments: BTreeMap<Identifier, frost::round1::SigningCommitments>,
+        deadline: Instant,
+    },
+    AwaitingApproval {
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        deadline: Instant,
+    },
+    CollectingShares {
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        /// Whether the own share was signed, the nonces are spent then.
+        signed: bool,
+        shares: BTreeMap<Identifier, frost::round2::SignatureShare>,
+        deadline: Instant,
+    },
+    Complete {
+        signed_transaction: Transaction,
+    },
+    Failed {
+        error: SigningError,
+    },
+}
+
+impl From<&SigningState> for SessionView {
+    fn from(state: &SigningState) -> Self {
+        match state.clone() {
+            SigningState::Idle => SessionView::Idle,
+            SigningState::CollectingCommitments { session_id, request, commitments, deadline, .. } => {
+                SessionView::CollectingCommitments { session_id, request, commitments, deadline }
+            }
+            SigningState::AwaitingApproval { session_id, request, signing_package, deadline, .. } => {
+                SessionView::AwaitingApproval { session_id, request, signing_package, deadline }
+            }
+            SigningState::CollectingShares { session_id, request, signing_package, nonces, shares, deadline } => {
+                SessionView::CollectingShares {
+                    session_id,
+                    request,
+                    signing_package,
+                    signed: nonces.is_none(),
+                    shares,
+                    deadline,
+                }
+            }
+            SigningState::Complete { signed_transaction } => SessionView::Complete { signed_transaction },
+            SigningState::Failed { error } => SessionView::Failed { error },
+        }
+    }
+}
+
+/// Phase of a session, a [`SigningState`] without its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SigningPhase {
+    Idle,
+    CollectingCommitments,
+    AwaitingApproval,
+    CollectingShares,
+    Complete,
+    Failed,
+}
+
+/// Allowed transitions between phases, the state diagram of ARCHITECTURE.md.
+const TRANSITIONS: &[(SigningPhase, SigningPhase)] = &[
+    (SigningPhase::Idle, SigningPhase::CollectingCommitments),
+    (SigningPhase::Idle, SigningPhase::AwaitingApproval),
+    (SigningPhase::Idle, SigningPhase::CollectingShares),
+    (SigningPhase::Idle, SigningPhase::Failed),
+    (SigningPhase::CollectingCommitments, SigningPhase::AwaitingApproval),
+    (SigningPhase::CollectingCommitments, SigningPhase::CollectingShares),
+    (SigningPhase::CollectingCommitments, SigningPhase::Failed),
+    (SigningPhase::AwaitingApproval, SigningPhase::CollectingShares),
+    (SigningPhase::AwaitingApproval, SigningPhase::Failed),
+    (SigningPhase::CollectingShares, SigningPhase::Complete),
+    (SigningPhase::CollectingShares, SigningPhase::Failed),
+    (SigningPhase::Complete, SigningPhase::Idle),
+    (SigningPhase::Failed, SigningPhase::Idle),
+];
+
+impl SigningPhase {
+    /// Whether the transition table allows moving from this phase to the given one.
+    pub fn can_transition_to(self, to: SigningPhase) -> bool {
+        TRANSITIONS.contains(&(self, to))
+    }
+}
+
+impl fmt::Display for SigningPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// State transition of a session, published to the subscribers of the signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub session_id: SessionId,
+    pub participant_id: Identifier,
+    pub from: SigningPhase,
+    pub to: SigningPhase,
+}
+
+/// Transitions buffered per subscriber, slower subscribers miss the oldest ones.
+const TRANSITION_EVENTS_CAPACITY: usize = 256;
+
+/// Sessions of a signer by id, sessions without an entry are Idle.
+type Sessions = BTreeMap<SessionId, SigningState>;
+
+/// FROST Signer, runs any number of sessions up to the configured cap at once.
+#[derive(Clone)]
+pub struct FrostSigner {
+    pub participant_id: Identifier,
+    pub key_package: frost::keys::KeyPackage,
+    public_key_package: Arc<frost::keys::PublicKeyPackage>,
+    participant_index: Option<u16>,
+    sessions: Arc<Mutex<Sessions>>,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    sealer: Arc<Sealer>,
+    guard: Arc<ReplayGuard>,
+    config: CeremonyConfig,
+    store: Option<Arc<dyn StateStore>>,
+    nonce_pool: Arc<Mutex<NoncePool>>,
+    group_script: ScriptBuf,
+    policy: Arc<Mutex<PolicyEngine>>,
+    approvals: Option<Arc<dyn ApprovalQueue>>,
+    transitions: broadcast::Sender<Transition>,
+}
+
+impl FrostSigner {
+    pub fn new(
+        participant_id: Identifier,
+        key_package: frost::keys::KeyPackage,
+        public_key_package: &frost::keys::PublicKeyPackage,
+        transport: Arc<dyn Transport<Msg = Envelope>>,
+    ) -> Result<Self, SigningError> {
+        // The output script of a Taproot key doesn't depend on the network
+        let group_script = group_address(public_key_package, Network::Bitcoin)?.script_pubkey();
+        Ok(Self {
+            participant_id,
+            participant_index: participant_index(public_key_package, participant_id),
+            sealer: Arc::new(Sealer::new(&key_package, public_key_package)?),
+            guard: Arc::new(ReplayGuard::new(public_key_package)?),
+            key_package,
+            public_key_package: Arc::new(public_key_package.clone()),
+            sessions: Arc::new(Mutex::new(BTreeMap::new())),
+            transport,
+            config: CeremonyConfig::default(),
+            store: None,
+            nonce_pool: Arc::new(Mutex::new(NoncePool::default())),
+            policy: Arc::new(Mutex::new(PolicyEngine::permissive(group_script.clone()))),
+            group_script,
+            approvals: None,
+            transitions: broadcast::channel(TRANSITION_EVENTS_CAPACITY).0,
+        })
+    }
+
+    /// Sets the ceremony configuration used for round deadlines.
+    pub fn with_config(mut self, config: CeremonyConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the policy evaluated before releasing a share, its allowed destinations must be addresses on `network`.
+    pub fn with_policy(mut self, policy: SignerPolicy, network: Network) -> Result<Self, SigningError> {
+        self.policy = Arc::new(Mutex::new(PolicyEngine::new(policy, network, self.group_script.clone())?));
+        Ok(self)
+    }
+
+    /// Sets the queue where signing requests wait for operator approval before a share is generated.
+    pub fn with_approval_queue(mut self, approvals: Arc<dyn ApprovalQueue>) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Sets the store that journals state transitions and received messages.
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Recovers the sessions left unfinished by a previous run. Sessions whose own share was already signed resume
+    /// collecting shares, the others are aborted: their nonces were lost and must never be regenerated. Returns the
+    /// aborted sessions.
+    #[instrument(skip(self), fields(participant_id = ?self.participant_id))]
+    pub fn recover(&self) -> Result<Vec<SessionId>, SigningError> {
+        let Some(store) = &self.store else {
+            return Ok(Vec::new());
+        };
+
+        let mut sessions = self.lock_sessions()?;
+        let mut aborted = Vec::new();
+        for session_id in store.sessions()? {
+            let record = SessionRecord::replay(store.load(session_id)?);
+            if record.is_finished() || sessions.contains_key(&session_id) {
+                continue;
+            }
+            if let Some(state) = self.resumed_state(session_id, record) {
+                info!(%session_id, "Resuming session interrupted by a restart, the own share was already signed.");
+                sessions.insert(session_id, state);
+                self.publish(session_id, SigningPhase::Idle, SigningPhase::CollectingShares);
+                continue;
+            }
+            warn!(%session_id, "Aborting session interrupted by a restart.");
+            let error = "Session aborted after restart.".to_string();
+            store.append(session_id, &JournalEntry::Failed { error })?;
+            aborted.push(session_id);
+        }
+        Ok(aborted)
+    }
+
+    /// State of a journaled session that can continue without its nonces: the own share was signed, so only the
+    /// shares of the peers are left to collect.
+    fn resumed_state(&self, session_id: SessionId, record: SessionRecord) -> Option<SigningState> {
+        let (Some(transaction), Some(signing_package)) = (record.transaction, record.signing_package) else {
+            return None;
+        };
+        if !record.share_signed || record.prev_tx_outs.is_empty() {
+            return None;
+        }
+        Some(SigningState::CollectingShares {
+            session_id,
+            request: SigningRequest { transaction, prev_tx_outs: record.prev_tx_outs },
+            signing_package,
+            nonces: None,
+            shares: record.shares,
+            deadline: Instant::now() + self.config.round2_timeout,
+        })
+    }
+
+    /// Appends an entry to the session journal, if the signer has a store.
+    fn journal(&self, session_id: SessionId, entry: JournalEntry) -> Result<(), SigningError> {
+        if let Some(store) = &self.store {
+            store.append(session_id, &entry)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the signer waits for operator approval before signing.
+    pub fn requires_approval(&self) -> bool {
+        self.approvals.is_some()
+    }
+
+    /// Subscribes to the state transitions of the signer's sessions.
+    pub fn subscribe(&self) -> broadcast::Receiver<Transition> {
+        self.transitions.subscribe()
+    }
+
+    /// Moves a session from the given phase to the next state if the transition table allows it, and publishes the
+    /// transition.
+    fn transition(
+        &self,
+        session_id: SessionId,
+        from: SigningPhase,
+        state: &mut SigningState,
+        next: SigningState,
+    ) -> Result<(), SigningError> {
+        let to = next.phase();
+        if !from.can_transition_to(to) {
+            return Err(SigningError::InvalidTransition { from, to });
+        }
+        *state = next;
+        self.publish(session_id, from, to);
+        Ok(())
+    }
+
+    fn publish(&self, session_id: SessionId, from: SigningPhase, to: SigningPhase) {
+        debug!(%session_id, %from, %to, "Session state transition.");
+        // Sending only fails without subscribers
+        let _ = self.transitions.send(Transition { session_id, participant_id: self.participant_id, from, to });
+    }
+
+    /// Forgets a completed or failed session, it is Idle again.
+    fn forget(&self, sessions: &mut Sessions, session_id: SessionId) -> Result<(), SigningError> {
+        let Some(from) = sessions.get(&session_id).map(SigningState::phase) else {
+            return Ok(());
+        };
+        if !from.can_transition_to(SigningPhase::Idle) {
+            return Err(SigningError::InvalidTransition { from, to: SigningPhase::Idle });
+        }
+        sessions.remove(&session_id);
+        self.guard.forget_session(session_id);
+        self.publish(session_id, from, SigningPhase::Idle);
+        Ok(())
+    }
+
+    fn lock_sessions(&self) -> Result<MutexGuard<'_, Sessions>, SigningError> {
+        self.sessions.lock().map_err(|e| SigningError::InternalError(format!("Failed to lock sessions mutex: {e}")))
+    }
+
+    /// State of a session without its nonces, sessions the signer doesn't know are Idle.
+    pub fn get_state(&self, session_id: SessionId) -> Result<SessionView, SigningError> {
+        Ok(self.lock_sessions()?.get(&session_id).map_or(SessionView::Idle, SessionView::from))
+    }
+
+    /// Sessions the signer keeps, in progress or finished but not collected yet.
+    pub fn sessions(&self) -> Result<Vec<SessionId>, SigningError> {
+        Ok(self.lock_sessions()?.keys().cloned().collect())
+    }
+
+    /// Checks that a new session can start: it must be Idle and the signer below its session cap.
+    fn admit_session(&self, sessions: &Sessions, session_id: SessionId) -> Result<(), SigningError> {
+        if sessions.contains_key(&session_id) {
+            return Err(SigningError::InvalidState(format!("Session {session_id} is not in Idle state.")));
+        }
+        if sessions.len() >= self.config.max_sessions {
+            return Err(SigningError::TooManySessions(self.config.max_sessions));
+        }
+        Ok(())
+    }
+
+    /// Collects garbage when the signer reached its session cap, to make room for a new session.
+    async fn make_room(&self) -> Result<(), SigningError> {
+        if self.lock_sessions()?.len() >= self.config.max_sessions {
+            self.collect_garbage().await?;
+        }
+        Ok(())
+    }
+
+    /// Removes finished sessions and fails and removes sessions whose deadline passed, telling the peers to abort
+    /// them. Returns the removed sessions.
+    #[instrument(skip(self), fields(participant_id = ?self.participant_id))]
+    pub async fn collect_garbage(&self) -> Result<Vec<SessionId>, SigningError> {
+        let (collected, expired) = {
+            let mut sessions = self.lock_sessions()?;
+            self.collect_sessions(&mut sessions)
+        };
+        for (session_id, error) in &expired {
+            self.broadcast_abort(*session_id, error).await;
+        }
+        Ok(collected)
+    }
+
+    /// Removes finished and expired sessions, returns the removed ones and the errors the expired ones failed with.
+    fn collect_sessions(&self, sessions: &mut Sessions) -> (Vec<SessionId>, Vec<(SessionId, SigningError)>) {
+        let now = Instant::now();
+        let mut collected = Vec::new();
+        let mut expired = Vec::new();
+        for (session_id, state) in sessions.iter_mut() {
+            if let Some(round) = state.round().filter(|_| state.deadline().is_some_and(|deadline| now >= deadline)) {
+                let error = SigningError::Timeout { round, missing: Vec::new() };
+                self.fail_session(state, *session_id, &error);
+                expired.push((*session_id, error));
+            }
+            if state.active_session().is_none() {
+                collected.push(*session_id);
+            }
+        }
+        collected.retain(|session_id| self.forget(sessions, *session_id).is_ok());
+        if !collected.is_empty() {
+            debug!(sessions = collected.len(), expired = expired.len(), "Collected finished and expired sessions.");
+        }
+        (collected, expired)
+    }
+
+    /// Enters round 2, through operator approval if the signer has an approval queue. The session is in the new state
+    /// before any check runs, so a failed check fails the session with its nonces.
+    fn enter_sharing_round(
+        &self,
+        from: SigningPhase,
+        state: &mut SigningState,
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        nonces: frost::round1::SigningNonces,
+    ) -> Result<(), SigningError> {
+        let Some(approvals) = &self.approvals else {
+            return self.start_share_collection(from, state, session_id, request, signing_package, nonces);
+        };
+
+        let next = SigningState::AwaitingApproval {
+            session_id,
+            request,
+            signing_package,
+            nonces,
+            deadline: Instant::now() + self.config.approval_timeout,
+        };
+        self.transition(session_id, from, state, next)?;
+        let SigningState::AwaitingApproval { request, signing_package, .. } = &*state else {
+            unreachable!("state set above");
+        };
+
+        // Operators only get to see requests the signer would sign
+        request.verify_signing_package(signing_package)?;
+        let summary = TransactionSummary::new(request)?;
+        let deadline = SystemTime::now() + self.config.approval_timeout;
+        let (participant, index) = (self.participant_id, self.participant_index);
+        approvals.submit(&PendingRequest { session_id, participant, index, summary, deadline })?;
+        info!(%session_id, "Signing request is awaiting operator approval.");
+        Ok(())
+    }
+
+    /// Checks for the operator decision while the session awaits approval. Returns whether the signer may continue to
+    /// sign, a rejected or expired request fails the session.
+    #[instrument(skip(self), fields(participant_id = ?self.participant_id))]
+    pub fn poll_approval(&self, session_id: SessionId) -> Result<bool, SigningError> {
+        let mut sessions = self.lock_sessions()?;
+        let Some(state) = sessions.get_mut(&session_id) else {
+            return Ok(true);
+        };
+        let SigningState::AwaitingApproval { deadline, .. } = &*state else {
+            return Ok(true);
+        };
+        let deadline = *deadline;
+        let approvals = self
+            .approvals
+            .as_ref()
+            .ok_or_else(|| SigningError::InternalError("Awaiting approval without an approval queue.".to_string()));
+
+        let error = match approvals
+            .and_then(|approvals| approvals.decision(session_id, self.participant_id).map_err(SigningError::from))
+        {
+            Ok(Some(ApprovalDecision::Approved)) => {
+                info!(%session_id, "Signing request approved.");
+                let SigningState::AwaitingApproval { request, signing_package, nonces, .. } =
+                    std::mem::replace(state, SigningState::Idle)
+                else {
+                    unreachable!("state checked above");
+                };
+                let from = SigningPhase::AwaitingApproval;
+                match self.start_share_collection(from, state, session_id, request, signing_package, nonces) {
+                    Ok(()) => return Ok(true),
+                    Err(e) => e,
+                }
+            }
+            Ok(Some(ApprovalDecision::Rejected { reason })) => SigningError::ApprovalRejected { session_id, reason },
+            Ok(None) if Instant::now() >= deadline => {
+                SigningError::Timeout { round: SigningRound::Approval, missing: vec![self.participant_id] }
+            }
+            Ok(None) => return Ok(false),
+            Err(e) => e,
+        };
+
+        self.fail_session(state, session_id, &error);
+        Err(error)
+    }
+
+    /// Moves to collecting shares with the session nonces ready for signing.
+    fn start_share_collection(
+        &self,
+        from: SigningPhase,
+        state: &mut SigningState,
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        nonces: frost::round1::SigningNonces,
+    ) -> Result<(), SigningError> {
+        let journal_entry = JournalEntry::CollectingShares { signing_package: signing_package.clone() };
+        let next = SigningState::CollectingShares {
+            session_id,
+            request,
+            signing_package,
+            nonces: Some(nonces),
+            shares: BTreeMap::new(),
+            deadline: Instant::now() + self.config.round2_timeout,
+        };
+        self.transition(session_id, from, state, next)?;
+        self.journal(session_id, journal_entry)
+    }
+
+    /// Checks that the signing package signs the requested transaction and that the transaction passes the signer
+    /// policy, the paid out amount is recorded towards the daily limit when both checks pass. With a store, the
+    /// amounts are kept there, so the limit holds across ceremonies and restarts.
+    fn approve_request(&self, request: &SigningRequest, signing_package: &SigningPackage) -> Result<(), SigningError> {
+        request.verify_signing_package(signing_package)?;
+        let mut policy = self.policy.lock().map_err(|e| SigningError::InternalError(e.to_string()))?;
+        let now = SystemTime::now();
+        if let Some(store) = self.store.as_ref().filter(|_| policy.has_daily_limit()) {
+            policy.load_history(store.spends()?.into_iter().map(|spend| (spend.at, spend.amount)));
+        }
+        let amount = policy.evaluate(&request.transaction, &request.prev_tx_outs, now)?;
+        if let Some(store) = &self.store {
+            store.record_spend(SpendRecord { at: now, amount })?;
+        }
+        policy.record_spend(amount, now);
+        Ok(())
+    }
+
+    /// Checks a peer's share against the signing package and the peer's verifying share.
+    fn verify_share(
+        &self,
+        signing_package: &SigningPackage,
+        sender: Identifier,
+        share: &frost::round2::SignatureShare,
+    ) -> Result<(), SigningError> {
+        let public = &self.public_key_package;
+        public
+            .verifying_shares()
+            .get(&sender)
+            .ok_or(SigningError::InvalidSignatureShare(sender))
+            .and_then(|verifying_share| {
+                frost::verify_signature_share(sender, verifying_share, share, signing_package, public.verifying_key())
+                    .map_err(|_| SigningError::InvalidSignatureShare(sender))
+            })
+            .inspect_err(|_| metrics::invalid_share(sender))
+    }
+
+    /// Moves the session to Failed: its nonces are zeroized and the failure is journaled.
+    fn fail_session(&self, state: &mut SigningState, session_id: SessionId, error: &SigningError) {
+        warn!(%session_id, "Signing session failed: {error}");
+        if let Err(e) = self.journal(session_id, JournalEntry::Failed { error: error.to_string() }) {
+            warn!(%session_id, "Failed to journal the failed session: {e}");
+        }
+        state.zeroize_nonces();
+        let from = state.phase();
+        if let Err(e) = self.transition(session_id, from, state, SigningState::Failed { error: error.clone() }) {
+            warn!(%session_id, "Failed to fail the session: {e}");
+        }
+    }
+
+    /// Seals and broadcasts a message to the peers.
+    async fn broadcast(&self, message: SigningMessage) -> Result<(), SigningError> {
+        self.transport.broadcast(self.sealer.seal(message)?).await?;
+        Ok(())
+    }
+
+    /// Tells the peers that the session failed, so they stop waiting for this signer.
+    async fn broadcast_abort(&self, session_id: SessionId, error: &SigningError) {
+        let message = SigningMessage::Abort(session_id, self.participant_id, error.to_string());
+        if let Err(e) = self.broadcast(message).await {
+            warn!(%session_id, "Failed to broadcast abort: {e}");
+        }
+    }
+
+    /// Fails the session if it is still in progress and tells the peers to abort it.
+    #[instrument(skip(self, error), fields(participant_id = ?self.participant_id))]
+    pub async fn abort(&self, session_id: SessionId, error: &SigningError) {
+        let aborted = {
+            let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+            match sessions.get_mut(&session_id) {
+                Some(state) if state.active_session().is_some() => {
+                    self.fail_session(state, session_id, error);
+                    true
+                }
+                _ => false,
+            }
+        };
+        if aborted {
+            self.broadcast_abort(session_id, error).await;
+        }
+    }
+
+    /// Forgets a completed or failed session, it is Idle again. Sessions in progress have to be aborted first.
+    pub fn reset(&self, session_id: SessionId) -> Result<(), SigningError> {
+        let mut sessions = self.lock_sessions()?;
+        self.forget(&mut sessions, session_id)
+    }
+
+    /// Start round 1
+    #[instrument(skip(self, request), fields(participant_id = ?self.participant_id))]
+    pub async fn initiate_signing_round(
+        &self,
+        session_id: SessionId,
+        request: SigningRequest,
+    ) -> Result<(), SigningError> {
+        self.make_room().await?;
+        let commitments = {
+            let mut sessions = self.lock_sessions()?;
+            self.admit_session(&sessions, session_id)?;
+
+            // Nonces are generated at most once per session, also across restarts
+            if let Some(store) = &self.store {
+                if !store.load(session_id)?.is_empty() {
+                    return Err(SigningError::NonceReuse(session_id));
+                }
+            }
+            let entry = JournalEntry::CollectingCommitments {
+                transaction: request.transaction.clone(),
+                prev_tx_outs: request.prev_tx_outs.clone(),
+            };
+            self.journal(session_id, entry)?;
+
+            let (nonces, commitments) = frost::round1::commit(self.key_package.signing_share(), &mut OsRng);
+            // Broadcasts skip the sender, so the signer keeps its own commitment with the received ones
+            self.journal(session_id, JournalEntry::CommitmentReceived { from: self.participant_id, commitments })?;
+            let deadline = Instant::now() + self.config.round1_timeout;
+            let next = SigningState::CollectingCommitments {
+                session_id,
+                request,
+                nonces,
+                commitments: BTreeMap::from([(self.participant_id, commitments)]),
+                deadline,
+            };
+            let state = sessions.entry(session_id).or_insert(SigningState::Idle);
+            self.transition(session_id, SigningPhase::Idle, state, next)?;
+            commitments
+        };
+
+        debug!("Broadcasting nonce commitment.");
+        let msg = SigningMessage::NonceCommitment(session_id, self.participant_id, Box::new(commitments));
+        if let Err(e) = self.broadcast(msg).await {
+            self.abort(session_id, &e).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Generates a batch of nonces ahead of signing and publishes their commitments to the coordinator.
+    #[instrument(skip(self), fields(participant_id = ?self.participant_id))]
+    pub async fn preprocess(&self, count: usize) -> Result<(), SigningError> {
+        let batch = {
+            let mut pool = self.nonce_pool.lock().map_err(|e| SigningError::InternalError(e.to_string()))?;
+            pool.generate(self.key_package.signing_share(), count)
+        };
+
+        debug!(count = batch.len(), "Broadcasting preprocessed nonce commitments.");
+        self.broadcast(SigningMessage::PreprocessedCommitments(self.participant_id, batch)).await
+    }
+
+    /// Number of unused preprocessed nonces.
+    pub fn preprocessed_nonces(&self) -> Result<usize, SigningError> {
+        let pool = self.nonce_pool.lock().map_err(|e| SigningError::InternalError(e.to_string()))?;
+        Ok(pool.len())
+    }
+
+    /// One round signing with a preprocessed nonce: moves from Idle straight to collecting shares and broadcasts
+    /// the own share. The nonce is removed from the pool before signing, so it is never used twice. With an approval
+    /// queue the signer stops in AwaitingApproval and signs once the request was approved.
+    #[instrument(skip(self, request, signing_package), fields(participant_id = ?self.participant_id))]
+    pub async fn sign_preprocessed(
+        &self,
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        commitment_id: CommitmentId,
+    ) -> Result<(), SigningError> {
+        self.make_room().await?;
+        let entered = {
+            let mut sessions = self.lock_sessions()?;
+            self.admit_session(&sessions, session_id)?;
+
+            let nonces = {
+                let mut pool = self.nonce_pool.lock().map_err(|e| SigningError::InternalError(e.to_string()))?;
+                pool.take(commitment_id).ok_or(SigningError::UnknownCommitment(commitment_id))?
+            };
+            let state = sessions.entry(session_id).or_insert(SigningState::Idle);
+            let entered =
+                self.enter_sharing_round(SigningPhase::Idle, state, session_id, request, signing_package, nonces);
+            if let Err(e) = &entered {
+                self.fail_session(state, session_id, e);
+            }
+            entered
+        };
+        if let Err(e) = entered {
+            self.broadcast_abort(session_id, &e).await;
+            return Err(e);
+        }
+
+        if self.poll_approval(session_id)? {
+            self.sign_and_broadcast_share(session_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Start round 2
+    #[instrument(skip(self, signing_package), fields(participant_id = ?self.participant_id))]
+    pub fn advance_to_sharing_round(
+        &self,
+        session_id: SessionId,
+        signing_package: SigningPackage,
+    ) -> Result<(), SigningError> {
+        let mut sessions = self.lock_sessions()?;
+        let state = match sessions.get_mut(&session_id) {
+            Some(state @ SigningState::CollectingCommitments { .. }) => state,
+            state => {
+                let state = state.map(|s| &*s).unwrap_or(&SigningState::Idle);
+                return Err(SigningError::InvalidState(format!(
+                    "Cannot advance to sharing round from state {state:?}"
+                )));
+            }
+        };
+        debug!("Transitioning to round 2.");
+        let SigningState::CollectingCommitments { request, nonces, .. } = std::mem::replace(state, SigningState::Idle)
+        else {
+            unreachable!("state checked above");
+        };
+
+        let from = SigningPhase::CollectingCommitments;
+        let result = self.enter_sharing_round(from, state, session_id, request, signing_package, nonces);
+        if let Err(e) = &result {
+            self.fail_session(state, session_id, e);
+        }
+        result
+    }
+
+    /// Broadcast signature shares.
+    #[instrument(skip(self), fields(participant_id = ?self.participant_id))]
+    pub async fn sign_and_broadcast_share(&self, session_id: SessionId) -> Result<(), SigningError> {
+        let signed = {
+            let mut sessions = self.lock_sessions()?;
+            let Some(state) = sessions.get_mut(&session_id) else {
+                return Err(SigningError::InvalidState(format!("Cannot sign share in state {:?}", SigningState::Idle)));
+            };
+            let signed = match state {
+                SigningState::CollectingShares { signing_package, nonces, request, shares, .. } => {
+                    let mut session_nonces = nonces.take().ok_or(SigningError::NonceReuse(session_id))?;
+                    let signed = self.sign_share(session_id, request, signing_package, &session_nonces);
+                    session_nonces.zeroize();
+                    // Like the commitment, the own share is kept with the received ones
+                    signed.and_then(|share| {
+                        shares.insert(self.participant_id, share);
+                        self.journal(session_id, JournalEntry::ShareReceived { from: self.participant_id, share })?;
+                        Ok(share)
+                    })
+                }
+                s => return Err(SigningError::InvalidState(format!("Cannot sign share in state {s:?}"))),
+            };
+            if let Err(e) = &signed {
+                self.fail_session(state, session_id, e);
+            }
+            signed
+        };
+
+        let share = match signed {
+            Ok(share) => share,
+            Err(e) => {
+                self.broadcast_abort(session_id, &e).await;
+                return Err(e);
+            }
+        };
+        if let Err(e) = self.broadcast(SigningMessage::SignatureShare(session_id, self.participant_id, share)).await {
+            self.abort(session_id, &e).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Checks the request and signs the own share.
+    fn sign_share(
+        &self,
+        session_id: SessionId,
+        request: &SigningRequest,
+        signing_package: &SigningPackage,
+        nonces: &frost::round1::SigningNonces,
+    ) -> Result<frost::round2::SignatureShare, SigningError> {
+        self.approve_request(request, signing_package)?;
+        let share = frost::round2::sign_with_tweak(signing_package, nonces, &self.key_package, None)?;
+        self.journal(session_id, JournalEntry::ShareSigned)?;
+        Ok(share)
+    }
+
+    /// Finalize the transaction, only a session collecting shares can complete.
+    #[instrument(skip(self, signed_transaction), fields(participant_id = ?self.participant_id))]
+    pub fn complete_signing(&self, session_id: SessionId, signed_transaction: Transaction) -> Result<(), SigningError> {
+        let mut sessions = self.lock_sessions()?;
+        let to = SigningPhase::Complete;
+        let Some(state) = sessions.get_mut(&session_id) else {
+            return Err(SigningError::InvalidTransition { from: SigningPhase::Idle, to });
+        };
+        let from = state.phase();
+        let entry = JournalEntry::Complete { signed_transaction: signed_transaction.clone() };
+        self.transition(session_id, from, state, SigningState::Complete { signed_transaction })?;
+        if let Err(e) = self.journal(session_id, entry) {
+            warn!("Failed to journal completed session: {e}");
+        }
+        Ok(())
+    }
+
+    /// Process messages from other participants, routed to their session by the session id. Envelopes from other
+    /// groups, of earlier rounds or seen before are rejected. An abort from a peer fails the session.
+    #[instrument(skip(self, envelope), fields(participant_id = ?self.participant_id))]
+    pub async fn process_message(&self, envelope: Envelope) -> Result<(), SigningError> {
+        if let Some((session_id, error)) = self.apply_message(envelope)? {
+            self.broadcast_abort(session_id, &error).await;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Applies a message to its session, returns the session and error if the message failed the session.
+    fn apply_message(&self, envelope: Envelope) -> Result<Option<(SessionId, SigningError)>, SigningError> {
+        let mut sessions = self.lock_sessions()?;
+        let Some(session_id) = envelope.message.session_id() else {
+            debug!(kind = envelope.message.kind(), "Ignoring message outside of a session.");
+            return Ok(None);
+        };
+        let current_round = sessions.get(&session_id).and_then(SigningState::round).map_or(0, |round| round.number());
+        self.guard.open(&envelope, current_round)?;
+        let Some(state) = sessions.get_mut(&session_id) else {
+            debug!(%session_id, "Ignoring message of an unknown session.");
+            return Ok(None);
+        };
+        let msg = envelope.message;
+
+        if let SigningMessage::Abort(_, sender, reason) = msg {
+            if state.active_session().is_some() {
+                let error = SigningError::Aborted { session_id, by: sender, reason };
+                self.fail_session(state, session_id, &error);
+            }
+            return Ok(None);
+        }
+
+        let result = match state {
+            SigningState::CollectingCommitments { commitments, .. } => match msg {
+                SigningMessage::NonceCommitment(_, sender, new_commitments) if !commitments.contains_key(&sender) => {
+                    debug!(from = ?sender, "Received nonce commitment.");
+                    commitments.insert(sender, *new_commitments);
+                    let entry = JournalEntry::CommitmentReceived { from: sender, commitments: *new_commitments };
+                    self.journal(session_id, entry)
+                }
+                _ => Ok(()),
+            },
+            SigningState::CollectingShares { signing_package, shares, .. } => match msg {
+                SigningMessage::SignatureShare(_, sender, _)
+                    if !signing_package.signing_commitments().contains_key(&sender) =>
+                {
+                    debug!(from = ?sender, "Ignoring share of a participant outside the signing package.");
+                    Ok(())
+                }
+                SigningMessage::SignatureShare(_, sender, share) if !shares.contains_key(&sender) => {
+                    debug!(from = ?sender, "Received signature share.");
+                    self.verify_share(signing_package, sender, &share).and_then(|()| {
+                        shares.insert(sender, share);
+                        self.journal(session_id, JournalEntry::ShareReceived { from: sender, share })
+                    })
+                }
+                _ => Ok(()),
+            },
+            _ => {
+                warn!(%session_id, "Received message in unexpected state.");
+                Ok(())
+            }
+        };
+
+        // An invalid share fails the session, as does a message the signer can't journal and so can't recover
+        let Err(error) = result else {
+            return Ok(None);
+        };
+        self.fail_session(state, session_id, &error);
+        Ok(Some((session_id, error)))
+    }
+}
+
+/// Reply of a signer to a command, sent once the command is done.
+type Reply = oneshot::Sender<Result<(), SigningError>>;
+
+/// Commands the coordinator sends to a running signer.
+enum SignerCommand {
+    /// Start round 1 of a session, replies once the nonce commitment was broadcast.
+    Start { session_id: SessionId, request: SigningRequest, reply: Reply },
+
+    /// Enter round 2 with the signing package, replies once the share was broadcast, after operator approval if
+    /// the signer has an approval queue.
+    Sign { session_id: SessionId, signing_package: SigningPackage, reply: Reply },
+
+    /// Sign with a preprocessed nonce, replies like `Sign`.
+    SignPreprocessed {
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        commitment_id: CommitmentId,
+        reply: Reply,
+    },
+
+    /// Complete the session with the signed transaction.
+    Complete { session_id: SessionId, signed_transaction: Transaction, reply: Reply },
+}
+
+/// Early messages kept per session and sender, one for each round.
+const EARLY_MESSAGES_PER_SENDER: usize = 2;
+
+/// Early messages kept per session.
+const EARLY_MESSAGES_PER_SESSION: usize = 64;
+
+/// Messages that arrived before their session reached the round they belong to, with the time they arrived.
+#[derive(Default)]
+struct EarlyMessages(Vec<(Instant, Envelope)>);
+
+impl EarlyMessages {
+    /// Keeps a message unless its session or its sender in the session has too many kept already.
+    fn push(&mut self, received: Instant, envelope: Envelope) {
+        let session_id = envelope.message.session_id();
+        let session = || self.0.iter().filter(|(_, kept)| kept.message.session_id() == session_id);
+        let from_sender = session().filter(|(_, kept)| kept.sender() == envelope.sender()).count();
+        if from_sender >= EARLY_MESSAGES_PER_SENDER || session().count() >= EARLY_MESSAGES_PER_SESSION {
+            debug!(from = ?envelope.sender(), "Dropping early message, too many are kept.");
+            return;
+        }
+        self.0.push((received, envelope));
+    }
+}
+
+/// Handle of a running signer loop, the loop stops once every handle was dropped.
+#[derive(Clone)]
+pub struct SignerHandle {
+    pub participant_id: Identifier,
+    commands: mpsc::UnboundedSender<SignerCommand>,
+}
+
+impl SignerHandle {
+    async fn request(&self, command: impl FnOnce(Reply) -> SignerCommand) -> Result<(), SigningError> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| SigningError::InternalError(format!("Signer {:?} stopped.", self.participant_id)))?;
+        response.await.map_err(|_| SigningError::InternalError(format!("Signer {:?} stopped.", self.participant_id)))?
+    }
+
+    /// Starts round 1 of a session.
+    pub async fn start(&self, session_id: SessionId, request: SigningRequest) -> Result<(), SigningError> {
+        self.request(|reply| SignerCommand::Start { session_id, request, reply }).await
+    }
+
+    /// Signs the session's signing package, waits for operator approval if needed.
+    pub async fn sign(&self, session_id: SessionId, signing_package: SigningPackage) -> Result<(), SigningError> {
+        self.request(|reply| SignerCommand::Sign { session_id, signing_package, reply }).await
+    }
+
+    /// Signs the signing package with a preprocessed nonce, waits for operator approval if needed.
+    pub async fn sign_preprocessed(
+        &self,
+        session_id: SessionId,
+        request: SigningRequest,
+        signing_package: SigningPackage,
+        commitment_id: CommitmentId,
+    ) -> Result<(), SigningError> {
+        self.request(|reply| SignerCommand::SignPreprocessed {
+            session_id,
+            request,
+            signing_package,
+            commitment_id,
+            reply,
+        })
+        .await
+    }
+
+    /// Completes the session with the signed transaction.
+    pub async fn complete(&self, session_id: SessionId, signed_transaction: Transaction) -> Result<(), SigningError> {
+        self.request(|reply| SignerCommand::Complete { session_id, signed_transaction, reply }).await
+    }
+}
+
+impl FrostSigner {
+    /// Spawns the signer loop: the signer receives the messages from its mailbox and runs the commands of the
+    /// coordinator on its own, independent of the other signers.
+    pub fn spawn(&self) -> SignerHandle {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(self.clone().run(receiver));
+        SignerHandle { participant_id: self.participant_id, commands }
+    }
+
+    #[instrument(skip_all, fields(participant_id = ?self.participant_id))]
+    async fn run(self, mut commands: mpsc::UnboundedReceiver<SignerCommand>) {
+        let mut awaiting_approval: Vec<(SessionId, Reply)> = Vec::new();
+        let mut early = EarlyMessages::default();
+        let mut approval_poll = tokio::time::interval(APPROVAL_POLL_INTERVAL);
+        let mut gc = tokio::time::interval_at(tokio::time::Instant::now() + GC_INTERVAL, GC_INTERVAL);
+        loop {
+            // Received messages go first, so a session has all messages sent before the command that completes it
+            tokio::select! {
+                biased;
+                received = self.transport.next_message() => match received {
+                    Ok((_, envelope)) => self.receive(Instant::now(), envelope, &mut early).await,
+                    Err(e) => {
+                        warn!("Failed to receive message: {e}");
+                        break;
+                    }
+                },
+                command = commands.recv() => match command {
+                    Some(command) => {
+                        self.execute(command, &mut awaiting_approval).await;
+                        self.retry_early(&mut early).await;
+                    }
+                    None => break,
+                },
+                _ = approval_poll.tick(), if !awaiting_approval.is_empty() => {
+                    let mut still_waiting = Vec::new();
+                    for (session_id, reply) in awaiting_approval.drain(..) {
+                        match self.poll_approval(session_id) {
+                            Ok(true) => {
+                                let _ = reply.send(self.sign_and_broadcast_share(session_id).await);
+                            }
+                            Ok(false) => still_waiting.push((session_id, reply)),
+                            Err(e) => {
+                                self.broadcast_abort(session_id, &e).await;
+                                let _ = reply.send(Err(e));
+                            }
+                        }
+                    }
+                    awaiting_approval = still_waiting;
+                    self.retry_early(&mut early).await;
+                }
+                _ = gc.tick() => {
+                    if let Err(e) = self.collect_garbage().await {
+                        warn!("Failed to collect sessions: {e}");
+                    }
+                }
+            }
+        }
+        debug!("Signer loop stopped.");
+    }
+
+    /// Processes a received message, or keeps it until its session caught up if it arrived early. Early messages
+    /// are authenticated before they are kept, so forged ones can't take the place of a peer's.
+    async fn receive(&self, received: Instant, envelope: Envelope, early: &mut EarlyMessages) {
+        if !self.is_early(&envelope.message) {
+            self.process(envelope).await;
+        } else if let Err(e) = self.guard.verify(&envelope) {
+            debug!(from = ?envelope.sender(), "Dropping message: {e}");
+        } else {
+            early.push(received, envelope);
+        }
+    }
+
+    async fn process(&self, envelope: Envelope) {
+        if let Err(e) = self.process_message(envelope).await {
+            debug!("Failed to process message: {e}");
+        }
+    }
+
+    /// Processes the early messages whose session caught up, drops those kept longer than a ceremony lasts.
+    async fn retry_early(&self, early: &mut EarlyMessages) {
+        for (received, envelope) in std::mem::take(&mut early.0) {
+            if received.elapsed() > self.config.ceremony_timeout {
+                debug!(from = ?envelope.sender(), "Dropping expired early message.");
+            } else if self.is_early(&envelope.message) {
+                early.push(received, envelope);
+            } else {
+                self.process(envelope).await;
+            }
+        }
+    }
+
+    /// Whether a message belongs to a round its running session didn't reach yet, peers move on at their own pace.
+    /// Messages of sessions the signer didn't start are not kept.
+    fn is_early(&self, message: &SigningMessage) -> bool {
+        let Some(session_id) = message.session_id() else {
+            return false;
+        };
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(phase) = sessions.get(&session_id).map(SigningState::phase) else {
+            return false;
+        };
+        match message {
+            SigningMessage::SignatureShare(..) => {
+                matches!(phase, SigningPhase::CollectingCommitments | SigningPhase::AwaitingApproval)
+            }
+            _ => false,
+        }
+    }
+
+    /// Runs a command, commands waiting for operator approval are replied to once the request was decided.
+    async fn execute(&self, command: SignerCommand, awaiting_approval: &mut Vec<(SessionId, Reply)>) {
+        let (session_id, reply, result) = match command {
+            SignerCommand::Start { session_id, request, reply } => {
+                (session_id, reply, self.initiate_signing_round(session_id, request).await)
+            }
+            SignerCommand::Sign { session_id, signing_package, reply } => {
+                let result = self.advance_to_sharing_round(session_id, signing_package);
+                if let Err(e) = &result {
+                    self.broadcast_abort(session_id, e).await;
+                }
+                (session_id, reply, result)
+            }
+            SignerCommand::SignPreprocessed { session_id, request, signing_package, commitment_id, reply } => {
+                let result = self.sign_preprocessed(session_id, request, signing_package, commitment_id).await;
+                (session_id, reply, result)
+            }
+            SignerCommand::Complete { session_id, signed_transaction, reply } => {
+                let _ = reply.send(self.complete_signing(session_id, signed_transaction));
+                return;
+            }
+        };
+        if result.is_err() {
+            let _ = reply.send(result);
+            return;
+        }
+
+        match self.get_state(session_id) {
+            Ok(SessionView::AwaitingApproval { .. }) => awaiting_approval.push((session_id, reply)),
+            Ok(SessionView::CollectingShares { signed: false, .. }) => {
+                let _ = reply.send(self.sign_and_broadcast_share(session_id).await);
+            }
+            state => {
+                let _ = reply.send(state.map(|_| ()));
+            }
+        }
+    }
+}
+
+/// Local signers with their loops running, a long-lived coordinator shares them between the sessions it signs.
+#[derive(Clone)]
+pub struct LocalSigners {
+    signers: HashMap<Identifier, FrostSigner>,
+    handles: HashMap<Identifier, SignerHandle>,
+}
+
+impl LocalSigners {
+    /// Configures the signers for the ceremony and spawns their loops, which stop once the last clone is dropped.
+    pub fn spawn(signers: HashMap<Identifier, FrostSigner>, config: &CeremonyConfig) -> Self {
+        let signers: HashMap<_, _> =
+            signers.into_iter().map(|(id, signer)| (id, signer.with_config(config.clone()))).collect();
+        let handles = signers.iter().map(|(id, signer)| (*id, signer.spawn())).collect();
+        Self { signers, handles }
+    }
+
+    pub fn signers(&self) -> &HashMap<Identifier, FrostSigner> {
+        &self.signers
+    }
+
+    /// Local signers among the candidates of the selection, with their handles.
+    fn select(&self, candidates: &BTreeSet<Identifier>) -> Self {
+        let mut selected = self.clone();
+        selected.signers.retain(|id, _| candidates.contains(id));
+        selected.handles.retain(|id, _| candidates.contains(id));
+        selected
+    }
+}
+
+/// A coordinator function to perform a FROST signing ceremony for a Taproot input.
+pub async fn run_signing_ceremony(
+    key_data: KeyData,
+    transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+) -> Result<Transaction, SigningError> {
+    run_signing_ceremony_with_config(key_data, transaction, prev_tx_outs, &CeremonyConfig::default()).await
+}
+
+/// A coordinator function to perform a FROST signing ceremony with the given configuration.
+pub async fn run_signing_ceremony_with_config(
+    key_data: KeyData,
+    transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    let (signers, transport) = setup_configured_signers(&key_data, config)?;
+    run_signing_ceremony_with_signers(&key_data, signers, transport, transaction, prev_tx_outs, config).await
+}
+
+/// Sets up the local signers with the policy, approval queue and state store of the configuration.
+pub fn setup_configured_signers(
+    key_data: &KeyData,
+    config: &CeremonyConfig,
+) -> Result<(HashMap<Identifier, FrostSigner>, Arc<InMemoryTransport>), SigningError> {
+    let (mut signers, transport) = setup_signers(key_data)?;
+    if let Some(policy) = &config.policy {
+        if policy.daily_limit.is_some() && config.state_dir.is_none() {
+            return Err(SigningError::PolicyViolation(
+                "the daily limit needs a state directory to keep the signed amounts".to_string(),
+            ));
+        }
+        for signer in signers.values_mut() {
+            *signer = signer.clone().with_policy(policy.clone(), config.network)?;
+        }
+    }
+    if let Some(approval_dir) = &config.approval_dir {
+        let approvals: Arc<dyn ApprovalQueue> = Arc::new(FileApprovalQueue::open(approval_dir)?);
+        for signer in signers.values_mut() {
+            *signer = signer.clone().with_approval_queue(approvals.clone());
+        }
+    }
+    if let Some(state_dir) = &config.state_dir {
+        for signer in signers.values_mut() {
+            let journal = FileJournal::open(state_dir.join(hex::encode(signer.participant_id.serialize())))?;
+            *signer = signer.clone().with_store(Arc::new(journal));
+            signer.recover()?;
+        }
+    }
+    Ok((signers, transport))
+}
 
-pub type SessionId = u64;
+/// A coordinator function to perform a FROST signing ceremony with the signers reachable over the given transport.
+/// Participants of the group without a local signer are treated as offline.
+pub async fn run_signing_ceremony_with_signers(
+    key_data: &KeyData,
+    signers: HashMap<Identifier, FrostSigner>,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    let session_id = SessionId::random();
+    run_signing_session(key_data, signers, transport, session_id, transaction, prev_tx_outs, config).await
+}
 
-/// Message transmitted between participants.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum SigningMessage {
-    NonceCommitment(SessionId, Identifier, Box<frost::round1::SigningCommitments>),
-    SignatureShare(SessionId, Identifier, frost::round2::SignatureShare),
+/// Runs the signing ceremony of a session whose id the caller chose, e.g. to report on it while it runs.
+pub async fn run_signing_session(
+    key_data: &KeyData,
+    mut signers: HashMap<Identifier, FrostSigner>,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    session_id: SessionId,
+    transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    let candidates = config.selection.candidates(key_data)?;
+    signers.retain(|id, _| candidates.contains(id));
+    let signers = LocalSigners::spawn(signers, config);
+    run_local_session(key_data, &signers, transport, session_id, transaction, prev_tx_outs, config).await
 }
 
-/// FROST state machine states
-#[derive(Debug, Clone)]
-pub enum SigningState {
-    /// Idle state
-    Idle,
+/// Runs the signing ceremony of a session on signers already running, other sessions may run on them at the same
+/// time. The transport must only deliver the messages of this session to the coordinator.
+#[instrument(skip_all, fields(session_id))]
+pub async fn run_local_session(
+    key_data: &KeyData,
+    signers: &LocalSigners,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    session_id: SessionId,
+    transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    tracing::Span::current().record("session_id", tracing::field::display(session_id));
+    info!("Starting signing ceremony.");
 
-    /// Round 1: All participants generate and broadcast commitments.
-    CollectingCommitments {
-        session_id: SessionId,
-        transaction: Transaction,
-        commitments: BTreeMap<Identifier, frost::round1::SigningCommitments>,
-        deadline: Instant,
-    },
+    let audit = config.audit.as_deref();
+    let started = Instant::now();
+    metrics::ceremony_started();
+    record_session_start(audit, session_id, &transaction, prev_tx_outs)?;
+    let result = sign_session(key_data, signers, transport, transaction, prev_tx_outs, config, session_id).await;
+    if let Err(e) = &result {
+        abort_signers(signers.signers(), session_id, e).await;
+    }
+    metrics::ceremony_finished(&result, started.elapsed());
+    record_session_outcome(audit, session_id, result)
+}
 
-    /// Round 2: Participants generate and broadcast signature shares.
-    CollectingShares {
-        session_id: SessionId,
-        signing_package: SigningPackage,
-        shares: BTreeMap<Identifier, frost::round2::SignatureShare>,
-        deadline: Instant,
-    },
+/// Fails the session on every local signer still taking part in it, each tells its peers to abort.
+async fn abort_signers(signers: &HashMap<Identifier, FrostSigner>, session_id: SessionId, error: &SigningError) {
+    for signer in signers.values() {
+        signer.abort(session_id, error).await;
+    }
+}
 
-    /// Finalize the transaction
-    Complete { signed_transaction: Transaction },
+/// Runs the signing ceremony of a session with participants that run their own signer (`run_remote_signer`) and are
+/// reached over the relay, they get the request and the signing package from the coordinator. Remote operators have
+/// until the round 2 timeout to approve.
+#[instrument(skip_all, fields(session_id))]
+pub async fn run_remote_session(
+    key_data: &KeyData,
+    transport: Arc<WebSocketTransport>,
+    request: &OfflineRequest,
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    let session_id = request.session_id;
+    tracing::Span::current().record("session_id", tracing::field::display(session_id));
+    info!("Starting signing ceremony with remote signers.");
 
-    /// Failed state
-    Failed { error: SigningError },
+    let audit = config.audit.as_deref();
+    let started = Instant::now();
+    metrics::ceremony_started();
+    record_session_start(audit, session_id, &request.request.transaction, &request.request.prev_tx_outs)?;
+    let result = sign_remote_session(key_data, transport, request, config).await;
+    metrics::ceremony_finished(&result, started.elapsed());
+    record_session_outcome(audit, session_id, result)
 }
 
-/// FROST Signer
-#[derive(Clone)]
-pub struct FrostSigner {
-    pub participant_id: Identifier,
-    pub key_package: frost::keys::KeyPackage,
-    state: Arc<Mutex<SigningState>>,
-    transport: Arc<dyn Transport<Msg = SigningMessage>>,
-}
+/// Both signing rounds of a session with remote signers.
+async fn sign_remote_session(
+    key_data: &KeyData,
+    transport: Arc<WebSocketTransport>,
+    request: &OfflineRequest,
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    let session_id = request.session_id;
+    let audit = config.audit.as_deref();
+    let guard = ReplayGuard::new(&key_data.public)?;
+    let ceremony_deadline = Instant::now() + config.ceremony_timeout;
+    let threshold = key_data.threshold as usize;
+    let candidates = config.selection.candidates(key_data)?;
 
-impl FrostSigner {
-    pub fn new(
-        participant_id: Identifier,
-        key_package: frost::keys::KeyPackage,
-        transport: Arc<dyn Transport<Msg = SigningMessage>>,
-    ) -> Self {
-        Self { participant_id, key_package, state: Arc::new(Mutex::new(SigningState::Idle)), transport }
+    // Round 1: the candidates commit to the request and broadcast their commitments.
+    let message = CoordinatorMessage::Request(request.clone());
+    for id in &candidates {
+        transport.send_to_participant(*id, &message)?;
     }
+    let collection = RoundCollection {
+        session_id,
+        round: SigningRound::Commitments,
+        expected: &candidates,
+        required: config.selection.required(threshold),
+        deadline: ceremony_deadline.min(Instant::now() + config.round1_timeout),
+        early_completion: config.early_completion,
+        audit,
+        guard: &guard,
+    };
+    let (commitments, responders) = collect_commitments(transport.clone(), collection).await?;
 
-    pub fn get_state(&self) -> Result<SigningState, SigningError> {
-        self.state
-            .lock()
-            .map_err(|e| SigningError::InternalError(format!("Failed to lock state mutex: {e}")))
-            .map(|s| s.clone())
-    }
+    let chosen = config.selection.choose(&responders, threshold)?;
+    info!(signers = ?chosen, "Selected signers for round 2.");
+    let participants = chosen.iter().cloned().collect();
+    record_event(audit, AuditEvent::Participants { session_id, participants })?;
+    let commitments = commitments.into_iter().filter(|(id, _)| chosen.contains(id)).collect();
+    let mut transaction = request.request.transaction.clone();
+    let signing_package = create_signing_package(&mut transaction, &request.request.prev_tx_outs, commitments)?;
 
-    /// Start round 1
-    #[instrument(skip(self, transaction), fields(participant_id = ?self.participant_id))]
-    pub async fn initiate_signing_round(
-        &self,
-        session_id: SessionId,
-        transaction: Transaction,
-    ) -> Result<frost::round1::SigningNonces, SigningError> {
-        let (nonces, commitments) = {
-            let mut state = self
-                .state
-                .lock()
-                .map_err(|e| SigningError::InternalError(format!("Failed to lock state mutex: {e}")))?;
+    // Round 2: every candidate gets the package, the chosen ones sign it and the others drop their nonces.
+    let message = CoordinatorMessage::Package(request.clone(), signing_package.clone());
+    for id in &candidates {
+        transport.send_to_participant(*id, &message)?;
+    }
+    let deadline = ceremony_deadline.min(Instant::now() + config.round2_timeout);
+    let shares = collect_shares(transport, session_id, &chosen, deadline, audit, &guard).await?;
+    let transaction = aggregate_signature(key_data, &signing_package, &shares, transaction)?;
+    info!("Signing ceremony complete, transaction is finalized.");
+    Ok(transaction)
+}
 
-            if !matches!(*state, SigningState::Idle) {
-                return Err(SigningError::InvalidState("Signer is not in Idle state.".to_string()));
-            }
+/// Both signing rounds of a session.
+async fn sign_session(
+    key_data: &KeyData,
+    signers: &LocalSigners,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    mut transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+    session_id: SessionId,
+) -> Result<Transaction, SigningError> {
+    let audit = config.audit.as_deref();
+    let guard = ReplayGuard::new(&key_data.public)?;
+    let mut ceremony_deadline = Instant::now() + config.ceremony_timeout;
+    let threshold = key_data.threshold as usize;
+    let candidates = config.selection.candidates(key_data)?;
+    let LocalSigners { signers, handles } = signers.select(&candidates);
 
-            let deadline = Instant::now() + Duration::from_secs(60);
-            *state =
-                SigningState::CollectingCommitments { session_id, transaction, commitments: BTreeMap::new(), deadline };
+    // Round 1: Candidate participants generate and broadcast commitments.
+    perform_round_one(&handles, session_id, &transaction, prev_tx_outs).await?;
+    let required = config.selection.required(threshold);
+    let collection = RoundCollection {
+        session_id,
+        round: SigningRound::Commitments,
+        expected: &candidates,
+        required,
+        deadline: ceremony_deadline.min(Instant::now() + config.round1_timeout),
+        early_completion: config.early_completion,
+        audit,
+        guard: &guard,
+    };
+    let (commitments, responders) = collect_commitments(transport.clone(), collection).await?;
 
-            frost::round1::commit(self.key_package.signing_share(), &mut OsRng)
-        };
+    // Only the chosen participants take part in round 2, the rest are released. Only local signers can be asked to
+    // sign, so every participant in the signing package provides a share.
+    let responders: Vec<Identifier> = responders.into_iter().filter(|id| signers.contains_key(id)).collect();
+    let chosen = config.selection.choose(&responders, threshold)?;
+    info!(signers = ?chosen, "Selected signers for round 2.");
+    let participants = chosen.iter().cloned().collect();
+    record_event(audit, AuditEvent::Participants { session_id, participants })?;
+    let commitments = commitments.into_iter().filter(|(id, _)| chosen.contains(id)).collect();
+    let signing_package = create_signing_package(&mut transaction, prev_tx_outs, commitments)?;
 
-        debug!("Broadcasting nonce commitment.");
-        let msg = SigningMessage::NonceCommitment(session_id, self.participant_id, Box::new(commitments));
-        self.transport.broadcast(msg).await?;
+    // Round 2: the chosen participants sign, after operator approval if required, and broadcast their shares.
+    let chosen_signers: HashMap<_, _> = signers.into_iter().filter(|(id, _)| chosen.contains(id)).collect();
+    ceremony_deadline += perform_round_two(&chosen_signers, &handles, session_id, &signing_package).await?;
+    let deadline = ceremony_deadline.min(Instant::now() + config.round2_timeout);
+    let shares = collect_shares(transport, session_id, &chosen, deadline, audit, &guard).await?;
+    finalize_transaction(key_data, &handles, &chosen, session_id, &signing_package, &shares, transaction).await
+}
 
-        Ok(nonces)
+/// Tops up the coordinator's commitment pool from signers running low on preprocessed nonces.
+/// Drains the transport, so it must not run concurrently with a signing ceremony on the same transport.
+pub async fn replenish_commitments(
+    key_data: &KeyData,
+    signers: &HashMap<Identifier, FrostSigner>,
+    transport: &Arc<InMemoryTransport>,
+    pool: &mut CommitmentPool,
+    batch_size: usize,
+) -> Result<(), SigningError> {
+    for signer in signers.values() {
+        if pool.needs_replenishment(signer.participant_id) {
+            signer.preprocess(batch_size).await?;
+        }
     }
-
-    /// Start round 2
-    #[instrument(skip(self, signing_package), fields(participant_id = ?self.participant_id))]
-    pub fn advance_to_sharing_round(&self, signing_package: SigningPackage) -> Result<(), SigningError> {
-        let mut state = self.state.lock().map_err(|e| SigningError::InternalError(e.to_string()))?;
-
-        match state.deref_mut() {
-            SigningState::CollectingCommitments { session_id, .. } => {
-                debug!("Transitioning to CollectingShares state.");
-                *state = SigningState::CollectingShares {
-                    session_id: *session_id,
-                    signing_package,
-                    shares: BTreeMap::new(),
-                    deadline: Instant::now() + Duration::from_secs(60),
-                };
-                Ok(())
-            }
-            s => Err(SigningError::InvalidState(format!("Cannot advance to sharing round from state {s:?}"))),
+    let guard = ReplayGuard::new(&key_data.public)?;
+    while let Some((_, envelope)) = transport.receive().await? {
+        if let Err(e) = guard.open(&envelope, 0) {
+            debug!(from = ?envelope.sender(), "Dropping message: {e}");
+            continue;
+        }
+        if let SigningMessage::PreprocessedCommitments(sender, batch) = envelope.message {
+            pool.add(sender, batch);
         }
     }
+    Ok(())
+}
 
-    /// Broadcast signature shares.
-    #[instrument(skip(self, nonces), fields(participant_id = ?self.participant_id))]
-    pub async fn sign_and_broadcast_share(&self, nonces: &frost::round1::SigningNonces) -> Result<(), SigningError> {
-        let (share, session_id) = {
-            let state = self.state.lock().map_err(|e| SigningError::InternalError(e.to_string()))?;
-            match &*state {
-                SigningState::CollectingShares { signing_package, session_id, .. } => {
-                    let share = frost::round2::sign_with_tweak(signing_package, nonces, &self.key_package, None)?;
-                    (share, *session_id)
-                }
-                s => return Err(SigningError::InvalidState(format!("Cannot sign share in state {s:?}"))),
-            }
-        };
-
-        let msg = SigningMessage::SignatureShare(session_id, self.participant_id, share);
-        self.transport.broadcast(msg).await?;
-        Ok(())
-    }
+/// A coordinator function to perform a single round FROST signing ceremony from preprocessed commitments.
+#[instrument(skip_all, fields(session_id))]
+pub async fn run_preprocessed_signing_ceremony(
+    key_data: &KeyData,
+    signers: &HashMap<Identifier, FrostSigner>,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    pool: &mut CommitmentPool,
+    mut transaction: Transaction,
+    prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+) -> Result<Transaction, SigningError> {
+    let session_id = SessionId::random();
+    tracing::Span::current().record("session_id", tracing::field::display(session_id));
+    info!("Starting preprocessed signing ceremony.");
 
-    /// Finalize the transaction
-    #[instrument(skip(self, signed_transaction), fields(participant_id = ?self.participant_id))]
-    pub fn complete_signing(&self, signed_transaction: Transaction) {
-        let mut state = self.state.lock().unwrap();
-        if !matches!(*state, SigningState::CollectingShares { .. }) {
-            warn!("Completing signature from unexpected state.");
-        }
-        *state = SigningState::Complete { signed_transaction };
-    }
-
-    /// Process messages from other participants.
-    #[instrument(skip(self, msg), fields(participant_id = ?self.participant_id))]
-    pub async fn process_message(&self, msg: SigningMessage) -> Result<(), SigningError> {
-        let mut state =
-            self.state.lock().map_err(|e| SigningError::InternalError(format!("Failed to lock state mutex: {e}")))?;
-
-        match state.deref_mut() {
-            SigningState::CollectingCommitments { session_id, commitments, .. } => {
-                if let SigningMessage::NonceCommitment(msg_session_id, sender, new_commitments) = msg {
-                    if msg_session_id == *session_id {
-                        debug!(from = ?sender, "Received nonce commitment.");
-                        commitments.insert(sender, *new_commitments);
-                    }
-                }
-            }
-            SigningState::CollectingShares { session_id, shares, .. } => {
-                if let SigningMessage::SignatureShare(msg_session_id, sender, share) = msg {
-                    if msg_session_id == *session_id {
-                        // TODO: need to verify received signature shares are valid to fail early and prevent certain attacks.
-                        debug!(from = ?sender, "Received signature share.");
-                        shares.insert(sender, share);
-                    }
-                }
-            }
-            _ => {
-                warn!("Received message in unexpected state.");
-            }
-        }
-        Ok(())
+    let audit = config.audit.as_deref();
+    let started = Instant::now();
+    metrics::ceremony_started();
+    record_session_start(audit, session_id, &transaction, prev_tx_outs)?;
+    let result =
+        sign_preprocessed_session(key_data, signers, transport, pool, transaction, prev_tx_outs, config, session_id)
+            .await;
+    if let Err(e) = &result {
+        abort_signers(signers, session_id, e).await;
     }
+    metrics::ceremony_finished(&result, started.elapsed());
+    record_session_outcome(audit, session_id, result)
 }
 
-/// A coordinator function to perform a FROST signing ceremony for a Taproot input.
-#[instrument(skip_all, fields(session_id))]
-pub async fn run_signing_ceremony(
-    key_data: KeyData,
+/// Single signing round of a session from preprocessed commitments.
+#[allow(clippy::too_many_arguments)]
+async fn sign_preprocessed_session(
+    key_data: &KeyData,
+    signers: &HashMap<Identifier, FrostSigner>,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    pool: &mut CommitmentPool,
     mut transaction: Transaction,
     prev_tx_outs: &[TxOut],
+    config: &CeremonyConfig,
+    session_id: SessionId,
 ) -> Result<Transaction, SigningError> {
-    let session_id = rand::random::<SessionId>();
-    tracing::Span::current().record("session_id", session_id);
-    info!("Starting signing ceremony.");
+    let audit = config.audit.as_deref();
+    let guard = ReplayGuard::new(&key_data.public)?;
 
-    let (signers, transport) = setup_signers(&key_data)?;
+    // Pick the signers from the participants with unused commitments in the pool, no commitment round needed
+    let mut ceremony_deadline = Instant::now() + config.ceremony_timeout;
+    let threshold = key_data.threshold as usize;
+    let candidates = config.selection.candidates(key_data)?;
+    let ready: Vec<Identifier> =
+        candidates.iter().filter(|id| signers.contains_key(*id) && pool.available(**id) > 0).cloned().collect();
+    let chosen = config.selection.choose(&ready, threshold).map_err(|e| {
+        match candidates.iter().find(|id| signers.contains_key(*id) && !ready.contains(id)) {
+            Some(exhausted) => SigningError::NoncePoolExhausted(*exhausted),
+            None => e,
+        }
+    })?;
+    info!(signers = ?chosen, "Selected signers.");
+    let participants = chosen.iter().cloned().collect();
+    record_event(audit, AuditEvent::Participants { session_id, participants })?;
 
-    // Round 1: All participants generate and broadcast commitments.
-    let nonces = perform_round_one(&signers, session_id, transaction.clone()).await?;
-    let commitments = collect_commitments(transport.clone(), &signers).await?;
-    if commitments.len() < key_data.threshold as usize {
-        return Err(SigningError::NotEnoughSigners);
+    let mut commitment_ids = BTreeMap::new();
+    let mut commitments = BTreeMap::new();
+    for id in &chosen {
+        let (commitment_id, signer_commitments) = pool.take(*id)?;
+        commitment_ids.insert(*id, commitment_id);
+        commitments.insert(*id, signer_commitments);
     }
     let signing_package = create_signing_package(&mut transaction, prev_tx_outs, commitments)?;
 
-    // Transition signers to Round 2
-    for signer in signers.values() {
-        signer.advance_to_sharing_round(signing_package.clone())?;
-    }
+    // Single round: the chosen signers sign with their preprocessed nonces, after operator approval if required.
+    info!("Generating and broadcasting signature shares.");
+    let signers: HashMap<_, _> = signers
+        .iter()
+        .filter(|(id, _)| chosen.contains(*id))
+        .map(|(id, signer)| (*id, signer.clone().with_config(config.clone())))
+        .collect();
+    let handles: HashMap<_, _> = signers.iter().map(|(id, signer)| (*id, signer.spawn())).collect();
+    let started = Instant::now();
+    try_join_all(handles.iter().map(|(id, handle)| {
+        let request = SigningRequest::new(transaction.clone(), prev_tx_outs);
+        handle.sign_preprocessed(session_id, request, signing_package.clone(), commitment_ids[id])
+    }))
+    .await?;
+    ceremony_deadline += approval_time(&signers, started.elapsed());
+
+    let expected = signers.keys().cloned().collect();
+    let deadline = ceremony_deadline.min(Instant::now() + config.round2_timeout);
+    let shares = collect_shares(transport, session_id, &expected, deadline, audit, &guard).await?;
+    finalize_transaction(key_data, &handles, &expected, session_id, &signing_package, &shares, transaction).await
+}
+
+/// Aggregates the signature shares, finalizes the transaction and completes the signers.
+async fn finalize_transaction(
+    key_data: &KeyData,
+    handles: &HashMap<Identifier, SignerHandle>,
+    signers: &BTreeSet<Identifier>,
+    session_id: SessionId,
+    signing_package: &SigningPackage,
+    shares: &BTreeMap<Identifier, frost::round2::SignatureShare>,
+    transaction: Transaction,
+) -> Result<Transaction, SigningError> {
+    let transaction = aggregate_signature(key_data, signing_package, shares, transaction)?;
 
-    // Round 2: Participants generate and broadcast signature shares.
-    perform_round_two(&signers, &nonces).await?;
-    let shares = collect_shares(transport, &signers).await?;
+    // Transition signers to complete state
+    try_join_all(signers.iter().map(|id| handles[id].complete(session_id, transaction.clone()))).await?;
+
+    info!("Signing ceremony complete, transaction is finalized.");
+    Ok(transaction)
+}
+
+/// Aggregates the signature shares and adds the signature to the transaction.
+fn aggregate_signature(
+    key_data: &KeyData,
+    signing_package: &SigningPackage,
+    shares: &BTreeMap<Identifier, frost::round2::SignatureShare>,
+    mut transaction: Transaction,
+) -> Result<Transaction, SigningError> {
     if shares.len() < key_data.threshold as usize {
         return Err(SigningError::NotEnoughSigners);
     }
 
-    // Aggregate the shares into a final signature.
-    let group_signature = frost::aggregate_with_tweak(&signing_package, &shares, &key_data.public, None)?;
+    // Aggregate the shares into a final signature, a failed aggregation identifies the invalid share.
+    let group_signature =
+        frost::aggregate_with_tweak(signing_package, shares, &key_data.public, None).map_err(|e| {
+            match e.culprit() {
+                Some(culprit) => {
+                    metrics::invalid_share(culprit);
+                    SigningError::InvalidSignatureShare(culprit)
+                }
+                None => e.into(),
+            }
+        })?;
     let signature_bytes = frost::Secp256K1Sha256TR::serialize_signature(&group_signature)?;
     debug!(aggregated_signature = %hex::encode(&signature_bytes), "Signature aggregation successful.");
 
     // Finalize the transaction
     transaction.input[0].witness.push(signature_bytes);
+    Ok(transaction)
+}
 
-    // Transition signers to complete state
-    for signer in signers.values() {
-        signer.complete_signing(transaction.clone());
+/// Outcome of recovering a session after a restart.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovery {
+    /// The session was complete or every signature share had been collected, the signed transaction is returned.
+    Completed(Transaction),
+
+    /// The session could not be finished and was aborted.
+    Aborted,
+}
+
+/// Coordinator recovery of a session from its journal after a restart. The session is finished if every signature
+/// share had already been collected, otherwise it is aborted: nonces are never persisted, so round 1 can't be resumed.
+pub fn recover_session(
+    key_data: &KeyData,
+    store: &dyn StateStore,
+    session_id: SessionId,
+) -> Result<Recovery, SigningError> {
+    let record = SessionRecord::replay(store.load(session_id)?);
+    if let Some(signed_transaction) = record.signed_transaction {
+        return Ok(Recovery::Completed(signed_transaction));
+    }
+    if record.error.is_some() {
+        return Ok(Recovery::Aborted);
     }
 
-    info!("Signing ceremony complete, transaction is finalized.");
-    Ok(transaction)
+    if let (Some(signing_package), Some(mut transaction)) = (&record.signing_package, record.transaction) {
+        let all_shares = signing_package.signing_commitments().keys().all(|id| record.shares.contains_key(id));
+        if all_shares {
+            info!(%session_id, "Resuming session, aggregating the journaled signature shares.");
+            let group_signature = frost::aggregate_with_tweak(signing_package, &record.shares, &key_data.public, None)?;
+            let signature_bytes = frost::Secp256K1Sha256TR::serialize_signature(&group_signature)?;
+            transaction.input[0].witness.push(signature_bytes);
+            store.append(session_id, &JournalEntry::Complete { signed_transaction: transaction.clone() })?;
+            return Ok(Recovery::Completed(transaction));
+        }
+    }
+
+    warn!(%session_id, "Aborting session interrupted by a restart.");
+    store.append(session_id, &JournalEntry::Failed { error: "Session aborted after restart.".to_string() })?;
+    Ok(Recovery::Aborted)
 }
 
-/// Initializes the signers and the transport layer for communication.
+/// Initializes the signers and the transport layer for communication, each signer gets its own mailbox. Returns the
+/// coordinator's handle of the transport, it receives the broadcasts of all signers.
 pub fn setup_signers(
     key_data: &KeyData,
 ) -> Result<(HashMap<Identifier, FrostSigner>, Arc<InMemoryTransport>), SigningError> {
     let identifiers = key_data.key_packages.keys().cloned().collect();
     let transport = Arc::new(InMemoryTransport::new(identifiers));
-    let signers: HashMap<_, _> = key_data
+    let signers = setup_signers_with(key_data, |id| Ok(Arc::new(transport.participant(id)?)))?;
+    Ok((signers, transport))
+}
+
+/// Initializes the signers, each communicating over the transport handle `mailbox` returns for it.
+pub fn setup_signers_with(
+    key_data: &KeyData,
+    mailbox: impl Fn(Identifier) -> Result<Arc<dyn Transport<Msg = Envelope>>, SigningError>,
+) -> Result<HashMap<Identifier, FrostSigner>, SigningError> {
+    key_data
         .key_packages
         .iter()
         .map(|(identifier, key_package)| {
-            let signer = FrostSigner::new(*identifier, key_package.clone(), transport.clone());
-            (*identifier, signer)
+            let signer = FrostSigner::new(*identifier, key_package.clone(), &key_data.public, mailbox(*identifier)?)?;
+            Ok((*identifier, signer))
         })
-        .collect();
-    Ok((signers, transport))
+        .collect()
 }
 
 /// Executes Round 1 of the signing protocol for all participants.
 async fn perform_round_one(
-    signers: &HashMap<Identifier, FrostSigner>,
+    handles: &HashMap<Identifier, SignerHandle>,
     session_id: SessionId,
-    transaction: Transaction,
-) -> Result<BTreeMap<Identifier, frost::round1::SigningNonces>, SigningError> {
+    transaction: &Transaction,
+    prev_tx_outs: &[TxOut],
+) -> Result<(), SigningError> {
     info!("Initiating Round 1: Generating and broadcasting commitments.");
-    let mut nonces = BTreeMap::new();
-    for (id, signer) in signers.iter() {
-        let signer_nonces = signer.initiate_signing_round(session_id, transaction.clone()).await?;
-        nonces.insert(*id, signer_nonces);
-    }
-    Ok(nonces)
+    try_join_all(
+        handles.values().map(|handle| handle.start(session_id, SigningRequest::new(transaction.clone(), prev_tx_outs))),
+    )
+    .await?;
+    Ok(())
 }
 
 /// Waits for and processes messages to collect commitments.
+/// Returns the collected commitments and the participants that sent them, in order of arrival.
 async fn collect_commitments(
-    transport: Arc<InMemoryTransport>,
-    signers: &HashMap<Identifier, FrostSigner>,
-) -> Result<BTreeMap<Identifier, frost::round1::SigningCommitments>, SigningError> {
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    collection: RoundCollection<'_>,
+) -> Result<(BTreeMap<Identifier, frost::round1::SigningCommitments>, Vec<Identifier>), SigningError> {
     info!("Collecting nonce commitments from all participants.");
+    let messages = collection.run(&transport).await?;
 
-    let deadline = signers
-        .values()
-        .find_map(|s| match s.get_state().ok()? {
-            SigningState::CollectingCommitments { deadline, .. } => Some(deadline),
-            _ => None,
-        })
-        .ok_or_else(|| SigningError::InvalidState("Signers not in commitment collection state.".to_string()))?;
-
-    // Drain the message queue until the deadline is hit or the queue is empty
-    loop {
-        let now = std::time::Instant::now();
-        if now >= deadline {
-            break; // Timeout is handled by extracting whatever we have below
-        }
-        let remaining_time = deadline - now;
-
-        match timeout(remaining_time, transport.receive()).await {
-            Ok(Ok(Some((_, message)))) => {
-                for signer in signers.values() {
-                    signer.process_message(message.clone()).await?;
-                }
-            }
-            Ok(Ok(None)) | Err(_) => {
-                // Channel closed or timeout, break and process what was received
-                break;
-            }
-            Ok(Err(e)) => return Err(e.into()), // Transport error
+    let mut commitments = BTreeMap::new();
+    let mut responders = Vec::new();
+    for message in messages {
+        if let SigningMessage::NonceCommitment(_, sender, signer_commitments) = message {
+            commitments.insert(sender, *signer_commitments);
+            responders.push(sender);
         }
     }
-
-    // Extract the collected commitments from any signer (they should all be in sync)
-    signers
-        .values()
-        .find_map(|s| {
-            if let Ok(SigningState::CollectingCommitments { commitments, .. }) = s.get_state() {
-                Some(commitments)
-            } else {
-                None
-            }
-        })
-        .ok_or_else(|| SigningError::InternalError("Could not retrieve commitments.".to_string()))
+    Ok((commitments, responders))
 }
 
 /// Creates the signing package, which includes the message to be signed (sighash).
@@ -339,66 +2059,227 @@ fn create_signing_package(
     Ok(SigningPackage::new(commitments, sighash.as_ref()))
 }
 
-/// Executes Round 2 of the signing protocol for all participants.
+/// Records an event if the ceremony has an audit log.
+fn record_event(audit: Option<&AuditLog>, event: AuditEvent) -> Result<(), SigningError> {
+    if let Some(audit) = audit {
+        audit.record(event)?;
+    }
+    Ok(())
+}
+
+fn record_session_start(
+    audit: Option<&AuditLog>,
+    session_id: SessionId,
+    transaction: &Transaction,
+    prev_tx_outs: &[TxOut],
+) -> Result<(), SigningError> {
+    if audit.is_none() {
+        return Ok(());
+    }
+    let sighash = hex::encode(SigningRequest::new(transaction.clone(), prev_tx_outs).sighash()?);
+    record_event(audit, AuditEvent::SessionStarted { session_id, txid: transaction.compute_txid(), sighash })
+}
+
+/// Records the outcome of a session. A failed session keeps its error when the audit log cannot be written.
+fn record_session_outcome(
+    audit: Option<&AuditLog>,
+    session_id: SessionId,
+    result: Result<Transaction, SigningError>,
+) -> Result<Transaction, SigningError> {
+    match result {
+        Ok(signed_transaction) => {
+            let txid = signed_transaction.compute_txid();
+            record_event(audit, AuditEvent::SessionCompleted { session_id, txid })?;
+            Ok(signed_transaction)
+        }
+        Err(e) => {
+            let event = AuditEvent::SessionFailed { session_id, error: e.to_string() };
+            if let Err(audit_error) = record_event(audit, event) {
+                warn!(%session_id, "Failed to record the session failure to the audit log: {audit_error}");
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Records a received session message with the hash of its serialized form.
+fn record_message(audit: Option<&AuditLog>, message: &SigningMessage) -> Result<(), SigningError> {
+    let (Some(audit), Some(session_id)) = (audit, message.session_id()) else {
+        return Ok(());
+    };
+    let serialized = serde_json::to_vec(message).map_err(|e| SigningError::InternalError(e.to_string()))?;
+    let digest = sha256::Hash::hash(&serialized).to_string();
+    let event =
+        AuditEvent::MessageReceived { session_id, from: message.sender(), kind: message.kind().to_string(), digest };
+    audit.record(event)?;
+    Ok(())
+}
+
+/// Executes Round 2 of the signing protocol for the chosen participants, each signs once its operator approved.
+/// Returns the time spent waiting for operator approval, which doesn't count towards the ceremony timeout.
 async fn perform_round_two(
     signers: &HashMap<Identifier, FrostSigner>,
-    nonces: &BTreeMap<Identifier, frost::round1::SigningNonces>,
-) -> Result<(), SigningError> {
+    handles: &HashMap<Identifier, SignerHandle>,
+    session_id: SessionId,
+    signing_package: &SigningPackage,
+) -> Result<Duration, SigningError> {
     info!("Initiating Round 2: Generating and broadcasting signature shares.");
-    for signer in signers.values() {
-        let nonce = &nonces[&signer.participant_id];
-        signer.sign_and_broadcast_share(nonce).await?;
+    let started = Instant::now();
+    try_join_all(signers.keys().map(|id| handles[id].sign(session_id, signing_package.clone()))).await?;
+    Ok(approval_time(signers, started.elapsed()))
+}
+
+/// Time spent in round 2 before the shares were broadcast, if any signer waited for operator approval.
+fn approval_time(signers: &HashMap<Identifier, FrostSigner>, elapsed: Duration) -> Duration {
+    if !signers.values().any(FrostSigner::requires_approval) {
+        return Duration::ZERO;
     }
-    Ok(())
+    metrics::round_completed(SigningRound::Approval, elapsed);
+    elapsed
 }
 
 /// Waits for and processes signature shares.
 async fn collect_shares(
-    transport: Arc<InMemoryTransport>,
-    signers: &HashMap<Identifier, FrostSigner>,
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    session_id: SessionId,
+    expected: &BTreeSet<Identifier>,
+    deadline: Instant,
+    audit: Option<&AuditLog>,
+    guard: &ReplayGuard,
 ) -> Result<BTreeMap<Identifier, frost::round2::SignatureShare>, SigningError> {
     info!("Collecting signature shares from all participants.");
 
-    let deadline = signers
-        .values()
-        .find_map(|s| match s.get_state().ok()? {
-            SigningState::CollectingShares { deadline, .. } => Some(deadline),
+    // Every participant in the signing package has to provide a share
+    let collection = RoundCollection {
+        session_id,
+        round: SigningRound::Shares,
+        expected,
+        required: expected.len(),
+        deadline,
+        early_completion: true,
+        audit,
+        guard,
+    };
+    let messages = collection.run(&transport).await?;
+
+    Ok(messages
+        .into_iter()
+        .filter_map(|message| match message {
+            SigningMessage::SignatureShare(_, sender, share) => Some((sender, share)),
             _ => None,
         })
-        .ok_or_else(|| SigningError::InvalidState("Signers not in share collection state.".to_string()))?;
+        .collect())
+}
 
-    // Drain the message queue until the deadline is hit or the queue is empty
-    loop {
-        let now = std::time::Instant::now();
-        if now >= deadline {
-            break;
-        }
-        let remaining_time = deadline - now;
+/// Collection progress of a round, the CollectingCommitments / CollectingShares substates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RoundStatus {
+    /// Waiting for more participants to respond.
+    Waiting,
+
+    /// Enough participants responded, the round can complete.
+    ThresholdMet,
+
+    /// The round deadline passed before the round completed.
+    DeadlineReached,
+}
+
+/// Collection of a single round of messages from the expected participants.
+struct RoundCollection<'a> {
+    session_id: SessionId,
+    round: SigningRound,
+    expected: &'a BTreeSet<Identifier>,
+    required: usize,
+    deadline: Instant,
+    early_completion: bool,
+    audit: Option<&'a AuditLog>,
+    guard: &'a ReplayGuard,
+}
 
-        match timeout(remaining_time, transport.receive()).await {
-            Ok(Ok(Some((_, message)))) => {
-                for signer in signers.values() {
-                    signer.process_message(message.clone()).await?;
+impl RoundCollection<'_> {
+    /// Receives the broadcasts of the participants until the round completes. Returns the round messages of the
+    /// responders in order of arrival, fails as soon as a participant aborts the session.
+    async fn run(&self, transport: &dyn Transport<Msg = Envelope>) -> Result<Vec<SigningMessage>, SigningError> {
+        let started = Instant::now();
+        let mut responders: Vec<Identifier> = Vec::new();
+        let mut messages = Vec::new();
+        loop {
+            match self.status(&responders) {
+                RoundStatus::ThresholdMet => {
+                    debug!(round = %self.round, responders = responders.len(), "Round collection complete.");
+                    metrics::round_completed(self.round, started.elapsed());
+                    return Ok(messages);
+                }
+                RoundStatus::DeadlineReached if responders.len() >= self.required => {
+                    metrics::round_completed(self.round, started.elapsed());
+                    return Ok(messages);
                 }
+                RoundStatus::DeadlineReached => {
+                    let missing = self.expected.iter().filter(|id| !responders.contains(id)).cloned().collect();
+                    return Err(SigningError::Timeout { round: self.round, missing });
+                }
+                RoundStatus::Waiting => {}
             }
-            // Channel closed or timeout, break and process what was received
-            Ok(Ok(None)) | Err(_) => {
-                break;
+
+            // Sleep until a message arrives or the deadline passes
+            let remaining_time = self.deadline.saturating_duration_since(Instant::now());
+            match timeout(remaining_time, transport.next_message()).await {
+                Ok(Ok((_, envelope))) => {
+                    if let Err(e) = self.guard.open(&envelope, self.round.number()) {
+                        // Late messages of the previous round are expected
+                        match e {
+                            EnvelopeError::Duplicate { .. } | EnvelopeError::StaleRound { .. } => {
+                                debug!(from = ?envelope.sender(), "Dropping message: {e}")
+                            }
+                            _ => warn!(from = ?envelope.sender(), "Dropping message: {e}"),
+                        }
+                        continue;
+                    }
+                    record_message(self.audit, &envelope.message)?;
+                    metrics::message_received(&envelope.message);
+                    match envelope.message {
+                        SigningMessage::Abort(session_id, by, reason) if session_id == self.session_id => {
+                            return Err(SigningError::Aborted { session_id, by, reason });
+                        }
+                        message => {
+                            if let Some(sender) = self.sender_of(&message) {
+                                if !responders.contains(&sender) {
+                                    responders.push(sender);
+                                    messages.push(message);
+                                }
+                            }
+                        }
+                    }
+                }
+                // Deadline reached, handled by the status check
+                Err(_) => {}
+                // Transport error
+                Ok(Err(e)) => return Err(e.into()),
             }
-            // Transport error
-            Ok(Err(e)) => return Err(e.into()),
         }
     }
 
-    // Extract the collected shares from any signer
-    signers
-        .values()
-        .find_map(|s| {
-            if let Ok(SigningState::CollectingShares { shares, .. }) = s.get_state() {
-                Some(shares)
-            } else {
-                None
-            }
-        })
-        .ok_or_else(|| SigningError::InternalError("Could not retrieve shares.".to_string()))
+    /// Current collection progress given the participants that responded so far.
+    fn status(&self, responders: &[Identifier]) -> RoundStatus {
+        if responders.len() == self.expected.len() || (self.early_completion && responders.len() >= self.required) {
+            RoundStatus::ThresholdMet
+        } else if Instant::now() >= self.deadline {
+            RoundStatus::DeadlineReached
+        } else {
+            RoundStatus::Waiting
+        }
+    }
+
+    /// Expected sender of a message belonging to this round of the session.
+    fn sender_of(&self, message: &SigningMessage) -> Option<Identifier> {
+        if message.session_id() != Some(self.session_id) {
+            return None;
+        }
+        let sender = match (self.round, message) {
+            (SigningRound::Commitments, SigningMessage::NonceCommitment(_, sender, _)) => *sender,
+            (SigningRound::Shares, SigningMessage::SignatureShare(_, sender, _)) => *sender,
+            _ => return None,
+        };
+        self.expected.contains(&sender).then_some(sender)
+    }
 }
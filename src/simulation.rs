@@ -0,0 +1,280 @@
+use crate::{
+    codec::{from_bytes, to_bytes},
+    envelope::Envelope,
+    errors::{SigningError, TransportError},
+    keys::KeyData,
+    signer::{setup_signers_with, FrostSigner},
+    transport::{InMemoryTransport, Transport},
+};
+use async_trait::async_trait;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use frost_secp256k1_tr::Identifier;
+use rand::Rng;
+use rand_chacha::{rand_core::SeedableRng, ChaCha8Rng};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Faults injected into the messages of a simulated network. Probabilities are between 0 and 1.
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// Seed of the fault decisions, a run with the same seed and message order injects the same faults.
+    pub seed: u64,
+
+    /// Probability a message is lost.
+    pub drop_rate: f64,
+
+    /// Probability a message is delivered twice.
+    pub duplicate_rate: f64,
+
+    /// Probability a message is held back, so later messages overtake it.
+    pub reorder_rate: f64,
+
+    /// Longest time a reordered message is held back.
+    pub reorder_window: Duration,
+
+    /// Latency added to every message.
+    pub delay: Duration,
+
+    /// Probability the payload of a message is changed in transit, its envelope signature no longer verifies.
+    pub tamper_rate: f64,
+}
+
+/// Number of messages affected by each fault so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub delivered: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub delayed: u64,
+    pub tampered: u64,
+    pub partitioned: u64,
+}
+
+/// In memory network injecting faults into the messages its handles receive. Every receiver decides on the faults
+/// of its copy of a broadcast on its own.
+pub struct SimulatedNetwork {
+    network: InMemoryTransport,
+    faults: FaultConfig,
+    isolated: Mutex<BTreeSet<Identifier>>,
+    stats: Mutex<FaultStats>,
+}
+
+impl SimulatedNetwork {
+    pub fn new(participants: Vec<Identifier>, faults: FaultConfig) -> Arc<Self> {
+        Arc::new(Self {
+            network: InMemoryTransport::new(participants),
+            faults,
+            isolated: Mutex::new(BTreeSet::new()),
+            stats: Mutex::new(FaultStats::default()),
+        })
+    }
+
+    /// Cuts the given participants off from the rest of the network, including the coordinator.
+    pub fn partition(&self, isolated: impl IntoIterator<Item = Identifier>) {
+        *lock(&self.isolated) = isolated.into_iter().collect();
+    }
+
+    /// Reconnects all participants.
+    pub fn heal(&self) {
+        lock(&self.isolated).clear();
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        *lock(&self.stats)
+    }
+
+    /// Handle of the coordinator, it receives the broadcasts of all participants.
+    pub fn coordinator(self: &Arc<Self>) -> SimulatedTransport {
+        SimulatedTransport::new(self.clone(), self.network.clone())
+    }
+
+    /// Handle of a participant.
+    pub fn participant(self: &Arc<Self>, id: Identifier) -> Result<SimulatedTransport, TransportError> {
+        Ok(SimulatedTransport::new(self.clone(), self.network.participant(id)?))
+    }
+
+    /// Initializes the signers on the simulated network, returns them with the coordinator's handle.
+    pub fn setup_signers(
+        self: &Arc<Self>,
+        key_data: &KeyData,
+    ) -> Result<(HashMap<Identifier, FrostSigner>, Arc<SimulatedTransport>), SigningError> {
+        let signers = setup_signers_with(key_data, |id| Ok(Arc::new(self.participant(id)?)))?;
+        Ok((signers, Arc::new(self.coordinator())))
+    }
+
+    /// Whether a message from the sender to the receiver crosses the partition, the coordinator is never isolated.
+    fn is_cut(&self, sender: Identifier, receiver: Option<Identifier>) -> bool {
+        let isolated = lock(&self.isolated);
+        isolated.contains(&sender) != receiver.is_some_and(|id| isolated.contains(&id))
+    }
+
+    fn record(&self, update: impl FnOnce(&mut FaultStats)) {
+        update(&mut lock(&self.stats));
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Message held back until it is due.
+struct Held {
+    due: Instant,
+    sender: Identifier,
+    envelope: Envelope,
+}
+
+/// Encoded size of the envelope fields before the message: version, group id, round and sequence.
+const ENVELOPE_HEADER_SIZE: usize = 2 + 32 + 1 + 8;
+
+/// Size of the envelope signature following the message.
+const SIGNATURE_SIZE: usize = 64;
+
+/// Flips a byte of the message in the envelope. The flip keeps the sender and the round, so receivers can only reject
+/// the message by its signature and blame the sender. The sequence is changed instead if no flip decodes.
+fn tamper(rng: &mut ChaCha8Rng, envelope: Envelope) -> Envelope {
+    if let Ok(bytes) = to_bytes(&envelope) {
+        let payload = ENVELOPE_HEADER_SIZE..bytes.len().saturating_sub(SIGNATURE_SIZE);
+        for _ in 0..32 {
+            if payload.is_empty() {
+                break;
+            }
+            let mut tampered = bytes.clone();
+            tampered[rng.gen_range(payload.clone())] ^= rng.gen_range(1..=u8::MAX);
+            match from_bytes::<Envelope>(&tampered) {
+                Ok(changed) if changed.sender() == envelope.sender() && changed.message.round() == envelope.round => {
+                    return changed;
+                }
+                _ => {}
+            }
+        }
+    }
+    let sequence = envelope.sequence.wrapping_add(rng.gen_range(1..=u64::from(u32::MAX)));
+    Envelope { sequence, ..envelope }
+}
+
+/// Handle of a simulated network, messages are sent unchanged and the faults injected when they are received.
+pub struct SimulatedTransport {
+    network: Arc<SimulatedNetwork>,
+    inner: InMemoryTransport,
+    rng: Mutex<ChaCha8Rng>,
+    held: Mutex<Vec<Held>>,
+}
+
+impl SimulatedTransport {
+    fn new(network: Arc<SimulatedNetwork>, inner: InMemoryTransport) -> Self {
+        // Each handle has its own generator, so its faults don't depend on how the handles are scheduled
+        let mut engine = sha256::Hash::engine();
+        engine.input(&network.faults.seed.to_le_bytes());
+        if let Some(owner) = inner.owner() {
+            engine.input(&owner.serialize());
+        }
+        let rng = ChaCha8Rng::from_seed(sha256::Hash::from_engine(engine).to_byte_array());
+        Self { network, inner, rng: Mutex::new(rng), held: Mutex::new(Vec::new()) }
+    }
+
+    /// Injects the faults into a received message, returns it if it is delivered right away.
+    fn inject(&self, sender: Identifier, mut envelope: Envelope) -> Option<(Identifier, Envelope)> {
+        let network = &self.network;
+        let faults = &network.faults;
+        if network.is_cut(sender, self.inner.owner()) {
+            network.record(|stats| stats.partitioned += 1);
+            return None;
+        }
+
+        let mut rng = lock(&self.rng);
+        if rng.gen::<f64>() < faults.drop_rate {
+            network.record(|stats| stats.dropped += 1);
+            return None;
+        }
+        if rng.gen::<f64>() < faults.tamper_rate {
+            envelope = tamper(&mut rng, envelope);
+            network.record(|stats| stats.tampered += 1);
+        }
+
+        let now = Instant::now();
+        let mut held = lock(&self.held);
+        if rng.gen::<f64>() < faults.duplicate_rate {
+            held.push(Held { due: now + faults.delay, sender, envelope: envelope.clone() });
+            network.record(|stats| stats.duplicated += 1);
+        }
+        let mut due = now + faults.delay;
+        if rng.gen::<f64>() < faults.reorder_rate {
+            due += faults.reorder_window.mul_f64(rng.gen());
+            network.record(|stats| stats.reordered += 1);
+        }
+        if due > now {
+            if !faults.delay.is_zero() {
+                network.record(|stats| stats.delayed += 1);
+            }
+            held.push(Held { due, sender, envelope });
+            return None;
+        }
+        network.record(|stats| stats.delivered += 1);
+        Some((sender, envelope))
+    }
+
+    /// Takes the held message due first, if it is due.
+    fn take_due(&self) -> Option<(Identifier, Envelope)> {
+        let mut held = lock(&self.held);
+        let now = Instant::now();
+        let (index, _) = held.iter().enumerate().filter(|(_, h)| h.due <= now).min_by_key(|(_, h)| h.due)?;
+        let Held { sender, envelope, .. } = held.remove(index);
+        self.network.record(|stats| stats.delivered += 1);
+        Some((sender, envelope))
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        lock(&self.held).iter().map(|h| h.due).min()
+    }
+}
+
+#[async_trait]
+impl Transport for SimulatedTransport {
+    type Msg = Envelope;
+
+    async fn send(&self, receiver: Identifier, msg: Self::Msg) -> Result<(), TransportError> {
+        self.inner.send(receiver, msg).await
+    }
+
+    async fn broadcast(&self, msg: Self::Msg) -> Result<(), TransportError> {
+        self.inner.broadcast(msg).await
+    }
+
+    async fn receive(&self) -> Result<Option<(Identifier, Self::Msg)>, TransportError> {
+        loop {
+            if let Some(message) = self.take_due() {
+                return Ok(Some(message));
+            }
+            let Some((sender, envelope)) = self.inner.receive().await? else {
+                return Ok(None);
+            };
+            if let Some(message) = self.inject(sender, envelope) {
+                return Ok(Some(message));
+            }
+        }
+    }
+
+    async fn next_message(&self) -> Result<(Identifier, Self::Msg), TransportError> {
+        loop {
+            if let Some(message) = self.take_due() {
+                return Ok(message);
+            }
+            // Faults are injected as soon as a message is taken from the mailbox, so dropping the future loses nothing
+            let (sender, envelope) = match self.next_due() {
+                Some(due) => tokio::select! {
+                    received = self.inner.next_message() => received?,
+                    _ = tokio::time::sleep_until(due.into()) => continue,
+                },
+                None => self.inner.next_message().await?,
+            };
+            if let Some(message) = self.inject(sender, envelope) {
+                return Ok(message);
+            }
+        }
+    }
+}
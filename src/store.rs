@@ -0,0 +1,272 @@
+use crate::{errors::StoreError, signer::SessionId};
+use bitcoin::{Transaction, TxOut};
+use frost_secp256k1_tr::{round1::SigningCommitments, round2::SignatureShare, Identifier, SigningPackage};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+use tracing::warn;
+
+/// Journal entry recorded for a signing session, either a state transition or a received message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JournalEntry {
+    /// Nonces were generated and the signer is collecting commitments.
+    CollectingCommitments {
+        transaction: Transaction,
+        /// Spent outputs, missing from journals of earlier versions.
+        #[serde(default)]
+        prev_tx_outs: Vec<TxOut>,
+    },
+
+    /// Nonce commitment received from a participant.
+    CommitmentReceived { from: Identifier, commitments: SigningCommitments },
+
+    /// Signing package received, the signer is collecting shares.
+    CollectingShares { signing_package: SigningPackage },
+
+    /// Own signature share was generated, the session nonces are spent.
+    ShareSigned,
+
+    /// Signature share received from a participant.
+    ShareReceived { from: Identifier, share: SignatureShare },
+
+    /// Transaction was finalized.
+    Complete { signed_transaction: Transaction },
+
+    /// Session failed or was aborted.
+    Failed { error: String },
+}
+
+/// Pluggable storage for session journals.
+pub trait StateStore: Send + Sync {
+    /// Durably append an entry to the session journal.
+    fn append(&self, session_id: SessionId, entry: &JournalEntry) -> Result<(), StoreError>;
+
+    /// Load all entries of a session journal, empty if the session is unknown.
+    fn load(&self, session_id: SessionId) -> Result<Vec<JournalEntry>, StoreError>;
+
+    /// List all sessions with a journal.
+    fn sessions(&self) -> Result<Vec<SessionId>, StoreError>;
+
+    /// Durably record an amount paid out by a signed transaction, counted towards the policy's daily limit.
+    fn record_spend(&self, spend: SpendRecord) -> Result<(), StoreError>;
+
+    /// Load the recorded spends, oldest first.
+    fn spends(&self) -> Result<Vec<SpendRecord>, StoreError>;
+}
+
+/// Amount in satoshis paid out by a transaction the signer released a share for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendRecord {
+    pub at: SystemTime,
+    pub amount: u64,
+}
+
+/// Session data rebuilt by replaying its journal.
+#[derive(Debug, Clone, Default)]
+pub struct SessionRecord {
+    pub transaction: Option<Transaction>,
+    pub prev_tx_outs: Vec<TxOut>,
+    pub commitments: BTreeMap<Identifier, SigningCommitments>,
+    pub signing_package: Option<SigningPackage>,
+    pub share_signed: bool,
+    pub shares: BTreeMap<Identifier, SignatureShare>,
+    pub signed_transaction: Option<Transaction>,
+    pub error: Option<String>,
+}
+
+impl SessionRecord {
+    /// Replays journal entries in order.
+    pub fn replay(entries: impl IntoIterator<Item = JournalEntry>) -> Self {
+        let mut record = SessionRecord::default();
+        for entry in entries {
+            match entry {
+                JournalEntry::CollectingCommitments { transaction, prev_tx_outs } => {
+                    record.transaction = Some(transaction);
+                    record.prev_tx_outs = prev_tx_outs;
+                }
+                JournalEntry::CommitmentReceived { from, commitments } => {
+                    record.commitments.insert(from, commitments);
+                }
+                JournalEntry::CollectingShares { signing_package } => record.signing_package = Some(signing_package),
+                JournalEntry::ShareSigned => record.share_signed = true,
+                JournalEntry::ShareReceived { from, share } => {
+                    record.shares.insert(from, share);
+                }
+                JournalEntry::Complete { signed_transaction } => record.signed_transaction = Some(signed_transaction),
+                JournalEntry::Failed { error } => record.error = Some(error),
+            }
+        }
+        record
+    }
+
+    /// Whether the session reached a final state.
+    pub fn is_finished(&self) -> bool {
+        self.signed_transaction.is_some() || self.error.is_some()
+    }
+}
+
+/// File based journal, one JSON lines file per session.
+pub struct FileJournal {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileJournal {
+    /// Opens a journal in the given directory, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| StoreError::Io(e.to_string()))?;
+        Ok(Self { dir, lock: Mutex::new(()) })
+    }
+
+    fn session_path(&self, session_id: SessionId) -> PathBuf {
+        self.dir.join(format!("{session_id}.jsonl"))
+    }
+
+    /// Spends are kept apart from the session journals, the file name isn't a session id.
+    fn spends_path(&self) -> PathBuf {
+        self.dir.join("spends.jsonl")
+    }
+
+    fn append_line(&self, path: &Path, value: &impl Serialize) -> Result<(), StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let mut line = serde_json::to_vec(value).map_err(|e| StoreError::Corrupt(e.to_string()))?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| StoreError::Io(e.to_string()))?;
+        drop_partial_line(&mut file).map_err(|e| StoreError::Io(e.to_string()))?;
+        file.write_all(&line).map_err(|e| StoreError::Io(e.to_string()))?;
+        file.sync_data().map_err(|e| StoreError::Io(e.to_string()))
+    }
+}
+
+/// Truncates the file to its last complete line. A crash while appending can leave a partial line, an entry appended
+/// after it would be unreadable.
+fn drop_partial_line(file: &mut File) -> io::Result<()> {
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(());
+    }
+    let mut last = [0; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        return Ok(());
+    }
+    let mut contents = Vec::with_capacity(len as usize);
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut contents)?;
+    let complete = contents.iter().rposition(|byte| *byte == b'\n').map_or(0, |index| index + 1);
+    warn!("Dropping the partial last line of a journal.");
+    file.set_len(complete as u64)
+}
+
+impl StateStore for FileJournal {
+    fn append(&self, session_id: SessionId, entry: &JournalEntry) -> Result<(), StoreError> {
+        self.append_line(&self.session_path(session_id), entry)
+    }
+
+    fn load(&self, session_id: SessionId) -> Result<Vec<JournalEntry>, StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let contents = match fs::read_to_string(self.session_path(session_id)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(StoreError::Io(e.to_string())),
+        };
+
+        let lines: Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+        let mut entries = Vec::with_capacity(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                // A crash while appending can leave the last line truncated
+                Err(e) if index + 1 == lines.len() && !contents.ends_with('\n') => {
+                    warn!(session_id = %session_id, "Ignoring truncated journal entry: {e}");
+                }
+                Err(e) => return Err(StoreError::Corrupt(format!("session {session_id}, entry {index}: {e}"))),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn sessions(&self) -> Result<Vec<SessionId>, StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(|e| StoreError::Io(e.to_string()))? {
+            let path = entry.map_err(|e| StoreError::Io(e.to_string()))?.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                if let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()?.parse().ok()) {
+                    sessions.push(session_id);
+                }
+            }
+        }
+        sessions.sort();
+        Ok(sessions)
+    }
+
+    fn record_spend(&self, spend: SpendRecord) -> Result<(), StoreError> {
+        self.append_line(&self.spends_path(), &spend)
+    }
+
+    fn spends(&self) -> Result<Vec<SpendRecord>, StoreError> {
+        let _guard = self.lock.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let contents = match fs::read_to_string(self.spends_path()) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(StoreError::Io(e.to_string())),
+        };
+        // Unlike a truncated journal entry, a lost spend would lift the limit, so any unreadable line is an error
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| StoreError::Corrupt(format!("spends: {e}"))))
+            .collect()
+    }
+}
+
+/// In memory journal, state is lost on restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    journals: Mutex<HashMap<SessionId, Vec<JournalEntry>>>,
+    spends: Mutex<Vec<SpendRecord>>,
+}
+
+impl StateStore for MemoryStore {
+    fn append(&self, session_id: SessionId, entry: &JournalEntry) -> Result<(), StoreError> {
+        let mut journals = self.journals.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        journals.entry(session_id).or_default().push(entry.clone());
+        Ok(())
+    }
+
+    fn load(&self, session_id: SessionId) -> Result<Vec<JournalEntry>, StoreError> {
+        let journals = self.journals.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        Ok(journals.get(&session_id).cloned().unwrap_or_default())
+    }
+
+    fn sessions(&self) -> Result<Vec<SessionId>, StoreError> {
+        let journals = self.journals.lock().map_err(|e| StoreError::Io(e.to_string()))?;
+        let mut sessions: Vec<_> = journals.keys().cloned().collect();
+        sessions.sort();
+        Ok(sessions)
+    }
+
+    fn record_spend(&self, spend: SpendRecord) -> Result<(), StoreError> {
+        self.spends.lock().map_err(|e| StoreError::Io(e.to_string()))?.push(spend);
+        Ok(())
+    }
+
+    fn spends(&self) -> Result<Vec<SpendRecord>, StoreError> {
+        Ok(self.spends.lock().map_err(|e| StoreError::Io(e.to_string()))?.clone())
+    }
+}
@@ -1,12 +1,14 @@
 #![allow(dead_code)]
 
-use crate::{errors::TransportError, signer::SigningMessage};
+use crate::{envelope::Envelope, errors::TransportError, signer::SessionId};
 use async_trait::async_trait;
 use frost_secp256k1_tr::Identifier;
 use std::{
-    collections::VecDeque,
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Arc, Mutex, Weak},
 };
+use tokio::sync::Notify;
+use tracing::{debug, warn};
 
 /// Transport trait for sending and receiving messages.
 #[async_trait]
@@ -16,52 +18,211 @@ pub trait Transport: Send + Sync {
     /// Send a message to a participant.
     async fn send(&self, receiver: Identifier, msg: Self::Msg) -> Result<(), TransportError>;
 
-    /// Broadcast a message to all participants.
+    /// Broadcast a message to all participants but the sender.
     async fn broadcast(&self, msg: Self::Msg) -> Result<(), TransportError>;
 
-    /// Receive a message if any.
+    /// Receive a message and its sender if any.
     async fn receive(&self) -> Result<Option<(Identifier, Self::Msg)>, TransportError>;
+
+    /// Wait until a message is available and receive it. Dropping the future must not lose a message, signer loops
+    /// select over it.
+    async fn next_message(&self) -> Result<(Identifier, Self::Msg), TransportError>;
 }
 
-/// Transport message shared queue.
-pub type TransportMsgQueue = VecDeque<(Identifier, SigningMessage)>;
+/// Transport message queue of a mailbox, messages are kept with their sender.
+pub type TransportMsgQueue = VecDeque<(Identifier, Envelope)>;
+
+/// Inbox of a single participant.
+#[derive(Default)]
+struct Mailbox {
+    /// Queue of messages
+    queue: Mutex<TransportMsgQueue>,
+
+    /// Wakes up receivers waiting for new messages.
+    notify: Notify,
+}
 
-/// In memory transport implementation
+impl Mailbox {
+    fn push(&self, sender: Identifier, msg: Envelope) -> Result<(), String> {
+        self.queue.lock().map_err(|e| e.to_string())?.push_back((sender, msg));
+        self.notify.notify_waiters();
+        Ok(())
+    }
+}
+
+/// Mailboxes of all participants, plus the coordinator's which gets a copy of every broadcast.
+struct Mailboxes {
+    participants: BTreeMap<Identifier, Mailbox>,
+    coordinator: Mailbox,
+}
+
+/// In memory transport implementation, every participant receives from its own mailbox through its own handle.
 #[derive(Clone)]
 pub struct InMemoryTransport {
-    /// Queue of messages
-    queue: Arc<Mutex<TransportMsgQueue>>,
+    mailboxes: Arc<Mailboxes>,
 
-    /// List of participant IDs.
-    participants: Vec<Identifier>,
+    /// Participant the handle belongs to, the coordinator's handle has none.
+    owner: Option<Identifier>,
 }
 
 impl InMemoryTransport {
+    /// Creates the mailboxes of the participants and returns the coordinator's handle.
     pub fn new(participants: Vec<Identifier>) -> Self {
-        InMemoryTransport { queue: Arc::new(Mutex::new(VecDeque::new())), participants }
+        let participants = participants.into_iter().map(|id| (id, Mailbox::default())).collect();
+        InMemoryTransport {
+            mailboxes: Arc::new(Mailboxes { participants, coordinator: Mailbox::default() }),
+            owner: None,
+        }
+    }
+
+    /// Handle of a participant, it sends as the participant and receives from the participant's mailbox only.
+    pub fn participant(&self, id: Identifier) -> Result<Self, TransportError> {
+        if !self.mailboxes.participants.contains_key(&id) {
+            return Err(TransportError::UnknownParticipant(id));
+        }
+        Ok(InMemoryTransport { mailboxes: self.mailboxes.clone(), owner: Some(id) })
+    }
+
+    /// Participant the handle belongs to, none for the coordinator's handle.
+    pub fn owner(&self) -> Option<Identifier> {
+        self.owner
+    }
+
+    fn mailbox(&self) -> &Mailbox {
+        match self.owner {
+            Some(id) => &self.mailboxes.participants[&id],
+            None => &self.mailboxes.coordinator,
+        }
     }
 }
 
 #[async_trait]
 impl Transport for InMemoryTransport {
-    type Msg = SigningMessage;
+    type Msg = Envelope;
 
     async fn send(&self, receiver: Identifier, msg: Self::Msg) -> Result<(), TransportError> {
-        let mut q = self.queue.lock().map_err(|e| TransportError::Send(e.to_string()))?;
-        q.push_back((receiver, msg));
-        Ok(())
+        let sender = self.owner.ok_or_else(|| TransportError::Send("The coordinator can't send messages.".into()))?;
+        let mailbox = self.mailboxes.participants.get(&receiver).ok_or(TransportError::UnknownParticipant(receiver))?;
+        mailbox.push(sender, msg).map_err(TransportError::Send)
     }
 
     async fn broadcast(&self, msg: Self::Msg) -> Result<(), TransportError> {
-        let mut q = self.queue.lock().map_err(|e| TransportError::Broadcast(e.to_string()))?;
-        for id in &self.participants {
-            q.push_back((*id, msg.clone()));
+        let sender =
+            self.owner.ok_or_else(|| TransportError::Broadcast("The coordinator can't send messages.".into()))?;
+        for (id, mailbox) in &self.mailboxes.participants {
+            if *id != sender {
+                mailbox.push(sender, msg.clone()).map_err(TransportError::Broadcast)?;
+            }
+        }
+        self.mailboxes.coordinator.push(sender, msg).map_err(TransportError::Broadcast)
+    }
+
+    async fn receive(&self) -> Result<Option<(Identifier, Self::Msg)>, TransportError> {
+        let mut q = self.mailbox().queue.lock().map_err(|e| TransportError::Receive(e.to_string()))?;
+        Ok(q.pop_front())
+    }
+
+    async fn next_message(&self) -> Result<(Identifier, Self::Msg), TransportError> {
+        loop {
+            // Register for wakeups before checking the queue so a message pushed in between is not missed
+            let notified = self.mailbox().notify.notified();
+            if let Some(message) = self.receive().await? {
+                return Ok(message);
+            }
+            notified.await;
         }
-        Ok(())
+    }
+}
+
+/// Mailboxes of the sessions a coordinator runs at the same time.
+type SessionMailboxes = Mutex<HashMap<SessionId, Arc<Mailbox>>>;
+
+/// Splits the coordinator's transport by session, so sessions running at the same time each receive their own
+/// messages only.
+#[derive(Clone)]
+pub struct SessionRouter {
+    transport: Arc<dyn Transport<Msg = Envelope>>,
+    sessions: Arc<SessionMailboxes>,
+}
+
+impl SessionRouter {
+    /// Spawns the task handing the received messages to their session, it stops with the last router clone.
+    pub fn spawn(transport: Arc<dyn Transport<Msg = Envelope>>) -> Self {
+        let sessions = Arc::new(SessionMailboxes::default());
+        tokio::spawn(route(transport.clone(), Arc::downgrade(&sessions)));
+        Self { transport, sessions }
+    }
+
+    /// Transport of a session, messages of the session received before it was opened are dropped.
+    pub fn open(&self, session_id: SessionId) -> SessionTransport {
+        let mailbox = Arc::new(Mailbox::default());
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner()).insert(session_id, mailbox.clone());
+        SessionTransport { router: self.clone(), session_id, mailbox }
+    }
+}
+
+async fn route(transport: Arc<dyn Transport<Msg = Envelope>>, sessions: Weak<SessionMailboxes>) {
+    loop {
+        let (sender, envelope) = match transport.next_message().await {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Failed to receive message: {e}");
+                return;
+            }
+        };
+        let Some(sessions) = sessions.upgrade() else {
+            return;
+        };
+        let mailbox = envelope
+            .message
+            .session_id()
+            .and_then(|session_id| sessions.lock().unwrap_or_else(|e| e.into_inner()).get(&session_id).cloned());
+        match mailbox {
+            Some(mailbox) => {
+                let _ = mailbox.push(sender, envelope);
+            }
+            None => debug!(from = ?sender, "Dropping message of no running session."),
+        }
+    }
+}
+
+/// Coordinator's transport of a single session, the session is closed when it is dropped.
+pub struct SessionTransport {
+    router: SessionRouter,
+    session_id: SessionId,
+    mailbox: Arc<Mailbox>,
+}
+
+impl Drop for SessionTransport {
+    fn drop(&mut self) {
+        self.router.sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.session_id);
+    }
+}
+
+#[async_trait]
+impl Transport for SessionTransport {
+    type Msg = Envelope;
+
+    async fn send(&self, receiver: Identifier, msg: Self::Msg) -> Result<(), TransportError> {
+        self.router.transport.send(receiver, msg).await
+    }
+
+    async fn broadcast(&self, msg: Self::Msg) -> Result<(), TransportError> {
+        self.router.transport.broadcast(msg).await
     }
 
     async fn receive(&self) -> Result<Option<(Identifier, Self::Msg)>, TransportError> {
-        let mut q = self.queue.lock().map_err(|e| TransportError::Receive(e.to_string()))?;
+        let mut q = self.mailbox.queue.lock().map_err(|e| TransportError::Receive(e.to_string()))?;
         Ok(q.pop_front())
     }
+
+    async fn next_message(&self) -> Result<(Identifier, Self::Msg), TransportError> {
+        loop {
+            let notified = self.mailbox.notify.notified();
+            if let Some(message) = self.receive().await? {
+                return Ok(message);
+            }
+            notified.await;
+        }
+    }
 }
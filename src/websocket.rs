@@ -0,0 +1,399 @@
+use crate::{
+    codec::{from_wire, to_wire, Compact},
+    envelope::{identity_keypair, identity_keys, tagged_hash, Envelope},
+    errors::{SigningError, StoreError, TransportError},
+    offline::{OfflineRequest, OfflineSigner},
+    relay::{websocket_config, Peer, RelayMessage, Topic},
+    signer::SessionId,
+    transport::Transport,
+};
+use async_trait::async_trait;
+use bitcoin::secp256k1::{ecdh::SharedSecret, Keypair, PublicKey, Secp256k1, SecretKey};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use frost_secp256k1_tr::{
+    keys::{KeyPackage, PublicKeyPackage},
+    Identifier, SigningPackage,
+};
+use futures::{SinkExt, StreamExt};
+use rand::{rngs::OsRng, RngCore};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{connect_async_with_config, tungstenite::Message};
+use tracing::{info, warn};
+
+/// Domain separation tag of the pairwise payload keys.
+const PAYLOAD_KEY_TAG: &[u8] = b"frost-demo/relay-payload";
+
+/// Size of the ChaCha20-Poly1305 nonce prefixed to every payload.
+const NONCE_SIZE: usize = 12;
+
+/// Messages kept in each direction between the transport and the relay connection.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Interval a remote signer checks for operator decisions on the packages it holds back.
+const APPROVAL_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Message of the coordinator to a participant that runs its own signer, encrypted like the envelopes.
+#[derive(Debug, Clone)]
+pub enum CoordinatorMessage {
+    /// Round 1: the request to commit to.
+    Request(OfflineRequest),
+
+    /// Round 2: the signing package of the chosen participants, with the request it signs.
+    Package(OfflineRequest, SigningPackage),
+}
+
+/// Frame opened by a participant, from another participant or from the coordinator.
+enum Received {
+    Envelope(Identifier, Envelope),
+    Coordinator(CoordinatorMessage),
+}
+
+/// Keys of a relay peer: its own key and the public keys of the peers it talks to.
+pub struct RelayIdentity {
+    pub peer: Peer,
+    keypair: Keypair,
+    peers: BTreeMap<Peer, PublicKey>,
+    /// Group the participant proves its identity key in, the coordinator has none.
+    public_key_package: Option<PublicKeyPackage>,
+}
+
+impl RelayIdentity {
+    /// Identity of a participant, keyed by the identity key of its share. Broadcasts are encrypted to the coordinator's
+    /// key as well.
+    pub fn participant(
+        key_package: &KeyPackage,
+        public_key_package: &PublicKeyPackage,
+        coordinator: PublicKey,
+    ) -> Result<Self, SigningError> {
+        let peer = Peer::Participant(*key_package.identifier());
+        let mut peers = participant_keys(public_key_package)?;
+        peers.remove(&peer);
+        peers.insert(Peer::Coordinator, coordinator);
+        Ok(Self {
+            peer,
+            keypair: identity_keypair(key_package)?,
+            peers,
+            public_key_package: Some(public_key_package.clone()),
+        })
+    }
+
+    /// Identity of the coordinator, it receives the broadcasts of the participants.
+    pub fn coordinator(secret_key: SecretKey, public_key_package: &PublicKeyPackage) -> Result<Self, SigningError> {
+        Ok(Self {
+            peer: Peer::Coordinator,
+            keypair: Keypair::from_secret_key(&Secp256k1::new(), &secret_key),
+            peers: participant_keys(public_key_package)?,
+            public_key_package: None,
+        })
+    }
+}
+
+fn participant_keys(public_key_package: &PublicKeyPackage) -> Result<BTreeMap<Peer, PublicKey>, SigningError> {
+    Ok(identity_keys(public_key_package)?
+        .into_iter()
+        .map(|(id, public_key)| (Peer::Participant(id), public_key))
+        .collect())
+}
+
+/// End-to-end encryption between a peer and the others in a topic, with a key per pair of peers.
+struct Channel {
+    topic: Topic,
+    peer: Peer,
+    ciphers: BTreeMap<Peer, ChaCha20Poly1305>,
+}
+
+impl Channel {
+    fn new(topic: Topic, identity: &RelayIdentity) -> Result<Self, TransportError> {
+        let topic_bytes = serde_json::to_vec(&topic).map_err(|e| TransportError::Connect(e.to_string()))?;
+        let ciphers = identity
+            .peers
+            .iter()
+            .map(|(peer, public_key)| {
+                let shared = SharedSecret::new(public_key, &identity.keypair.secret_key());
+                let key = tagged_hash(PAYLOAD_KEY_TAG, &[shared.secret_bytes().as_slice(), &topic_bytes].concat());
+                (*peer, ChaCha20Poly1305::new(Key::from_slice(&key)))
+            })
+            .collect();
+        Ok(Self { topic, peer: identity.peer, ciphers })
+    }
+
+    /// Binds a payload to the topic and the direction it travels in.
+    fn associated_data(&self, from: Peer, to: Peer) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(&(&self.topic, from, to))
+    }
+
+    /// Encrypts an envelope, or a message of the coordinator, to a peer as a frame for the relay.
+    fn seal<T: Compact>(&self, to: Peer, message: &T) -> Result<Message, TransportError> {
+        let cipher = self.ciphers.get(&to).ok_or_else(|| TransportError::Send(format!("No key for {to:?}")))?;
+        let plaintext = to_wire(message).map_err(|e| TransportError::Send(e.to_string()))?;
+        let aad = self.associated_data(self.peer, to).map_err(|e| TransportError::Send(e.to_string()))?;
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: &aad })
+            .map_err(|e| TransportError::Send(e.to_string()))?;
+        let frame = RelayMessage::Frame { from: self.peer, to, payload: [nonce.as_slice(), &ciphertext].concat() };
+        Ok(Message::Text(serde_json::to_string(&frame).map_err(|e| TransportError::Send(e.to_string()))?))
+    }
+
+    /// Decrypts a frame from the relay, returns the envelope and its sender or the message of the coordinator.
+    fn open(&self, text: &str) -> Result<Received, TransportError> {
+        let Ok(RelayMessage::Frame { from, to, payload }) = serde_json::from_str(text) else {
+            return Err(TransportError::Receive("Not a relay frame".into()));
+        };
+        if to != self.peer || payload.len() < NONCE_SIZE {
+            return Err(TransportError::Receive(format!("Malformed frame from {from:?}")));
+        }
+        let cipher = self.ciphers.get(&from).ok_or_else(|| TransportError::Receive(format!("No key for {from:?}")))?;
+        let aad = self.associated_data(from, to).map_err(|e| TransportError::Receive(e.to_string()))?;
+        let (nonce, ciphertext) = payload.split_at(NONCE_SIZE);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| TransportError::Receive(format!("Payload from {from:?} failed to decrypt")))?;
+        let Peer::Participant(sender) = from else {
+            let message = from_wire(&plaintext).map_err(|e| TransportError::Receive(e.to_string()))?;
+            return Ok(Received::Coordinator(message));
+        };
+        let envelope: Envelope = from_wire(&plaintext).map_err(|e| TransportError::Receive(e.to_string()))?;
+        if envelope.sender() != sender {
+            return Err(TransportError::Receive(format!("Envelope of {:?} relayed by {sender:?}", envelope.sender())));
+        }
+        Ok(Received::Envelope(sender, envelope))
+    }
+}
+
+/// Transport over a WebSocket relay, for peers that can only connect out. Payloads are encrypted end-to-end, the
+/// relay only learns who talks to whom.
+pub struct WebSocketTransport {
+    channel: Arc<Channel>,
+    outgoing: mpsc::Sender<Message>,
+    incoming: Mutex<mpsc::Receiver<(Identifier, Envelope)>>,
+    coordinator_messages: Mutex<mpsc::Receiver<CoordinatorMessage>>,
+    reader: JoinHandle<()>,
+}
+
+impl WebSocketTransport {
+    /// Connects to the relay at `url` (e.g. ws://127.0.0.1:9000) and subscribes to the topic.
+    pub async fn connect(url: &str, topic: Topic, identity: RelayIdentity) -> Result<Self, TransportError> {
+        let channel = Arc::new(Channel::new(topic, &identity)?);
+        let (websocket, _) = connect_async_with_config(url, Some(websocket_config()), false)
+            .await
+            .map_err(|e| TransportError::Connect(e.to_string()))?;
+        let (mut sink, mut stream) = websocket.split();
+
+        // The relay only subscribes peers that sign its challenge
+        let nonce = match stream.next().await {
+            Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                Ok(RelayMessage::Challenge { nonce }) => nonce,
+                _ => return Err(TransportError::Connect("Expected a challenge from the relay".into())),
+            },
+            _ => return Err(TransportError::Connect("Relay closed the connection before the challenge".into())),
+        };
+        let hello =
+            RelayMessage::hello(&nonce, topic, identity.peer, &identity.keypair, identity.public_key_package.as_ref())?;
+        let hello = serde_json::to_string(&hello).map_err(|e| TransportError::Connect(e.to_string()))?;
+        sink.send(Message::Text(hello)).await.map_err(|e| TransportError::Connect(e.to_string()))?;
+
+        let (outgoing, mut frames) = mpsc::channel::<Message>(QUEUE_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(frame) = frames.recv().await {
+                if let Err(e) = sink.send(frame).await {
+                    warn!("Failed to send to the relay: {e}");
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        });
+
+        let (deliver, incoming) = mpsc::channel(QUEUE_CAPACITY);
+        let (deliver_coordinator, coordinator_messages) = mpsc::channel(QUEUE_CAPACITY);
+        let reader = tokio::spawn({
+            let channel = channel.clone();
+            async move {
+                while let Some(message) = stream.next().await {
+                    let text = match message {
+                        Ok(Message::Text(text)) => text,
+                        Ok(Message::Close(_)) => break,
+                        Ok(_) => continue,
+                        Err(e) => {
+                            warn!("Relay connection failed: {e}");
+                            break;
+                        }
+                    };
+                    let open = match channel.open(&text) {
+                        Ok(Received::Envelope(sender, envelope)) => enqueue(&deliver, (sender, envelope)),
+                        Ok(Received::Coordinator(message)) => enqueue(&deliver_coordinator, message),
+                        Err(e) => {
+                            warn!("Dropping relay frame: {e}");
+                            true
+                        }
+                    };
+                    if !open {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            channel,
+            outgoing,
+            incoming: Mutex::new(incoming),
+            coordinator_messages: Mutex::new(coordinator_messages),
+            reader,
+        })
+    }
+
+    /// Sends a message of the coordinator to a participant, only the coordinator can.
+    pub fn send_to_participant(
+        &self,
+        receiver: Identifier,
+        message: &CoordinatorMessage,
+    ) -> Result<(), TransportError> {
+        if self.channel.peer != Peer::Coordinator {
+            return Err(TransportError::Send("Only the coordinator sends requests.".into()));
+        }
+        let to = Peer::Participant(receiver);
+        if !self.channel.ciphers.contains_key(&to) {
+            return Err(TransportError::UnknownParticipant(receiver));
+        }
+        self.push(self.channel.seal(to, message)?).map_err(TransportError::Send)
+    }
+
+    /// Waits for the next message of the coordinator.
+    pub async fn next_coordinator_message(&self) -> Result<CoordinatorMessage, TransportError> {
+        self.coordinator_messages
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or_else(|| TransportError::Receive("Relay connection closed".into()))
+    }
+
+    fn push(&self, frame: Message) -> Result<(), String> {
+        self.outgoing.try_send(frame).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => "Send queue to the relay is full".to_string(),
+            mpsc::error::TrySendError::Closed(_) => "Relay connection closed".to_string(),
+        })
+    }
+}
+
+/// Hands a received message to its queue, returns whether the queue is still open.
+fn enqueue<T>(queue: &mpsc::Sender<T>, message: T) -> bool {
+    match queue.try_send(message) {
+        Ok(()) => true,
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            warn!("Receive queue is full, dropping frame.");
+            true
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => false,
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    type Msg = Envelope;
+
+    async fn send(&self, receiver: Identifier, msg: Self::Msg) -> Result<(), TransportError> {
+        if self.channel.peer == Peer::Coordinator {
+            return Err(TransportError::Send("The coordinator can't send messages.".into()));
+        }
+        let to = Peer::Participant(receiver);
+        if !self.channel.ciphers.contains_key(&to) {
+            return Err(TransportError::UnknownParticipant(receiver));
+        }
+        self.push(self.channel.seal(to, &msg)?).map_err(TransportError::Send)
+    }
+
+    async fn broadcast(&self, msg: Self::Msg) -> Result<(), TransportError> {
+        if self.channel.peer == Peer::Coordinator {
+            return Err(TransportError::Broadcast("The coordinator can't send messages.".into()));
+        }
+        // Every peer gets its own copy, encrypted with its pairwise key
+        for to in self.channel.ciphers.keys() {
+            self.push(self.channel.seal(*to, &msg)?).map_err(TransportError::Broadcast)?;
+        }
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Option<(Identifier, Self::Msg)>, TransportError> {
+        Ok(self.incoming.lock().await.try_recv().ok())
+    }
+
+    async fn next_message(&self) -> Result<(Identifier, Self::Msg), TransportError> {
+        self.incoming.lock().await.recv().await.ok_or_else(|| TransportError::Receive("Relay connection closed".into()))
+    }
+}
+
+/// Runs the signer of a participant for the coordinator it reaches over the relay: it commits to the coordinator's
+/// requests and signs its signing packages with the checks of the air-gapped signer, and broadcasts the commitments
+/// and shares. Packages waiting for operator approval are retried until decided. The nonces of a session are kept
+/// encrypted in `nonce_dir` between the rounds. Returns once the relay connection closes.
+pub async fn run_remote_signer(
+    mut signer: OfflineSigner,
+    transport: &WebSocketTransport,
+    nonce_dir: &Path,
+) -> Result<(), SigningError> {
+    fs::create_dir_all(nonce_dir).map_err(|e| StoreError::Io(format!("{}: {e}", nonce_dir.display())))?;
+    let nonce_path = |session_id: SessionId| -> PathBuf { nonce_dir.join(format!("{session_id}.nonces")) };
+    let mut awaiting_approval: Vec<(OfflineRequest, SigningPackage)> = Vec::new();
+    let mut retry = tokio::time::interval(APPROVAL_RETRY_INTERVAL);
+    loop {
+        tokio::select! {
+            message = transport.next_coordinator_message() => match message? {
+                CoordinatorMessage::Request(request) => {
+                    let session_id = request.session_id;
+                    match signer.round1(&request, &nonce_path(session_id)) {
+                        Ok(envelope) => {
+                            transport.broadcast(envelope).await?;
+                            info!(%session_id, "Committed to the signing request.");
+                        }
+                        Err(e) => warn!(%session_id, "Refusing the signing request: {e}"),
+                    }
+                }
+                CoordinatorMessage::Package(request, signing_package) => {
+                    if signing_package.signing_commitments().contains_key(&signer.participant_id()) {
+                        awaiting_approval.push((request, signing_package));
+                    } else {
+                        // Not chosen to sign, the nonces are never used
+                        let _ = fs::remove_file(nonce_path(request.session_id));
+                    }
+                }
+            },
+            _ = retry.tick() => {}
+        }
+
+        let mut still_awaiting = Vec::new();
+        for (request, signing_package) in awaiting_approval {
+            let session_id = request.session_id;
+            match signer.round2(&request, &signing_package, &nonce_path(session_id)) {
+                Ok(Some(envelope)) => {
+                    transport.broadcast(envelope).await?;
+                    info!(%session_id, "Signed the signing package.");
+                }
+                Ok(None) => still_awaiting.push((request, signing_package)),
+                Err(e) => warn!(%session_id, "Refusing to sign the signing package: {e}"),
+            }
+        }
+        awaiting_approval = still_awaiting;
+    }
+}
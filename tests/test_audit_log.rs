@@ -0,0 +1,139 @@
+use bitcoin::secp256k1::SecretKey;
+use frost_demo::{
+    audit::{load_entries, load_or_create_key, verify_entries, verify_log, AuditEvent, AuditLog},
+    errors::{AuditError, SigningError},
+    signer::{run_signing_ceremony_with_config, run_signing_ceremony_with_signers, CeremonyConfig, SessionId},
+};
+use std::{fs, sync::Arc, time::Duration};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+#[tokio::test]
+async fn test_ceremony_is_recorded_to_audit_log() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let path = dir.path().join("audit.log");
+    let audit = Arc::new(AuditLog::open(&path, load_or_create_key(dir.path().join("audit.key")).unwrap()).unwrap());
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { audit: Some(audit.clone()), ..Default::default() };
+
+    let signed_tx = run_signing_ceremony_with_config(harness.key_data.clone(), tx.clone(), &prevouts, &config)
+        .await
+        .expect("Signing should succeed");
+
+    let entries = load_entries(&path).unwrap();
+    assert_eq!(verify_log(&path, &[audit.public_key()]), Ok(entries.len()));
+    let session_id = match &entries[0].event {
+        AuditEvent::SessionStarted { session_id, txid, .. } => {
+            assert_eq!(*txid, tx.compute_txid());
+            *session_id
+        }
+        other => panic!("Expected SessionStarted entry, got {other:?}"),
+    };
+    let participants = entries.iter().find_map(|entry| match &entry.event {
+        AuditEvent::Participants { participants, .. } => Some(participants.len()),
+        _ => None,
+    });
+    assert_eq!(participants, Some(2));
+    let messages = entries.iter().filter(|entry| matches!(entry.event, AuditEvent::MessageReceived { .. })).count();
+    assert!(messages >= 4, "Expected commitments and shares to be recorded, got {messages} messages");
+    assert_eq!(
+        entries.last().unwrap().event,
+        AuditEvent::SessionCompleted { session_id, txid: signed_tx.compute_txid() }
+    );
+}
+
+#[tokio::test]
+async fn test_failed_ceremony_is_recorded() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let path = dir.path().join("audit.log");
+    let audit = Arc::new(AuditLog::open(&path, SecretKey::from_slice(&[1; 32]).unwrap()).unwrap());
+    let (mut signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+
+    // A single online participant can't reach the threshold
+    let online = *signers.keys().next().unwrap();
+    signers.retain(|id, _| *id == online);
+    let config =
+        CeremonyConfig { round1_timeout: Duration::from_millis(100), audit: Some(audit.clone()), ..Default::default() };
+    let result = run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await;
+    assert!(result.is_err());
+
+    let entries = load_entries(&path).unwrap();
+    assert!(verify_entries(&entries, &[]).is_ok());
+    assert!(matches!(entries.last().unwrap().event, AuditEvent::SessionFailed { .. }));
+}
+
+#[tokio::test]
+async fn test_audit_log_detects_tampering() {
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let path = dir.path().join("audit.log");
+    let key = SecretKey::from_slice(&[1; 32]).unwrap();
+    {
+        let audit = AuditLog::open(&path, key).unwrap();
+        for i in 0..3 {
+            let session_id = SessionId::from([i; 32]);
+            audit.record(AuditEvent::SessionFailed { session_id, error: format!("timeout {i}") }).unwrap();
+        }
+    }
+
+    // Reopening continues the chain
+    let audit = AuditLog::open(&path, key).unwrap();
+    let session_id = SessionId::from([3; 32]);
+    audit.record(AuditEvent::SessionFailed { session_id, error: "timeout 3".to_string() }).unwrap();
+    assert_eq!(verify_log(&path, &[audit.public_key()]), Ok(4));
+
+    let other_key = AuditLog::open(dir.path().join("other.log"), SecretKey::from_slice(&[2; 32]).unwrap()).unwrap();
+    assert_eq!(verify_log(&path, &[other_key.public_key()]), Err(AuditError::UntrustedSigner(0)));
+
+    // Altered event
+    let contents = fs::read_to_string(&path).unwrap();
+    let tampered = contents.replacen("timeout 1", "timeout 7", 1);
+    fs::write(&path, &tampered).unwrap();
+    assert_eq!(verify_log(&path, &[]), Err(AuditError::InvalidHash(1)));
+
+    // Removed entry
+    let lines: Vec<&str> = contents.lines().collect();
+    fs::write(&path, [lines[0], lines[2], lines[3]].join("\n")).unwrap();
+    assert_eq!(verify_log(&path, &[]), Err(AuditError::BrokenChain(1)));
+}
+
+#[tokio::test]
+async fn test_audit_failure_keeps_session_error() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let path = dir.path().join("audit.log");
+    let audit = Arc::new(AuditLog::open(&path, SecretKey::from_slice(&[1; 32]).unwrap()).unwrap());
+    let (mut signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let online = *signers.keys().next().unwrap();
+    signers.retain(|id, _| *id == online);
+    let config =
+        CeremonyConfig { round1_timeout: Duration::from_millis(200), audit: Some(audit), ..Default::default() };
+
+    // The log becomes unwritable while the ceremony waits for the missing participant
+    let ceremony = tokio::spawn(async move {
+        run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(&path).unwrap();
+
+    let result = ceremony.await.unwrap();
+    assert!(matches!(result, Err(SigningError::Timeout { .. })), "Expected the timeout, got {result:?}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_audit_key_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let path = dir.path().join("audit.key");
+    let key = load_or_create_key(&path).unwrap();
+
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    assert_eq!(load_or_create_key(&path).unwrap(), key);
+}
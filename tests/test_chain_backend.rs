@@ -0,0 +1,66 @@
+use bitcoin::{Amount, FeeRate, Network, OutPoint};
+use frost_demo::chain::{estimate_spend_fee, ChainBackend, MockBackend, FALLBACK_FEE, MAX_FEE_RATE, SPEND_VSIZE};
+
+mod utils;
+use crate::utils::test::{TestHarness, FUNDING_HEIGHT, FUNDING_VALUE};
+
+#[tokio::test]
+async fn test_spend_through_mock_backend() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let backend = MockBackend::default();
+    backend.set_fee_rate(FeeRate::from_sat_per_vb(2).unwrap());
+    let outpoint = harness.fund_group(&backend);
+
+    let txid = harness.spend(outpoint, &backend, true).await.unwrap();
+
+    let [signed] = backend.broadcast_transactions().try_into().unwrap();
+    assert_eq!(signed.compute_txid(), txid);
+    assert_eq!(signed.input[0].previous_output, outpoint);
+    assert_eq!(signed.input[0].witness.len(), 1);
+    let paid: Amount = signed.output.iter().map(|output| output.value).sum();
+    assert_eq!(FUNDING_VALUE - paid, Amount::from_sat(2 * SPEND_VSIZE));
+
+    // The change is the only group UTXO left, unconfirmed
+    let group_script = harness.key_data.address(Network::Signet).unwrap().script_pubkey();
+    let utxos = backend.list_unspent(&group_script).await.unwrap();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!((utxos[0].outpoint.txid, utxos[0].height), (txid, None));
+    assert_eq!(backend.tip_height().await.unwrap(), FUNDING_HEIGHT);
+}
+
+#[tokio::test]
+async fn test_spend_without_fee_estimate_or_prevout() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let backend = MockBackend::default();
+    let outpoint = harness.fund_group(&backend);
+
+    let unknown = OutPoint { vout: 1, ..outpoint };
+    assert!(harness.spend(unknown, &backend, true).await.is_err());
+    assert!(backend.broadcast_transactions().is_empty());
+
+    harness.spend(outpoint, &backend, true).await.unwrap();
+    let [signed] = backend.broadcast_transactions().try_into().unwrap();
+    let paid: Amount = signed.output.iter().map(|output| output.value).sum();
+    assert_eq!(FUNDING_VALUE - paid, FALLBACK_FEE);
+
+    // The mock refuses double spends
+    assert!(backend.broadcast(&signed).await.is_err());
+}
+
+#[tokio::test]
+async fn test_fee_estimate_is_capped_and_opt_in() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let backend = MockBackend::default();
+    backend.set_fee_rate(FeeRate::from_sat_per_vb(1_000).unwrap());
+
+    assert_eq!(estimate_spend_fee(&backend, None).await, MAX_FEE_RATE.fee_vb(SPEND_VSIZE).unwrap());
+    let policy_max = FeeRate::from_sat_per_vb(5);
+    assert_eq!(estimate_spend_fee(&backend, policy_max).await, Amount::from_sat(5 * SPEND_VSIZE));
+
+    // Without opting in the default fee is paid
+    let outpoint = harness.fund_group(&backend);
+    harness.spend(outpoint, &backend, false).await.unwrap();
+    let [signed] = backend.broadcast_transactions().try_into().unwrap();
+    let paid: Amount = signed.output.iter().map(|output| output.value).sum();
+    assert_eq!(FUNDING_VALUE - paid, FALLBACK_FEE);
+}
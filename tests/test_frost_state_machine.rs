@@ -1,8 +1,17 @@
 use frost_demo::{
     errors::SigningError,
-    signer::{run_signing_ceremony, SessionId, SigningMessage, SigningState},
+    signer::{
+        run_signing_ceremony, run_signing_ceremony_with_config, run_signing_ceremony_with_signers, run_signing_session,
+        CeremonyConfig, SessionId, SessionView, SignerSelection, SigningMessage, SigningPhase, SigningRequest,
+        SigningRound, Transition,
+    },
     transport::Transport,
 };
+use frost_secp256k1_tr::{Identifier, SigningPackage};
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant},
+};
 
 mod utils;
 use crate::utils::test::TestHarness;
@@ -12,20 +21,20 @@ async fn test_state_transition_idle_to_collecting_commitments() {
     let harness = TestHarness::new(2, 3, None).await;
     let (signers, transport) = harness.create_signers();
     let signer = signers.values().next().unwrap();
-    let session_id: SessionId = 123;
-    let (transaction, _) = harness.create_dummy_transaction(1);
+    let session_id = SessionId::from([1; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
 
-    let initial_state = signer.get_state().unwrap();
-    assert!(matches!(initial_state, SigningState::Idle));
+    let initial_state = signer.get_state(session_id).unwrap();
+    assert!(matches!(initial_state, SessionView::Idle));
 
-    let result = signer.initiate_signing_round(session_id, transaction).await;
+    let result = signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await;
 
     assert!(result.is_ok());
 
     // Check state transition
-    let new_state = signer.get_state().unwrap();
+    let new_state = signer.get_state(session_id).unwrap();
     match new_state {
-        SigningState::CollectingCommitments { session_id: state_session_id, .. } => {
+        SessionView::CollectingCommitments { session_id: state_session_id, .. } => {
             assert_eq!(state_session_id, session_id);
         }
         _ => panic!("Expected CollectingCommitments state"),
@@ -34,8 +43,8 @@ async fn test_state_transition_idle_to_collecting_commitments() {
     // Check that a message was broadcast
     let sent_message = transport.receive().await.unwrap();
     assert!(sent_message.is_some());
-    if let Some((_, msg)) = sent_message {
-        match msg {
+    if let Some((_, envelope)) = sent_message {
+        match envelope.message {
             SigningMessage::NonceCommitment(msg_session_id, sender_id, _) => {
                 assert_eq!(msg_session_id, session_id);
                 assert_eq!(sender_id, signer.participant_id);
@@ -50,21 +59,21 @@ async fn test_initiate_signing_in_invalid_state() {
     let harness = TestHarness::new(2, 3, None).await;
     let (signers, _) = harness.create_signers();
     let signer = signers.values().next().unwrap();
-    let session_id: SessionId = 123;
-    let (transaction, _) = harness.create_dummy_transaction(1);
+    let session_id = SessionId::from([1; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
 
     // Initiate first round to move state away from Idle
-    signer.initiate_signing_round(session_id, transaction.clone()).await.unwrap();
-    let state_after_first_call = signer.get_state().unwrap();
-    assert!(matches!(state_after_first_call, SigningState::CollectingCommitments { .. }));
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction.clone(), &prevouts)).await.unwrap();
+    let state_after_first_call = signer.get_state(session_id).unwrap();
+    assert!(matches!(state_after_first_call, SessionView::CollectingCommitments { .. }));
 
-    // Try to initiate again
-    let result = signer.initiate_signing_round(session_id + 1, transaction).await;
+    // Try to initiate the same session again
+    let result = signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await;
 
     assert!(result.is_err());
     match result.err().unwrap() {
         SigningError::InvalidState(msg) => {
-            assert_eq!(msg, "Signer is not in Idle state.");
+            assert_eq!(msg, format!("Session {session_id} is not in Idle state."));
         }
         e => panic!("Expected InvalidState error, but got {:?}", e),
     }
@@ -75,11 +84,11 @@ async fn test_process_message_in_collecting_commitments() {
     let harness = TestHarness::new(2, 3, None).await;
     let (signers, _) = harness.create_signers();
     let signer = signers.values().next().unwrap();
-    let session_id: SessionId = 123;
-    let (transaction, _) = harness.create_dummy_transaction(1);
+    let session_id = SessionId::from([1; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
 
     // Move to CollectingCommitments state
-    signer.initiate_signing_round(session_id, transaction).await.unwrap();
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
 
     // Create a dummy commitment message from another participant
     let (other_signer, _) = harness.create_signers();
@@ -88,18 +97,20 @@ async fn test_process_message_in_collecting_commitments() {
         &harness.key_data.key_packages[other_participant_id].signing_share(),
         &mut rand::rngs::OsRng,
     );
-    let message = SigningMessage::NonceCommitment(session_id, *other_participant_id, Box::new(commitments.clone()));
+    let message =
+        harness.seal(SigningMessage::NonceCommitment(session_id, *other_participant_id, Box::new(commitments.clone())));
 
     let result = signer.process_message(message).await;
 
     assert!(result.is_ok());
 
     // Check that the commitment was added to the state
-    let state = signer.get_state().unwrap();
+    let state = signer.get_state(session_id).unwrap();
     match state {
-        SigningState::CollectingCommitments { commitments: state_commitments, .. } => {
-            assert_eq!(state_commitments.len(), 1);
+        SessionView::CollectingCommitments { commitments: state_commitments, .. } => {
+            assert_eq!(state_commitments.len(), 2);
             assert!(state_commitments.contains_key(other_participant_id));
+            assert!(state_commitments.contains_key(&signer.participant_id));
         }
         _ => panic!("Expected CollectingCommitments state"),
     }
@@ -110,11 +121,11 @@ async fn test_process_message_with_wrong_session_id() {
     let harness = TestHarness::new(2, 3, None).await;
     let (signers, _) = harness.create_signers();
     let signer = signers.values().next().unwrap();
-    let correct_session_id: SessionId = 123;
-    let wrong_session_id: SessionId = 456;
-    let (transaction, _) = harness.create_dummy_transaction(1);
+    let correct_session_id = SessionId::from([1; 32]);
+    let wrong_session_id = SessionId::from([2; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
 
-    signer.initiate_signing_round(correct_session_id, transaction).await.unwrap();
+    signer.initiate_signing_round(correct_session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
 
     // Create a message with the wrong session ID
     let (other_signer, _) = harness.create_signers();
@@ -123,15 +134,16 @@ async fn test_process_message_with_wrong_session_id() {
         &harness.key_data.key_packages[other_participant_id].signing_share(),
         &mut rand::rngs::OsRng,
     );
-    let message = SigningMessage::NonceCommitment(wrong_session_id, *other_participant_id, Box::new(commitments));
+    let message =
+        harness.seal(SigningMessage::NonceCommitment(wrong_session_id, *other_participant_id, Box::new(commitments)));
 
     signer.process_message(message).await.unwrap();
 
-    // The message should be ignored, so the commitments list should be empty
-    let state = signer.get_state().unwrap();
+    // The message should be ignored, so only the signer's own commitment is kept
+    let state = signer.get_state(correct_session_id).unwrap();
     match state {
-        SigningState::CollectingCommitments { commitments, .. } => {
-            assert!(commitments.is_empty());
+        SessionView::CollectingCommitments { commitments, .. } => {
+            assert_eq!(commitments.keys().collect::<Vec<_>>(), vec![&signer.participant_id]);
         }
         _ => panic!("Expected CollectingCommitments state"),
     }
@@ -144,21 +156,28 @@ async fn test_parallel_initiation_is_safe() {
     // Clone  signer to simulate concurrent access
     let signer = signers.values().next().unwrap().clone();
     let signer_clone = signer.clone();
-    let (transaction, _) = harness.create_dummy_transaction(1);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
     let transaction_clone = transaction.clone();
+    let prevouts_clone = prevouts.clone();
 
-    // Spawn two tasks trying to initiate a round concurrently
-    let task1 = tokio::spawn(async move { signer.initiate_signing_round(1, transaction).await });
+    // Spawn two tasks trying to initiate the same session concurrently
+    let task1 = tokio::spawn(async move {
+        signer.initiate_signing_round(SessionId::from([1; 32]), SigningRequest::new(transaction, &prevouts)).await
+    });
     // Add a small delay to increase the chance of collision
     tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-    let task2 = tokio::spawn(async move { signer_clone.initiate_signing_round(2, transaction_clone).await });
+    let task2 = tokio::spawn(async move {
+        signer_clone
+            .initiate_signing_round(SessionId::from([1; 32]), SigningRequest::new(transaction_clone, &prevouts_clone))
+            .await
+    });
 
     let results = vec![task1.await.unwrap(), task2.await.unwrap()];
 
     let successes = results.iter().filter(|r| r.is_ok()).count();
     let failures = results.iter().filter(|r| r.is_err()).count();
 
-    // Exactly one must succeed, and one must fail due to the state mutex
+    // Exactly one must succeed, and one must fail due to the sessions mutex
     assert_eq!(successes, 1, "Exactly one initiation should succeed");
     assert_eq!(failures, 1, "Exactly one initiation should fail");
 
@@ -205,3 +224,249 @@ async fn test_parallel_signing_ceremonies_are_isolated() {
     let expected_script_pubkey2 = "5120eaeab93eab93d0066df96fbe1553f9dcbe4a84ee63ccc33ed10201e3244de1f8";
     assert_eq!(script_pubkey2, expected_script_pubkey2, "The tx2 script_pubkey did not match the expected value");
 }
+
+#[tokio::test]
+async fn test_signing_ceremony_with_explicit_signer_subset() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let mut transitions: Vec<_> = signers.values().map(|signer| signer.subscribe()).collect();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { selection: SignerSelection::from_indices(&[1, 3]).unwrap(), ..Default::default() };
+
+    let result = run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await;
+
+    let signed_tx = result.expect("Signing with participants 1 and 3 should succeed");
+    assert_eq!(signed_tx.input[0].witness.len(), 1);
+
+    // Exactly participants 1 and 3 signed, participant 2 never took part
+    let mut signed = BTreeSet::new();
+    let mut participants = BTreeSet::new();
+    for receiver in &mut transitions {
+        while let Ok(transition) = receiver.try_recv() {
+            participants.insert(transition.participant_id);
+            if transition.to == SigningPhase::CollectingShares {
+                signed.insert(transition.participant_id);
+            }
+        }
+    }
+    let expected: BTreeSet<Identifier> = [1u16, 3].map(|index| Identifier::try_from(index).unwrap()).into();
+    assert_eq!(signed, expected);
+    assert_eq!(participants, expected);
+}
+
+#[tokio::test]
+async fn test_responders_without_a_local_signer_are_not_chosen() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let session_id = SessionId::from([6; 32]);
+
+    // Participant 3 has no local signer, its commitment arrives first but it could never be asked for a share
+    let remote = Identifier::try_from(3u16).unwrap();
+    signers.remove(&remote);
+    let (_, commitments) = frost_secp256k1_tr::round1::commit(
+        harness.key_data.key_packages[&remote].signing_share(),
+        &mut rand::rngs::OsRng,
+    );
+    let commitment = harness.seal(SigningMessage::NonceCommitment(session_id, remote, Box::new(commitments)));
+    transport.participant(remote).unwrap().broadcast(commitment).await.unwrap();
+
+    let config = CeremonyConfig { round1_timeout: Duration::from_secs(5), ..Default::default() };
+    let result = run_signing_session(&harness.key_data, signers, transport, session_id, tx, &prevouts, &config).await;
+
+    let signed_tx = result.expect("Signing with the local participants should succeed");
+    assert_eq!(signed_tx.input[0].witness.len(), 1);
+}
+
+#[tokio::test]
+async fn test_signing_ceremony_rejects_invalid_signer_subset() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+
+    // Fewer participants than the threshold
+    let below_threshold =
+        CeremonyConfig { selection: SignerSelection::from_indices(&[2]).unwrap(), ..Default::default() };
+    let result =
+        run_signing_ceremony_with_config(harness.key_data.clone(), tx.clone(), &prevouts, &below_threshold).await;
+    assert_eq!(result.err(), Some(SigningError::NotEnoughSigners));
+
+    // Participant outside of the group
+    let unknown = CeremonyConfig { selection: SignerSelection::from_indices(&[1, 4]).unwrap(), ..Default::default() };
+    let result = run_signing_ceremony_with_config(harness.key_data.clone(), tx, &prevouts, &unknown).await;
+    assert!(matches!(result, Err(SigningError::UnknownSigner(_))));
+}
+
+#[tokio::test]
+async fn test_round_timeout_names_missing_participants() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+
+    // Only one participant is online, the others never commit
+    let online = *signers.keys().next().unwrap();
+    signers.retain(|id, _| *id == online);
+    let config = CeremonyConfig { round1_timeout: Duration::from_millis(100), ..Default::default() };
+
+    let result = run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await;
+
+    match result {
+        Err(SigningError::Timeout { round, missing }) => {
+            assert_eq!(round, SigningRound::Commitments);
+            assert_eq!(missing.len(), 2);
+            assert!(!missing.contains(&online));
+        }
+        other => panic!("Expected Timeout error, but got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_round_completes_once_threshold_is_met() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+
+    // One participant is offline, the remaining two are enough to sign
+    let offline = *signers.keys().next().unwrap();
+    signers.remove(&offline);
+    let config = CeremonyConfig { round1_timeout: Duration::from_secs(30), ..Default::default() };
+
+    let started = Instant::now();
+    let result = run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await;
+
+    assert!(result.is_ok(), "signing failed: {:?}", result.err());
+    assert!(started.elapsed() < Duration::from_secs(5), "Ceremony should not wait out the round deadline");
+}
+
+#[tokio::test]
+async fn test_transport_wakes_up_waiting_receiver() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let signer = signers.values().next().unwrap().clone();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+
+    let receiver = transport.clone();
+    let waiting = tokio::spawn(async move { receiver.next_message().await });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let session_id = SessionId::from([1; 32]);
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+
+    let (_, envelope) = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap().unwrap();
+    assert!(matches!(envelope.message, SigningMessage::NonceCommitment(id, _, _) if id == session_id));
+}
+
+#[tokio::test]
+async fn test_signer_refuses_package_for_other_transaction() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, _) = harness.create_signers();
+    let signer = signers.values().next().unwrap();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let (other_transaction, _) = harness.create_dummy_transaction(2);
+    let session_id = SessionId::from([1; 32]);
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+
+    // The coordinator asks to sign the sighash of a different transaction
+    let commitments = match signer.get_state(session_id).unwrap() {
+        SessionView::CollectingCommitments { commitments, .. } => commitments,
+        other => panic!("Expected CollectingCommitments state, got {other:?}"),
+    };
+    let other_sighash = SigningRequest::new(other_transaction, &prevouts).sighash().unwrap();
+    let signing_package = SigningPackage::new(commitments, &other_sighash);
+    signer.advance_to_sharing_round(session_id, signing_package).unwrap();
+
+    let result = signer.sign_and_broadcast_share(session_id).await;
+
+    assert!(matches!(result, Err(SigningError::SighashMismatch { .. })), "Expected sighash mismatch, got {result:?}");
+    assert!(matches!(
+        signer.get_state(session_id).unwrap(),
+        SessionView::Failed { error: SigningError::SighashMismatch { .. } }
+    ));
+}
+
+#[tokio::test]
+async fn test_transitions_are_published() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, _) = harness.create_signers();
+    let signer = signers.values().next().unwrap();
+    let mut transitions = signer.subscribe();
+    let session_id = SessionId::from([1; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+    signer.abort(session_id, &SigningError::NotEnoughSigners).await;
+    signer.reset(session_id).unwrap();
+
+    let participant_id = signer.participant_id;
+    for (from, to) in [
+        (SigningPhase::Idle, SigningPhase::CollectingCommitments),
+        (SigningPhase::CollectingCommitments, SigningPhase::Failed),
+        (SigningPhase::Failed, SigningPhase::Idle),
+    ] {
+        assert_eq!(transitions.recv().await.unwrap(), Transition { session_id, participant_id, from, to });
+    }
+}
+
+#[tokio::test]
+async fn test_illegal_transitions_are_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, _) = harness.create_signers();
+    let signer = signers.values().next().unwrap();
+    let session_id = SessionId::from([1; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+
+    let result = signer.complete_signing(session_id, transaction.clone());
+    assert_eq!(result, Err(SigningError::InvalidTransition { from: SigningPhase::Idle, to: SigningPhase::Complete }));
+
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction.clone(), &prevouts)).await.unwrap();
+    let result = signer.complete_signing(session_id, transaction);
+    assert_eq!(
+        result,
+        Err(SigningError::InvalidTransition { from: SigningPhase::CollectingCommitments, to: SigningPhase::Complete })
+    );
+    assert!(matches!(signer.get_state(session_id).unwrap(), SessionView::CollectingCommitments { .. }));
+    assert!(SigningPhase::Idle.can_transition_to(SigningPhase::Failed));
+    assert!(!SigningPhase::Failed.can_transition_to(SigningPhase::Complete));
+}
+
+#[tokio::test]
+async fn test_signer_loop_processes_commands_and_messages() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let mut ids = signers.keys();
+    let (signer, peer) = (&signers[ids.next().unwrap()], *ids.next().unwrap());
+    let handle = signer.spawn();
+    let session_id = SessionId::from([1; 32]);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+
+    handle.start(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+    let (_, commitments) = frost_secp256k1_tr::round1::commit(
+        harness.key_data.key_packages[&peer].signing_share(),
+        &mut rand::rngs::OsRng,
+    );
+    let envelope = harness.seal(SigningMessage::NonceCommitment(session_id, peer, Box::new(commitments)));
+    transport.participant(peer).unwrap().send(signer.participant_id, envelope).await.unwrap();
+
+    // The signer receives the message from its mailbox on its own
+    let commitments = tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            match signer.get_state(session_id).unwrap() {
+                SessionView::CollectingCommitments { commitments, .. } if commitments.contains_key(&peer) => {
+                    break commitments;
+                }
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    })
+    .await
+    .expect("The signer should process the commitment");
+
+    let (other_transaction, _) = harness.create_dummy_transaction(2);
+    let other_sighash = SigningRequest::new(other_transaction, &prevouts).sighash().unwrap();
+    let result = handle.sign(session_id, SigningPackage::new(commitments, &other_sighash)).await;
+
+    assert!(matches!(result, Err(SigningError::SighashMismatch { .. })), "Expected sighash mismatch, got {result:?}");
+    let mut aborted = false;
+    while let Some((_, envelope)) = transport.receive().await.unwrap() {
+        aborted |= matches!(envelope.message, SigningMessage::Abort(id, ..) if id == session_id);
+    }
+    assert!(aborted, "Expected the signer to abort the session");
+}
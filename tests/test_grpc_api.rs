@@ -0,0 +1,175 @@
+use bitcoin::{consensus, psbt::Psbt, Network, OutPoint, Transaction};
+use frost_demo::{
+    chain::MockBackend,
+    grpc::{
+        self,
+        proto::{
+            session_event::Event, signer_client::SignerClient, CreateSpendRequest, GetAddressRequest,
+            GetGroupInfoRequest, SessionRef, SessionState, SessionStatus, SignPsbtRequest,
+        },
+        BearerAuth, SignerService,
+    },
+    service::{ApiToken, SigningService},
+    signer::CeremonyConfig,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::{net::TcpListener, time::timeout};
+use tonic::{codegen::InterceptedService, transport::Channel, Code, Request, Status};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+const TOKEN: &str = "grpc-api-test-token";
+
+type Client = SignerClient<InterceptedService<Channel, fn(Request<()>) -> Result<Request<()>, Status>>>;
+
+fn authorize(mut request: Request<()>) -> Result<Request<()>, Status> {
+    request.metadata_mut().insert("authorization", format!("Bearer {TOKEN}").parse().unwrap());
+    Ok(request)
+}
+
+/// Serves the signer API on a free localhost port, returns its URL.
+async fn serve(service: SigningService) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(grpc::serve(
+        listener,
+        SignerService::new(service),
+        BearerAuth::new(Some(ApiToken::new(TOKEN).unwrap())),
+    ));
+    url
+}
+
+async fn connect(url: String) -> Client {
+    let channel = Channel::from_shared(url).unwrap().connect().await.unwrap();
+    SignerClient::with_interceptor(channel, authorize as fn(Request<()>) -> Result<Request<()>, Status>)
+}
+
+/// Starts the signer API on a mock chain funding the group, returns a client and the funded outpoint.
+async fn start_server(harness: &TestHarness) -> (Client, OutPoint) {
+    let backend = Arc::new(MockBackend::default());
+    let outpoint = harness.fund_group(&backend);
+    let service = SigningService::new(harness.key_data.clone(), Network::Signet, CeremonyConfig::default())
+        .unwrap()
+        .with_backend(backend);
+    (connect(serve(service).await).await, outpoint)
+}
+
+/// Follows the events of a session until it finishes, returns the final status.
+async fn wait_for_outcome(client: &mut Client, session: SessionRef) -> SessionStatus {
+    let mut events = client.stream_session_events(session).await.unwrap().into_inner();
+    loop {
+        let event = timeout(Duration::from_secs(10), events.message()).await.unwrap().unwrap().unwrap();
+        if let Some(Event::Finished(status)) = event.event {
+            return status;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_group_info_and_address() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut client, _) = start_server(&harness).await;
+
+    let info = client.get_group_info(GetGroupInfoRequest {}).await.unwrap().into_inner();
+    assert_eq!((info.threshold, info.total, info.participants.len()), (2, 3, 3));
+
+    let address = client.get_address(GetAddressRequest { network: String::new() }).await.unwrap().into_inner();
+    assert_eq!(address.address, harness.key_data.address(Network::Signet).unwrap().to_string());
+    let address = client.get_address(GetAddressRequest { network: "regtest".into() }).await.unwrap().into_inner();
+    assert_eq!(address.address, harness.key_data.address(Network::Regtest).unwrap().to_string());
+}
+
+#[tokio::test]
+async fn test_create_spend_signs_in_the_background() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut client, outpoint) = start_server(&harness).await;
+    let (tx, _) = harness.create_dummy_transaction(1);
+    let to = bitcoin::Address::from_script(&tx.output[0].script_pubkey, Network::Signet).unwrap();
+
+    // The spent output is looked up on the chain
+    let request =
+        CreateSpendRequest { utxo: outpoint.to_string(), to: to.to_string(), amount: 10_000, broadcast: false };
+    let session = client.create_spend(request).await.unwrap().into_inner();
+    let status = wait_for_outcome(&mut client, session.clone()).await;
+
+    assert_eq!(status.state(), SessionState::Complete, "{status:?}");
+    let signed: Transaction = consensus::deserialize(&status.signed_transaction).unwrap();
+    assert_eq!(signed.compute_txid().to_string(), status.txid);
+    assert_eq!(signed.input[0].witness.len(), 1);
+    assert_eq!(client.get_session_status(session).await.unwrap().into_inner(), status);
+}
+
+#[tokio::test]
+async fn test_sign_psbt() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut client, _) = start_server(&harness).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(2);
+    let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[0].witness_utxo = Some(prevouts[0].clone());
+
+    let session = client.sign_psbt(SignPsbtRequest { psbt: psbt.serialize() }).await.unwrap().into_inner();
+    let status = wait_for_outcome(&mut client, session).await;
+
+    assert_eq!(status.state(), SessionState::Complete, "{status:?}");
+    let signed = Psbt::deserialize(&status.psbt).unwrap();
+    assert!(signed.inputs[0].tap_key_sig.is_some());
+    assert!(signed.extract_tx().is_ok());
+}
+
+#[tokio::test]
+async fn test_errors_map_to_status_codes() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut client, outpoint) = start_server(&harness).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(3);
+
+    let request = CreateSpendRequest {
+        utxo: outpoint.to_string(),
+        to: "not an address".into(),
+        amount: 10_000,
+        broadcast: false,
+    };
+    assert_eq!(client.create_spend(request.clone()).await.unwrap_err().code(), Code::InvalidArgument);
+
+    let to = bitcoin::Address::from_script(&tx.output[0].script_pubkey, Network::Signet).unwrap().to_string();
+    let too_much = CreateSpendRequest { to: to.clone(), amount: 1_000_000, ..request.clone() };
+    assert_eq!(client.create_spend(too_much).await.unwrap_err().code(), Code::InvalidArgument);
+
+    // An output the chain doesn't know can't be spent
+    let unknown =
+        CreateSpendRequest { utxo: OutPoint { vout: 1, ..outpoint }.to_string(), to: to.clone(), ..request.clone() };
+    assert_eq!(client.create_spend(unknown).await.unwrap_err().code(), Code::InvalidArgument);
+
+    // Without a node there is no spent output to sign for
+    let service = SigningService::new(harness.key_data.clone(), Network::Signet, CeremonyConfig::default()).unwrap();
+    let mut offline = connect(serve(service).await).await;
+    let spend = CreateSpendRequest { to, ..request };
+    assert_eq!(offline.create_spend(spend).await.unwrap_err().code(), Code::FailedPrecondition);
+
+    let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[0].witness_utxo = Some(bitcoin::TxOut { script_pubkey: Default::default(), ..prevouts[0].clone() });
+    let result = client.sign_psbt(SignPsbtRequest { psbt: psbt.serialize() }).await;
+    assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+
+    let unknown = SessionRef { session_id: "00".repeat(32) };
+    assert_eq!(client.get_session_status(unknown).await.unwrap_err().code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_calls_need_the_token() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let service = SigningService::new(harness.key_data.clone(), Network::Signet, CeremonyConfig::default()).unwrap();
+    let url = serve(service).await;
+
+    let mut anonymous = SignerClient::connect(url.clone()).await.unwrap();
+    let result = anonymous.get_group_info(GetGroupInfoRequest {}).await;
+    assert_eq!(result.unwrap_err().code(), Code::Unauthenticated);
+
+    let channel = Channel::from_shared(url).unwrap().connect().await.unwrap();
+    let mut forged = SignerClient::with_interceptor(channel, |mut request: Request<()>| {
+        request.metadata_mut().insert("authorization", "Bearer guessed".parse().unwrap());
+        Ok(request)
+    });
+    let result = forged.get_group_info(GetGroupInfoRequest {}).await;
+    assert_eq!(result.unwrap_err().code(), Code::Unauthenticated);
+}
@@ -0,0 +1,146 @@
+use bitcoin::secp256k1::PublicKey;
+use frost_demo::{
+    envelope::{identity_keypair, identity_keys, ReplayGuard, Sealer, PROTOCOL_VERSION},
+    errors::{EnvelopeError, SigningError},
+    signer::{SessionId, SessionView, SigningMessage, SigningRequest},
+};
+use frost_secp256k1_tr::Identifier;
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+/// Nonce commitment of a participant for the session.
+fn commitment(harness: &TestHarness, session_id: SessionId, sender: Identifier) -> SigningMessage {
+    let (_, commitments) = frost_secp256k1_tr::round1::commit(
+        harness.key_data.key_packages[&sender].signing_share(),
+        &mut rand::rngs::OsRng,
+    );
+    SigningMessage::NonceCommitment(session_id, sender, Box::new(commitments))
+}
+
+#[tokio::test]
+async fn test_duplicate_message_is_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, _) = harness.create_signers();
+    let mut ids = signers.keys();
+    let (signer, other) = (&signers[ids.next().unwrap()], *ids.next().unwrap());
+    let session_id = SessionId::random();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+
+    let envelope = harness.seal(commitment(&harness, session_id, other));
+    assert_eq!(envelope.version, PROTOCOL_VERSION);
+    signer.process_message(envelope.clone()).await.unwrap();
+
+    let result = signer.process_message(envelope).await;
+    assert!(
+        matches!(result, Err(SigningError::Envelope(EnvelopeError::Duplicate { sender, .. })) if sender == other),
+        "Expected duplicate to be rejected, got {result:?}"
+    );
+    match signer.get_state(session_id).unwrap() {
+        SessionView::CollectingCommitments { commitments, .. } => assert_eq!(commitments.len(), 2),
+        other => panic!("Expected CollectingCommitments state, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_message_from_foreign_group_is_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let foreign = TestHarness::new(2, 3, None).await;
+    let guard = ReplayGuard::new(&harness.key_data.public).unwrap();
+    let sender = *foreign.key_data.key_packages.keys().next().unwrap();
+
+    let envelope = foreign.seal(commitment(&foreign, SessionId::random(), sender));
+
+    assert!(matches!(guard.open(&envelope, 1), Err(EnvelopeError::ForeignGroup(_))));
+}
+
+#[tokio::test]
+async fn test_forged_sender_is_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let guard = ReplayGuard::new(&harness.key_data.public).unwrap();
+    let mut ids = harness.key_data.key_packages.keys();
+    let (forger, victim) = (*ids.next().unwrap(), *ids.next().unwrap());
+
+    // A group member signs a message claiming to come from another participant
+    let sealer = Sealer::new(&harness.key_data.key_packages[&forger], &harness.key_data.public).unwrap();
+    let envelope = sealer.seal(commitment(&harness, SessionId::random(), victim)).unwrap();
+    assert_eq!(guard.open(&envelope, 1), Err(EnvelopeError::InvalidSignature(victim)));
+
+    // Changing a sealed field invalidates the signature
+    let mut envelope = harness.seal(commitment(&harness, SessionId::random(), forger));
+    envelope.sequence += 1;
+    assert_eq!(guard.open(&envelope, 1), Err(EnvelopeError::InvalidSignature(forger)));
+}
+
+#[tokio::test]
+async fn test_stale_round_is_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let guard = ReplayGuard::new(&harness.key_data.public).unwrap();
+    let sender = *harness.key_data.key_packages.keys().next().unwrap();
+
+    let envelope = harness.seal(commitment(&harness, SessionId::random(), sender));
+
+    assert_eq!(guard.open(&envelope, 2), Err(EnvelopeError::StaleRound { round: 1, current: 2 }));
+    assert_eq!(guard.open(&envelope, 1), Ok(()));
+}
+
+#[tokio::test]
+async fn test_envelopes_are_not_signed_with_the_share() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let identities = identity_keys(&harness.key_data.public).unwrap();
+
+    for (id, key_package) in &harness.key_data.key_packages {
+        let share_key = PublicKey::from_slice(&key_package.verifying_share().serialize().unwrap()).unwrap();
+        assert_ne!(identities[id], share_key);
+        assert_eq!(identity_keypair(key_package).unwrap().public_key(), identities[id]);
+    }
+}
+
+#[tokio::test]
+async fn test_restarted_sender_is_not_a_replay() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let guard = ReplayGuard::new(&harness.key_data.public).unwrap();
+    let sender = *harness.key_data.key_packages.keys().next().unwrap();
+    let session_id = SessionId::random();
+
+    // Each run of the sender starts its own sequence epoch
+    let first = Sealer::new(&harness.key_data.key_packages[&sender], &harness.key_data.public).unwrap();
+    let restarted = Sealer::new(&harness.key_data.key_packages[&sender], &harness.key_data.public).unwrap();
+    assert_ne!(first.next_sequence() >> 32, restarted.next_sequence() >> 32);
+
+    let envelope = first.seal(commitment(&harness, session_id, sender)).unwrap();
+    assert_eq!(guard.open(&envelope, 1), Ok(()));
+    let envelope = restarted.seal(commitment(&harness, session_id, sender)).unwrap();
+    assert_eq!(guard.open(&envelope, 1), Ok(()));
+}
+
+#[tokio::test]
+async fn test_replay_window_is_bounded() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let guard = ReplayGuard::new(&harness.key_data.public).unwrap();
+    let sender = *harness.key_data.key_packages.keys().next().unwrap();
+    let session_id = SessionId::random();
+    let seal = |sequence: u64| {
+        Sealer::new(&harness.key_data.key_packages[&sender], &harness.key_data.public)
+            .unwrap()
+            .with_sequence(sequence)
+            .seal(commitment(&harness, session_id, sender))
+            .unwrap()
+    };
+
+    // Envelopes may arrive out of order within the window
+    assert_eq!(guard.open(&seal(100), 1), Ok(()));
+    assert_eq!(guard.open(&seal(98), 1), Ok(()));
+    assert_eq!(guard.open(&seal(99), 1), Ok(()));
+    assert_eq!(guard.open(&seal(98), 1), Err(EnvelopeError::Duplicate { sender, sequence: 98 }));
+
+    // Once the sender moved on, envelopes older than the window are rejected as well
+    assert_eq!(guard.open(&seal(200), 1), Ok(()));
+    assert_eq!(guard.open(&seal(120), 1), Err(EnvelopeError::Duplicate { sender, sequence: 120 }));
+    assert_eq!(guard.open(&seal(150), 1), Ok(()));
+
+    // A restarted sender starts a new epoch, the envelopes of its earlier run stay replays
+    assert_eq!(guard.open(&seal((1 << 32) + 1), 1), Ok(()));
+    assert_eq!(guard.open(&seal(201), 1), Err(EnvelopeError::Duplicate { sender, sequence: 201 }));
+}
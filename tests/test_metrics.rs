@@ -0,0 +1,56 @@
+use frost_demo::{
+    metrics::{install_recorder, CEREMONIES_COMPLETED, CEREMONIES_FAILED, MESSAGES_RECEIVED, ROUND_TIMEOUTS},
+    signer::{run_signing_ceremony, run_signing_ceremony_with_signers, CeremonyConfig},
+};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+/// Fetches the metrics page from the exporter.
+async fn scrape(addr: SocketAddr) -> String {
+    let mut stream = TcpStream::connect(addr).await.expect("Failed to connect to the metrics exporter");
+    let request = format!("GET /metrics HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "Unexpected response: {response}");
+    response
+}
+
+// The recorder is global, so the whole scenario runs in a single test
+#[tokio::test]
+async fn test_ceremony_metrics_are_served() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    install_recorder(Some(listener)).expect("Failed to install metrics recorder");
+
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    run_signing_ceremony(harness.key_data.clone(), tx, &prevouts).await.expect("Signing should succeed");
+
+    // A single online participant can't reach the threshold
+    let (mut signers, transport) = harness.create_signers();
+    let online = *signers.keys().next().unwrap();
+    signers.retain(|id, _| *id == online);
+    let (tx, prevouts) = harness.create_dummy_transaction(2);
+    let config = CeremonyConfig { round1_timeout: Duration::from_millis(100), ..Default::default() };
+    let result = run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await;
+    assert!(result.is_err());
+
+    let metrics = scrape(addr).await;
+    assert!(metrics.contains(&format!("{CEREMONIES_COMPLETED} 1")), "{metrics}");
+    assert!(metrics.contains(&format!("{CEREMONIES_FAILED}{{reason=\"timeout\"}} 1")), "{metrics}");
+    assert!(metrics.contains(&format!("{ROUND_TIMEOUTS}{{round=\"commitments\"}} 1")), "{metrics}");
+    assert!(metrics.contains("frost_round_duration_seconds_bucket{round=\"shares\""), "{metrics}");
+
+    let online = hex::encode(online.serialize());
+    assert!(
+        metrics.lines().any(|line| line.starts_with(MESSAGES_RECEIVED) && line.contains(&online)),
+        "Expected message counts for participant {online}: {metrics}"
+    );
+}
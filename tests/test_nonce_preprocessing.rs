@@ -0,0 +1,124 @@
+use frost_demo::{
+    errors::SigningError,
+    preprocess::{CommitmentPool, NoncePool},
+    signer::{replenish_commitments, run_preprocessed_signing_ceremony, CeremonyConfig},
+};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+#[tokio::test]
+async fn test_preprocessed_ceremony_signs_in_one_round() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let mut pool = CommitmentPool::new(1);
+    replenish_commitments(&harness.key_data, &signers, &transport, &mut pool, 2).await.unwrap();
+    for id in signers.keys() {
+        assert_eq!(pool.available(*id), 2);
+    }
+
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig::default();
+    let signed_tx =
+        run_preprocessed_signing_ceremony(&harness.key_data, &signers, transport, &mut pool, tx, &prevouts, &config)
+            .await
+            .expect("Preprocessed signing should succeed");
+
+    assert_eq!(signed_tx.input[0].witness.len(), 1);
+    let used: usize = signers.keys().map(|id| 2 - pool.available(*id)).sum();
+    assert_eq!(used, harness.key_data.threshold as usize);
+}
+
+#[tokio::test]
+async fn test_preprocessed_pool_depletion_and_replenishment() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let config = CeremonyConfig::default();
+    let mut pool = CommitmentPool::new(1);
+    replenish_commitments(&harness.key_data, &signers, &transport, &mut pool, 1).await.unwrap();
+
+    // Each participant has a single commitment, enough for one ceremony
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    run_preprocessed_signing_ceremony(
+        &harness.key_data,
+        &signers,
+        transport.clone(),
+        &mut pool,
+        tx.clone(),
+        &prevouts,
+        &config,
+    )
+    .await
+    .unwrap();
+
+    // Only one participant has a commitment left
+    let result = run_preprocessed_signing_ceremony(
+        &harness.key_data,
+        &signers,
+        transport.clone(),
+        &mut pool,
+        tx,
+        &prevouts,
+        &config,
+    )
+    .await;
+    assert!(matches!(result, Err(SigningError::NoncePoolExhausted(_))));
+
+    // Replenishment only asks the depleted participants for new commitments
+    let depleted: Vec<_> = signers.keys().filter(|id| pool.needs_replenishment(**id)).cloned().collect();
+    assert_eq!(depleted.len(), 2);
+    replenish_commitments(&harness.key_data, &signers, &transport, &mut pool, 1).await.unwrap();
+    for id in signers.keys() {
+        assert_eq!(pool.available(*id), 1);
+    }
+    for id in &depleted {
+        assert_eq!(signers[id].preprocessed_nonces().unwrap(), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_preprocessed_nonces_are_single_use() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (id, key_package) = harness.key_data.key_packages.iter().next().unwrap();
+
+    let mut nonce_pool = NoncePool::new(2);
+    let batch = nonce_pool.generate(key_package.signing_share(), 5);
+    assert_eq!(batch.len(), 2, "Pool size bounds the number of generated nonces");
+
+    // Signer side: a nonce can be taken once only
+    let (commitment_id, _) = batch[0];
+    assert!(nonce_pool.take(commitment_id).is_some());
+    assert!(nonce_pool.take(commitment_id).is_none());
+
+    // Coordinator side: a used commitment is never handed out again, even if it is published twice
+    let mut commitment_pool = CommitmentPool::new(0);
+    commitment_pool.add(*id, batch.clone());
+    let (taken, _) = commitment_pool.take(*id).unwrap();
+    commitment_pool.add(*id, batch);
+    assert_eq!(commitment_pool.available(*id), 1);
+    let (next, _) = commitment_pool.take(*id).unwrap();
+    assert_ne!(taken, next);
+    assert_eq!(commitment_pool.take(*id).err(), Some(SigningError::NoncePoolExhausted(*id)));
+}
+
+#[tokio::test]
+async fn test_restarted_pool_does_not_reissue_identifiers() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (id, key_package) = harness.key_data.key_packages.iter().next().unwrap();
+
+    let mut nonce_pool = NoncePool::new(2);
+    let batch = nonce_pool.generate(key_package.signing_share(), 2);
+    let mut commitment_pool = CommitmentPool::new(0);
+    commitment_pool.add(*id, batch.clone());
+
+    // A restarted signer has a new pool, its identifiers don't collide with the lost ones
+    let mut restarted = NoncePool::new(2);
+    assert_ne!(restarted.epoch(), nonce_pool.epoch());
+    let new_batch = restarted.generate(key_package.signing_share(), 1);
+    assert!(batch.iter().all(|(old, _)| *old != new_batch[0].0));
+
+    // The coordinator drops the commitments whose nonces were lost
+    commitment_pool.add(*id, new_batch.clone());
+    assert_eq!(commitment_pool.available(*id), 1);
+    assert_eq!(commitment_pool.take(*id).unwrap().0, new_batch[0].0);
+}
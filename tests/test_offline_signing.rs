@@ -0,0 +1,225 @@
+use bitcoin::Network;
+use frost_demo::{
+    approval::{ApprovalDecision, ApprovalQueue, MemoryApprovalQueue},
+    envelope::Envelope,
+    errors::{SigningError, StoreError},
+    keys::load_participant_key,
+    offline::{aggregate_shares, collect_commitments, FileTransport, OfflineRequest, OfflineSigner},
+    policy::SignerPolicy,
+    signer::{SessionId, SigningMessage, SigningRequest},
+    store::{MemoryStore, StateStore},
+    transport::Transport,
+};
+use frost_secp256k1_tr::{Identifier, SigningPackage};
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc, time::Duration};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+fn offline_request(harness: &TestHarness, session: u8) -> OfflineRequest {
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    OfflineRequest { session_id: SessionId::from([session; 32]), request: SigningRequest::new(tx, &prevouts) }
+}
+
+fn offline_signer(harness: &TestHarness, id: &Identifier, store: Arc<dyn StateStore>) -> OfflineSigner {
+    let key_package = harness.key_data.key_packages[id].clone();
+    OfflineSigner::new(key_package, harness.key_data.public.clone(), store).unwrap()
+}
+
+/// Carries the envelopes of the signers to the coordinator through a shared directory, like a USB stick.
+async fn carry(usb: &Path, envelopes: Vec<Envelope>) -> Vec<Envelope> {
+    for envelope in envelopes {
+        let outbox = FileTransport::new(Some(envelope.sender()), usb, usb.join("unused"));
+        outbox.broadcast(envelope).await.unwrap();
+    }
+    let coordinator = FileTransport::new(None, usb.join("unused"), usb);
+    let mut received = Vec::new();
+    while let Some((_, envelope)) = coordinator.receive().await.unwrap() {
+        received.push(envelope);
+    }
+    received
+}
+
+/// Signing package of a single signer's commitment.
+fn single_signer_package(request: &OfflineRequest, id: Identifier, envelope: Envelope) -> SigningPackage {
+    let SigningMessage::NonceCommitment(_, _, commitment) = envelope.message else {
+        panic!("Expected a commitment");
+    };
+    SigningPackage::new(BTreeMap::from([(id, *commitment)]), &request.request.sighash().unwrap())
+}
+
+#[tokio::test]
+async fn test_air_gapped_signing_over_files() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let request = offline_request(&harness, 5);
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().take(2).cloned().collect();
+    let nonce_path = |id: &Identifier| dir.path().join(format!("nonces-{}.enc", hex::encode(id.serialize())));
+    let mut signers: Vec<(Identifier, OfflineSigner)> =
+        ids.iter().map(|id| (*id, offline_signer(&harness, id, Arc::new(MemoryStore::default())))).collect();
+
+    let mut commitments = Vec::new();
+    for (id, signer) in &signers {
+        commitments.push(signer.round1(&request, &nonce_path(id)).unwrap());
+    }
+    let commitments = carry(&dir.path().join("round1"), commitments).await;
+    let signing_package = collect_commitments(&harness.key_data, &request, commitments).unwrap();
+    assert_eq!(signing_package.signing_commitments().len(), 2);
+
+    let mut shares = Vec::new();
+    for (id, signer) in &mut signers {
+        let share = signer.round2(&request, &signing_package, &nonce_path(id)).unwrap();
+        assert!(!nonce_path(id).exists(), "The nonces must be removed once used");
+        shares.push(share.expect("Signed without approval queue"));
+    }
+    let shares = carry(&dir.path().join("round2"), shares).await;
+
+    // A missing share is reported, all of them sign the transaction
+    let result = aggregate_shares(&harness.key_data, &request, &signing_package, shares[..1].to_vec());
+    assert!(matches!(result, Err(SigningError::Timeout { .. })), "{result:?}");
+    let transaction = aggregate_shares(&harness.key_data, &request, &signing_package, shares).unwrap();
+    assert_eq!(transaction.input[0].witness.len(), 1);
+}
+
+#[tokio::test]
+async fn test_nonces_are_never_reused() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let request = offline_request(&harness, 5);
+    let id = *harness.key_data.key_packages.keys().next().unwrap();
+    let mut signer = offline_signer(&harness, &id, Arc::new(MemoryStore::default()));
+    let nonce_path = dir.path().join("nonces.enc");
+
+    let envelope = signer.round1(&request, &nonce_path).unwrap();
+    let result = signer.round1(&request, &nonce_path);
+    assert!(matches!(result, Err(SigningError::InvalidState(_))), "{result:?}");
+    // The journal keeps a second round 1 of the session from generating other nonces
+    let result = signer.round1(&request, &dir.path().join("other-nonces.enc"));
+    assert_eq!(result.err(), Some(SigningError::NonceReuse(request.session_id)));
+
+    let signing_package = single_signer_package(&request, id, envelope);
+    let backup = fs::read(&nonce_path).unwrap();
+    signer.round2(&request, &signing_package, &nonce_path).unwrap().unwrap();
+    let result = signer.round2(&request, &signing_package, &nonce_path);
+    assert!(matches!(result, Err(SigningError::Store(StoreError::Io(_)))), "{result:?}");
+
+    // A restored copy of the nonce file is refused as well
+    fs::write(&nonce_path, backup).unwrap();
+    let result = signer.round2(&request, &signing_package, &nonce_path);
+    assert_eq!(result.err(), Some(SigningError::NonceReuse(request.session_id)));
+}
+
+#[tokio::test]
+async fn test_nonce_file_is_bound_to_key_share_and_session() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let request = offline_request(&harness, 5);
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let mut signer = offline_signer(&harness, &ids[0], Arc::new(MemoryStore::default()));
+    let nonce_path = dir.path().join("nonces.enc");
+    let envelope = signer.round1(&request, &nonce_path).unwrap();
+    let signing_package = single_signer_package(&request, ids[0], envelope);
+    assert!(!fs::read_to_string(&nonce_path).unwrap().contains("hiding"));
+
+    // Another participant's key share can't decrypt the nonces
+    let mut other = offline_signer(&harness, &ids[1], Arc::new(MemoryStore::default()));
+    let result = other.round2(&request, &signing_package, &nonce_path);
+    assert!(matches!(result, Err(SigningError::Store(StoreError::Corrupt(_)))), "{result:?}");
+
+    let foreign = OfflineRequest { session_id: SessionId::from([6; 32]), ..request.clone() };
+    let result = signer.round2(&foreign, &signing_package, &nonce_path);
+    assert!(matches!(result, Err(SigningError::InvalidState(_))), "{result:?}");
+
+    // A failed attempt keeps the nonces for the real round 2
+    assert!(nonce_path.exists());
+    signer.round2(&request, &signing_package, &nonce_path).unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_offline_signer_checks_policy() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let id = *harness.key_data.key_packages.keys().next().unwrap();
+    let store: Arc<dyn StateStore> = Arc::new(MemoryStore::default());
+
+    // The dummy transaction pays out 10000 sat
+    let policy = SignerPolicy { max_amount_per_tx: Some(5_000), ..Default::default() };
+    let signer = offline_signer(&harness, &id, store.clone()).with_policy(policy, Network::Signet).unwrap();
+    let result = signer.round1(&offline_request(&harness, 5), &dir.path().join("refused.enc"));
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))), "{result:?}");
+
+    // The daily limit counts the amounts signed in earlier sessions
+    let policy = SignerPolicy { daily_limit: Some(15_000), ..Default::default() };
+    let mut signer = offline_signer(&harness, &id, store.clone()).with_policy(policy, Network::Signet).unwrap();
+    let mut nonce_paths = Vec::new();
+    let mut packages = Vec::new();
+    for session in [6, 7] {
+        let request = offline_request(&harness, session);
+        let nonce_path = dir.path().join(format!("nonces-{session}.enc"));
+        let envelope = signer.round1(&request, &nonce_path).unwrap();
+        packages.push((request.clone(), single_signer_package(&request, id, envelope)));
+        nonce_paths.push(nonce_path);
+    }
+    signer.round2(&packages[0].0, &packages[0].1, &nonce_paths[0]).unwrap().unwrap();
+    let result = signer.round2(&packages[1].0, &packages[1].1, &nonce_paths[1]);
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))), "{result:?}");
+    assert!(!nonce_paths[1].exists(), "A refused session must drop its nonces");
+    assert_eq!(store.spends().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_offline_signer_waits_for_approval() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let id = *harness.key_data.key_packages.keys().next().unwrap();
+    let queue = Arc::new(MemoryApprovalQueue::default());
+    let mut signer = offline_signer(&harness, &id, Arc::new(MemoryStore::default()))
+        .with_approval_queue(queue.clone(), Duration::from_secs(60));
+
+    let approved = offline_request(&harness, 5);
+    let rejected = offline_request(&harness, 6);
+    let mut packages = Vec::new();
+    for request in [&approved, &rejected] {
+        let nonce_path = dir.path().join(format!("nonces-{}.enc", request.session_id));
+        let envelope = signer.round1(request, &nonce_path).unwrap();
+        packages.push((single_signer_package(request, id, envelope), nonce_path));
+    }
+    assert_eq!(queue.pending().unwrap().len(), 2);
+
+    // Round 2 signs nothing until the operator decided
+    assert!(signer.round2(&approved, &packages[0].0, &packages[0].1).unwrap().is_none());
+    assert!(packages[0].1.exists());
+    queue.decide(approved.session_id, id, ApprovalDecision::Approved).unwrap();
+    assert!(signer.round2(&approved, &packages[0].0, &packages[0].1).unwrap().is_some());
+
+    let decision = ApprovalDecision::Rejected { reason: "unknown recipient".to_string() };
+    queue.decide(rejected.session_id, id, decision).unwrap();
+    let result = signer.round2(&rejected, &packages[1].0, &packages[1].1);
+    assert!(matches!(result, Err(SigningError::ApprovalRejected { .. })), "{result:?}");
+    assert!(!packages[1].1.exists(), "A rejected session must drop its nonces");
+}
+
+#[tokio::test]
+async fn test_exported_key_share_holds_only_its_participant() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let id = *harness.key_data.key_packages.keys().next().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("participant.json");
+
+    let key = harness.key_data.participant(id).unwrap();
+    fs::write(&path, serde_json::to_string(&key).unwrap()).unwrap();
+    let json = fs::read_to_string(&path).unwrap();
+    let others = harness.key_data.key_packages.values().filter(|package| *package.identifier() != id);
+    for other in others {
+        assert!(!json.contains(&hex::encode(other.signing_share().serialize())));
+    }
+
+    // The exported share is enough to sign
+    let key = load_participant_key(&path).await.unwrap();
+    assert_eq!(key.key_package, harness.key_data.key_packages[&id]);
+    let signer = OfflineSigner::new(key.key_package, key.public, Arc::new(MemoryStore::default())).unwrap();
+    let request = offline_request(&harness, 9);
+    let envelope = signer.round1(&request, &dir.path().join("nonces")).unwrap();
+    assert_eq!(envelope.sender(), id);
+    assert!(harness.key_data.participant(Identifier::try_from(9u16).unwrap()).is_none());
+}
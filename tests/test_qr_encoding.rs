@@ -0,0 +1,130 @@
+use frost_demo::{
+    codec::{from_bytes, to_bytes},
+    envelope::Envelope,
+    errors::{CodecError, QrError},
+    offline::{MessageFile, OfflineRequest, OfflineSigner},
+    qr::{render, QrPayload, UrDecoder, UrEncoder},
+    signer::{SessionId, SigningMessage, SigningRequest},
+    store::MemoryStore,
+};
+use frost_secp256k1_tr::SigningPackage;
+use std::{collections::BTreeMap, sync::Arc};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+fn offline_request(harness: &TestHarness) -> OfflineRequest {
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    OfflineRequest { session_id: SessionId::from([3; 32]), request: SigningRequest::new(tx, &prevouts) }
+}
+
+/// Commitment of the first participant, sealed as it would leave an air-gapped signer.
+fn commitment(harness: &TestHarness, request: &OfflineRequest) -> Envelope {
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let key_package = harness.key_data.key_packages.values().next().unwrap().clone();
+    let signer = OfflineSigner::new(key_package, harness.key_data.public.clone(), Arc::new(MemoryStore::default()));
+    signer.unwrap().round1(request, &dir.path().join("nonces.enc")).unwrap()
+}
+
+#[tokio::test]
+async fn test_compact_encoding_round_trips() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let request = offline_request(&harness);
+    let envelope = commitment(&harness, &request);
+
+    let bytes = to_bytes(&envelope).unwrap();
+    assert!(bytes.len() < serde_json::to_vec(&envelope).unwrap().len() / 2, "Compact encoding should be compact");
+    let decoded: Envelope = from_bytes(&bytes).unwrap();
+    assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&envelope).unwrap());
+
+    let decoded: OfflineRequest = from_bytes(&to_bytes(&request).unwrap()).unwrap();
+    assert_eq!(decoded, request);
+
+    let id = *harness.key_data.key_packages.keys().next().unwrap();
+    let abort = harness.seal(SigningMessage::Abort(request.session_id, id, "reason".to_string()));
+    let decoded: Envelope = from_bytes(&to_bytes(&abort).unwrap()).unwrap();
+    assert!(
+        matches!(decoded.message, SigningMessage::Abort(_, sender, ref reason) if sender == id && reason == "reason")
+    );
+}
+
+#[tokio::test]
+async fn test_compact_decoding_rejects_malformed_input() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let envelope = commitment(&harness, &offline_request(&harness));
+    let bytes = to_bytes(&envelope).unwrap();
+
+    assert_eq!(from_bytes::<Envelope>(&[bytes.as_slice(), &[0]].concat()).err(), Some(CodecError::TrailingBytes(1)));
+    assert!(matches!(from_bytes::<Envelope>(&bytes[..bytes.len() - 1]), Err(CodecError::Truncated(_))));
+
+    // The message tag follows version (2), group id (32), round (1) and sequence (8)
+    let mut unknown = bytes.clone();
+    unknown[43] = 0xff;
+    assert_eq!(from_bytes::<Envelope>(&unknown).err(), Some(CodecError::UnknownVariant { kind: "message", tag: 0xff }));
+}
+
+#[tokio::test]
+async fn test_fountain_coded_sequence_survives_missed_parts() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let request = offline_request(&harness);
+    let mut encoder = UrEncoder::new(&QrPayload::Request(request.clone()), 40).unwrap();
+    let count = encoder.fragment_count();
+    assert!(count > 2, "The request should take several parts");
+
+    // Misses every other part, the fountain parts after the plain fragments make up for them
+    let mut decoder = UrDecoder::default();
+    for index in 0..count * 10 {
+        let part = encoder.next_part().unwrap();
+        assert!(part.starts_with("ur:frost-request/"));
+        if index % 2 == 0 {
+            decoder.receive(&part.to_uppercase()).unwrap();
+        }
+        if decoder.is_complete() {
+            break;
+        }
+    }
+
+    assert!(decoder.is_complete());
+    let QrPayload::Request(decoded) = decoder.payload().unwrap() else {
+        panic!("Expected a request");
+    };
+    assert_eq!(decoded, request);
+}
+
+#[tokio::test]
+async fn test_signing_package_and_message_sequences() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let request = offline_request(&harness);
+    let envelope = commitment(&harness, &request);
+    let SigningMessage::NonceCommitment(_, sender, commitments) = envelope.message.clone() else {
+        panic!("Expected a commitment");
+    };
+    let package = SigningPackage::new(BTreeMap::from([(sender, *commitments)]), &request.request.sighash().unwrap());
+
+    let mut decoder = UrDecoder::default();
+    let mut encoder = UrEncoder::new(&QrPayload::Package(package.clone()), 1000).unwrap();
+    assert_eq!(encoder.fragment_count(), 1);
+    let part = encoder.next_part().unwrap();
+    assert!(!render(&part).unwrap().is_empty());
+    decoder.receive(&part).unwrap();
+    let QrPayload::Package(decoded) = decoder.payload().unwrap() else {
+        panic!("Expected a signing package");
+    };
+    assert_eq!(decoded, package);
+
+    // Parts of another sequence are refused instead of being mixed in
+    let message = MessageFile { to: Some(sender), envelope };
+    let mut other = UrEncoder::new(&QrPayload::Message(message.clone()), 1000).unwrap();
+    let part = other.next_part().unwrap();
+    let result = decoder.receive(&part);
+    assert!(matches!(result, Err(QrError::Ur(_))), "{result:?}");
+
+    // A message keeps its addressee across the gap
+    let mut decoder = UrDecoder::default();
+    decoder.receive(&part).unwrap();
+    let QrPayload::Message(decoded) = decoder.payload().unwrap() else {
+        panic!("Expected a message");
+    };
+    assert_eq!(decoded.to, Some(sender));
+    assert_eq!(to_bytes(&decoded.envelope).unwrap(), to_bytes(&message.envelope).unwrap());
+}
@@ -0,0 +1,272 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use bitcoin::{
+    consensus,
+    hashes::{sha256, Hash},
+    Amount, FeeRate, OutPoint, ScriptBuf, Transaction, Txid,
+};
+use frost_demo::{
+    chain::{ChainBackend, EsploraBackend, HistoryEntry, SPEND_VSIZE},
+    electrum::{electrum_script_hash, ElectrumBackend},
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+mod utils;
+use crate::utils::test::{TestHarness, FUNDING_HEIGHT, FUNDING_VALUE};
+
+const TIP_HEIGHT: u64 = 123;
+
+/// Chain served by the mock servers: a transaction funding the group, confirmed at [`FUNDING_HEIGHT`].
+#[derive(Clone)]
+struct MockChain {
+    funding: Transaction,
+    script: ScriptBuf,
+    broadcast: Arc<Mutex<Vec<Transaction>>>,
+}
+
+impl MockChain {
+    fn new(harness: &TestHarness) -> Self {
+        let funding = harness.funding_transaction();
+        let script = funding.output[0].script_pubkey.clone();
+        Self { funding, script, broadcast: Arc::default() }
+    }
+
+    /// Fee paid by the only transaction broadcast, checked to be signed.
+    fn paid_fee(&self, txid: Txid) -> Amount {
+        let [signed] = self.broadcast_transactions().try_into().unwrap();
+        assert_eq!(signed.compute_txid(), txid);
+        assert_eq!(signed.input[0].witness.len(), 1);
+        FUNDING_VALUE - signed.output.iter().map(|output| output.value).sum()
+    }
+
+    fn outpoint(&self) -> OutPoint {
+        OutPoint { txid: self.funding.compute_txid(), vout: 0 }
+    }
+
+    fn record_broadcast(&self, raw: &str) -> Result<Txid, String> {
+        let transaction: Transaction =
+            consensus::deserialize(&hex::decode(raw).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        if transaction.input[0].previous_output != self.outpoint() {
+            return Err("missing inputs".into());
+        }
+        let txid = transaction.compute_txid();
+        self.broadcast.lock().unwrap().push(transaction);
+        Ok(txid)
+    }
+
+    fn broadcast_transactions(&self) -> Vec<Transaction> {
+        self.broadcast.lock().unwrap().clone()
+    }
+}
+
+fn fake_txid(n: u8) -> Txid {
+    Txid::from_byte_array([n; 32])
+}
+
+fn esplora_tx(txid: Txid, height: Option<u64>) -> Value {
+    json!({ "txid": txid, "status": { "confirmed": height.is_some(), "block_height": height } })
+}
+
+/// Serves the subset of the Esplora API the backend uses, script history spans two pages.
+async fn start_esplora(chain: MockChain) -> String {
+    let expected_hash = sha256::Hash::hash(chain.script.as_bytes()).to_string();
+    let check = move |hash: &str| if hash == expected_hash { Ok(()) } else { Err(StatusCode::NOT_FOUND) };
+    let (utxo_check, chain_check, page_check, mempool_check) = (check.clone(), check.clone(), check.clone(), check);
+
+    let router = Router::new()
+        .route(
+            "/tx/:txid/raw",
+            get(|State(chain): State<MockChain>, Path(txid): Path<Txid>| async move {
+                if txid == chain.funding.compute_txid() {
+                    Ok(consensus::serialize(&chain.funding))
+                } else {
+                    Err(StatusCode::NOT_FOUND)
+                }
+            }),
+        )
+        .route(
+            "/scripthash/:hash/utxo",
+            get(move |State(chain): State<MockChain>, Path(hash): Path<String>| async move {
+                utxo_check(&hash)?;
+                let txid = chain.funding.compute_txid();
+                let status = json!({ "confirmed": true, "block_height": FUNDING_HEIGHT });
+                let utxo = json!({ "txid": txid, "vout": 0, "value": FUNDING_VALUE.to_sat(), "status": status });
+                Ok::<_, StatusCode>(Json(json!([utxo])))
+            }),
+        )
+        .route(
+            "/scripthash/:hash/txs/chain",
+            get(move |Path(hash): Path<String>| async move {
+                chain_check(&hash)?;
+                // Newest first
+                let height = |n: u8| Some(FUNDING_HEIGHT + 26 - u64::from(n));
+                let page: Vec<_> = (1..=25).map(|n| esplora_tx(fake_txid(n), height(n))).collect();
+                Ok::<_, StatusCode>(Json(page))
+            }),
+        )
+        .route(
+            "/scripthash/:hash/txs/chain/:last",
+            get(move |State(chain): State<MockChain>, Path((hash, last)): Path<(String, Txid)>| async move {
+                page_check(&hash)?;
+                // Past the oldest transaction the page repeats, as some servers ignore the cursor
+                let funding = chain.funding.compute_txid();
+                assert!([fake_txid(25), funding].contains(&last), "Pages continue after the last transaction seen");
+                Ok::<_, StatusCode>(Json(vec![esplora_tx(funding, Some(FUNDING_HEIGHT))]))
+            }),
+        )
+        .route(
+            "/scripthash/:hash/txs/mempool",
+            get(move |Path(hash): Path<String>| async move {
+                mempool_check(&hash)?;
+                Ok::<_, StatusCode>(Json(vec![esplora_tx(fake_txid(0xee), None)]))
+            }),
+        )
+        .route("/fee-estimates", get(|| async { Json(json!({ "1": 20.5, "6": 2.0, "144": 1.0 })) }))
+        .route("/blocks/tip/height", get(|| async { TIP_HEIGHT.to_string() }))
+        .route(
+            "/tx",
+            post(|State(chain): State<MockChain>, body: String| async move {
+                chain.record_broadcast(&body).map(|txid| txid.to_string()).map_err(|e| (StatusCode::BAD_REQUEST, e))
+            }),
+        )
+        .with_state(chain);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, Router::new().nest("/api", router)).await });
+    url
+}
+
+fn electrum_result(chain: &MockChain, method: &str, params: &Value) -> Result<Value, String> {
+    let script_hash = electrum_script_hash(&chain.script);
+    let txid = chain.funding.compute_txid();
+    match method {
+        "server.version" => Ok(json!(["mock", "1.4"])),
+        "blockchain.transaction.get" if params[0] == json!(txid) => {
+            Ok(json!(consensus::encode::serialize_hex(&chain.funding)))
+        }
+        "blockchain.scripthash.listunspent" if params[0] == script_hash => {
+            Ok(json!([{ "tx_hash": txid, "tx_pos": 0, "height": FUNDING_HEIGHT, "value": FUNDING_VALUE.to_sat() }]))
+        }
+        "blockchain.scripthash.get_history" if params[0] == script_hash => {
+            Ok(json!([{ "tx_hash": txid, "height": FUNDING_HEIGHT }, { "tx_hash": fake_txid(0xee), "height": 0 }]))
+        }
+        "blockchain.estimatefee" => Ok(json!(0.00002)),
+        "blockchain.headers.subscribe" => Ok(json!({ "height": TIP_HEIGHT, "hex": "00" })),
+        "blockchain.transaction.broadcast" => {
+            chain.record_broadcast(params[0].as_str().unwrap_or_default()).map(|txid| json!(txid))
+        }
+        _ => Err(format!("unexpected {method} {params}")),
+    }
+}
+
+/// Serves the Electrum protocol for the chain, a header notification precedes the answer to the subscription.
+async fn start_electrum(chain: MockChain) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("tcp://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let chain = chain.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let request: Value = serde_json::from_str(&line).unwrap();
+                    let method = request["method"].as_str().unwrap();
+                    if method == "blockchain.headers.subscribe" {
+                        let params = json!([{ "height": TIP_HEIGHT + 1, "hex": "00" }]);
+                        let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+                        writer.write_all(format!("{notification}\n").as_bytes()).await.unwrap();
+                    }
+                    let response = match electrum_result(&chain, method, &request["params"]) {
+                        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                        Err(message) => {
+                            json!({ "jsonrpc": "2.0", "id": request["id"], "error": { "code": 1, "message": message } })
+                        }
+                    };
+                    writer.write_all(format!("{response}\n").as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_esplora_scan() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let chain = MockChain::new(&harness);
+    let backend = EsploraBackend::new(&start_esplora(chain.clone()).await);
+
+    let utxos = backend.list_unspent(&chain.script).await.unwrap();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!((utxos[0].outpoint, utxos[0].value, utxos[0].height), (chain.outpoint(), FUNDING_VALUE, Some(100)));
+
+    let history = backend.history(&chain.script).await.unwrap();
+    assert_eq!(history.len(), 27);
+    assert_eq!(history[0], HistoryEntry { txid: chain.funding.compute_txid(), height: Some(FUNDING_HEIGHT) });
+    assert_eq!(history[26], HistoryEntry { txid: fake_txid(0xee), height: None });
+    assert!(history[..26].windows(2).all(|pair| pair[0].height < pair[1].height));
+
+    assert_eq!(backend.estimate_fee_rate(6).await.unwrap(), FeeRate::from_sat_per_vb(2).unwrap());
+    // Without an estimate for the target, the nearest faster one is used
+    assert_eq!(backend.estimate_fee_rate(3).await.unwrap(), FeeRate::from_sat_per_kwu(5_125));
+    assert_eq!(backend.tip_height().await.unwrap(), TIP_HEIGHT);
+    assert!(backend.get_transaction(&fake_txid(1)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_spend_through_esplora() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let chain = MockChain::new(&harness);
+    let backend = EsploraBackend::new(&start_esplora(chain.clone()).await);
+
+    let txid = harness.spend(chain.outpoint(), &backend, true).await.unwrap();
+    assert_eq!(chain.paid_fee(txid), Amount::from_sat(2 * SPEND_VSIZE));
+}
+
+#[tokio::test]
+async fn test_electrum_scan() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let chain = MockChain::new(&harness);
+    let backend = ElectrumBackend::new(&start_electrum(chain.clone()).await).unwrap();
+
+    let utxos = backend.list_unspent(&chain.script).await.unwrap();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!((utxos[0].outpoint, utxos[0].value, utxos[0].height), (chain.outpoint(), FUNDING_VALUE, Some(100)));
+
+    let history = backend.history(&chain.script).await.unwrap();
+    assert_eq!(
+        history,
+        vec![
+            HistoryEntry { txid: chain.funding.compute_txid(), height: Some(FUNDING_HEIGHT) },
+            HistoryEntry { txid: fake_txid(0xee), height: None },
+        ]
+    );
+
+    // Errors of the server don't break the connection
+    assert!(backend.get_transaction(&fake_txid(1)).await.is_err());
+    assert_eq!(backend.estimate_fee_rate(6).await.unwrap(), FeeRate::from_sat_per_vb(2).unwrap());
+    assert_eq!(backend.tip_height().await.unwrap(), TIP_HEIGHT);
+
+    assert!(ElectrumBackend::new("ssl://electrum.example:50002").is_err());
+}
+
+#[tokio::test]
+async fn test_spend_through_electrum() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let chain = MockChain::new(&harness);
+    let backend = ElectrumBackend::new(&start_electrum(chain.clone()).await).unwrap();
+
+    let txid = harness.spend(chain.outpoint(), &backend, true).await.unwrap();
+    assert_eq!(chain.paid_fee(txid), Amount::from_sat(2 * SPEND_VSIZE));
+}
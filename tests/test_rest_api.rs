@@ -0,0 +1,193 @@
+use bitcoin::{consensus, Network, OutPoint, Transaction};
+use frost_demo::{
+    chain::MockBackend,
+    errors::{ApiError, SigningError},
+    rest,
+    service::{ApiToken, SessionEvent, SessionState, SessionStatus, SigningService},
+    signer::CeremonyConfig,
+};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+use tokio::{net::TcpListener, time::sleep};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+const TOKEN: &str = "rest-api-test-token";
+const UTXO: &str = "f2ba6014dd5598a2333b7d1553c932f7a9d7a22b704481da4a10fb0032e35f4b:0";
+
+struct Api {
+    client: Client,
+    url: String,
+}
+
+impl Api {
+    /// Starts the REST API on a free localhost port.
+    async fn start(harness: &TestHarness) -> Self {
+        Self::serve(SigningService::new(harness.key_data.clone(), Network::Signet, CeremonyConfig::default()).unwrap())
+            .await
+    }
+
+    /// Starts the REST API on a mock chain funding the group, returns it with the funded outpoint.
+    async fn start_funded(harness: &TestHarness) -> (Self, OutPoint) {
+        let backend = Arc::new(MockBackend::default());
+        let outpoint = harness.fund_group(&backend);
+        let service = SigningService::new(harness.key_data.clone(), Network::Signet, CeremonyConfig::default())
+            .unwrap()
+            .with_backend(backend);
+        (Self::serve(service).await, outpoint)
+    }
+
+    async fn serve(service: SigningService) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(rest::serve(listener, service, ApiToken::new(TOKEN).unwrap()));
+        Self { client: Client::new(), url }
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.client.get(format!("{}{path}", self.url)).bearer_auth(TOKEN)
+    }
+
+    fn post(&self, path: &str, body: Value) -> RequestBuilder {
+        self.client.post(format!("{}{path}", self.url)).bearer_auth(TOKEN).json(&body)
+    }
+
+    /// Polls the status of a session until it's no longer running.
+    async fn wait_for_outcome(&self, session_id: &str) -> SessionStatus {
+        for _ in 0..100 {
+            let status: SessionStatus =
+                self.get(&format!("/v1/sessions/{session_id}")).send().await.unwrap().json().await.unwrap();
+            if status.state != SessionState::Running {
+                return status;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("Session {session_id} didn't finish");
+    }
+}
+
+#[tokio::test]
+async fn test_requests_need_the_bearer_token() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let api = Api::start(&harness).await;
+
+    let response = api.client.get(format!("{}/v1/group", api.url)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = api.client.get(format!("{}/v1/group", api.url)).bearer_auth("wrong").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["error"].is_string());
+
+    let info: Value = api.get("/v1/group").send().await.unwrap().json().await.unwrap();
+    assert_eq!((info["threshold"].as_u64(), info["total"].as_u64()), (Some(2), Some(3)));
+    assert_eq!(info["participants"].as_array().unwrap().len(), 3);
+
+    let address: Value = api.get("/v1/address?network=regtest").send().await.unwrap().json().await.unwrap();
+    assert_eq!(address["address"], harness.key_data.address(Network::Regtest).unwrap().to_string());
+
+    // Tokens too short to resist guessing are refused up front
+    assert!(matches!(ApiToken::new("short-token"), Err(ApiError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_spend_is_signed_in_the_background() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (api, outpoint) = Api::start_funded(&harness).await;
+
+    // The spent output is looked up on the chain
+    let request = json!({
+        "utxo": outpoint.to_string(),
+        "to": harness.destination(1).to_string(),
+        "amount": 10_000,
+    });
+    let response = api.post("/v1/spends", request).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let created: Value = response.json().await.unwrap();
+    let status = api.wait_for_outcome(created["session_id"].as_str().unwrap()).await;
+
+    assert_eq!(status.state, SessionState::Complete, "{status:?}");
+    let signed: Transaction = consensus::deserialize(&status.signed_transaction.unwrap()).unwrap();
+    assert_eq!(Some(signed.compute_txid()), status.txid);
+    assert_eq!(signed.input[0].witness.len(), 1);
+}
+
+#[tokio::test]
+async fn test_errors_map_to_http_status_codes() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let api = Api::start(&harness).await;
+
+    let request = json!({ "utxo": UTXO, "to": "not an address", "amount": 10_000 });
+    assert_eq!(api.post("/v1/spends", request).send().await.unwrap().status(), StatusCode::BAD_REQUEST);
+    let response = api.post("/v1/psbts", json!({ "psbt": "00" })).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let unknown = "00".repeat(32);
+    let response = api.get(&format!("/v1/sessions/{unknown}")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = api.get("/v1/sessions/not-hex").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Without a node there is nothing to list UTXOs from
+    assert_eq!(api.get("/v1/utxos").send().await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_openapi_document() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let api = Api::start(&harness).await;
+
+    // Served without the token so clients can be generated from it
+    let document: Value =
+        api.client.get(format!("{}/openapi.json", api.url)).send().await.unwrap().json().await.unwrap();
+    for path in ["/v1/group", "/v1/address", "/v1/utxos", "/v1/spends", "/v1/psbts", "/v1/sessions/{id}"] {
+        assert!(document["paths"][path].is_object(), "Missing {path}");
+    }
+    assert_eq!(document["components"]["securitySchemes"]["bearer"]["scheme"], "bearer");
+    assert!(document["components"]["schemas"]["SessionStatus"].is_object());
+}
+
+#[tokio::test]
+async fn test_sessions_are_capped_and_expire() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let service = SigningService::new(harness.key_data.clone(), Network::Signet, CeremonyConfig::default())
+        .unwrap()
+        .with_session_limits(1, Duration::ZERO);
+    let psbt = harness.create_dummy_psbt(4);
+    let mut events = service.subscribe();
+
+    let first = service.sign_psbt(&psbt).unwrap();
+    assert!(matches!(service.sign_psbt(&psbt), Err(ApiError::Signing(SigningError::TooManySessions(1)))));
+    loop {
+        if let SessionEvent::Finished(status) = events.recv().await.unwrap() {
+            assert_eq!(status.session_id, first);
+            break;
+        }
+    }
+    assert_eq!(service.status(first).unwrap().state, SessionState::Complete);
+
+    // The finished session made room for the next one and expired when it started
+    let second = service.sign_psbt(&psbt).unwrap();
+    assert!(matches!(service.status(first), Err(ApiError::UnknownSession(_))));
+    assert_eq!(service.status(second).unwrap().state, SessionState::Running);
+}
+
+#[tokio::test]
+async fn test_overlapping_sessions_share_the_signers() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let config = CeremonyConfig { state_dir: Some(dir.path().to_path_buf()), ..Default::default() };
+    let service = SigningService::new(harness.key_data.clone(), Network::Signet, config).unwrap();
+    let mut events = service.subscribe();
+
+    // The second session starting doesn't recover, and so abort, the first one from the shared journals
+    let mut running: BTreeSet<_> =
+        [5, 6].into_iter().map(|seed| service.sign_psbt(&harness.create_dummy_psbt(seed)).unwrap()).collect();
+    while !running.is_empty() {
+        if let SessionEvent::Finished(status) = events.recv().await.unwrap() {
+            assert_eq!(status.state, SessionState::Complete, "session failed: {:?}", status.error);
+            assert!(running.remove(&status.session_id));
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use frost_demo::{
+    errors::SigningError,
+    signer::{
+        run_signing_ceremony_with_signers, CeremonyConfig, SessionId, SessionView, SigningMessage, SigningPhase,
+        SigningRequest,
+    },
+    transport::Transport,
+};
+use frost_secp256k1_tr::{self as frost, SigningPackage};
+use std::{collections::BTreeMap, time::Duration};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+#[tokio::test]
+async fn test_failed_ceremony_fails_signers_until_reset() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (mut signers, transport) = harness.create_signers();
+    let online = *signers.keys().next().unwrap();
+    signers.retain(|id, _| *id == online);
+    let signer = signers[&online].clone();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { round1_timeout: Duration::from_millis(100), ..Default::default() };
+
+    let result = run_signing_ceremony_with_signers(
+        &harness.key_data,
+        signers,
+        transport,
+        transaction.clone(),
+        &prevouts,
+        &config,
+    )
+    .await;
+
+    assert!(matches!(result, Err(SigningError::Timeout { .. })), "Expected timeout, got {result:?}");
+    let session_id = signer.sessions().unwrap()[0];
+    assert!(matches!(
+        signer.get_state(session_id).unwrap(),
+        SessionView::Failed { error: SigningError::Timeout { .. } }
+    ));
+
+    // Once reset the session is forgotten
+    signer.reset(session_id).unwrap();
+    assert!(matches!(signer.get_state(session_id).unwrap(), SessionView::Idle));
+    assert!(signer.sessions().unwrap().is_empty());
+    signer.initiate_signing_round(SessionId::random(), SigningRequest::new(transaction, &prevouts)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_abort_from_peer_fails_session() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, _) = harness.create_signers();
+    let mut ids = signers.keys();
+    let (signer, peer) = (&signers[ids.next().unwrap()], *ids.next().unwrap());
+    let session_id = SessionId::random();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+
+    // Aborts of other sessions are ignored
+    let other_session = harness.seal(SigningMessage::Abort(SessionId::random(), peer, "policy".to_string()));
+    signer.process_message(other_session).await.unwrap();
+    assert!(matches!(signer.get_state(session_id).unwrap(), SessionView::CollectingCommitments { .. }));
+
+    let abort = harness.seal(SigningMessage::Abort(session_id, peer, "policy".to_string()));
+    signer.process_message(abort).await.unwrap();
+
+    match signer.get_state(session_id).unwrap() {
+        SessionView::Failed { error: SigningError::Aborted { session_id: aborted, by, reason } } => {
+            assert_eq!((aborted, by, reason.as_str()), (session_id, peer, "policy"));
+        }
+        other => panic!("Expected Failed state, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_abort_broadcasts_and_reset_requires_inactive_session() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let signer = signers.values().next().unwrap();
+    let session_id = SessionId::random();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+    while transport.receive().await.unwrap().is_some() {}
+
+    assert_eq!(
+        signer.reset(session_id),
+        Err(SigningError::InvalidTransition { from: SigningPhase::CollectingCommitments, to: SigningPhase::Idle })
+    );
+    assert!(matches!(signer.get_state(session_id).unwrap(), SessionView::CollectingCommitments { .. }));
+
+    signer.abort(session_id, &SigningError::NotEnoughSigners).await;
+
+    assert!(matches!(
+        signer.get_state(session_id).unwrap(),
+        SessionView::Failed { error: SigningError::NotEnoughSigners }
+    ));
+    let (_, envelope) = transport.receive().await.unwrap().expect("Expected an abort message");
+    assert!(
+        matches!(envelope.message, SigningMessage::Abort(id, sender, _) if id == session_id && sender == signer.participant_id)
+    );
+    signer.reset(session_id).unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_share_from_peer_fails_session() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let mut ids = signers.keys();
+    let (signer, peer) = (&signers[ids.next().unwrap()], *ids.next().unwrap());
+    let session_id = SessionId::random();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let request = SigningRequest::new(transaction, &prevouts);
+    signer.initiate_signing_round(session_id, request.clone()).await.unwrap();
+
+    let SessionView::CollectingCommitments { commitments, .. } = signer.get_state(session_id).unwrap() else {
+        panic!("Expected CollectingCommitments state");
+    };
+    let key_package = &harness.key_data.key_packages[&peer];
+    let (_, peer_commitments) = frost::round1::commit(key_package.signing_share(), &mut rand::rngs::OsRng);
+    let commitments =
+        BTreeMap::from([(signer.participant_id, commitments[&signer.participant_id]), (peer, peer_commitments)]);
+    let signing_package = SigningPackage::new(commitments, &request.sighash().unwrap());
+    signer.advance_to_sharing_round(session_id, signing_package.clone()).unwrap();
+    while transport.receive().await.unwrap().is_some() {}
+
+    // The peer signs with nonces other than those it committed to
+    let (nonces, _) = frost::round1::commit(key_package.signing_share(), &mut rand::rngs::OsRng);
+    let share = frost::round2::sign_with_tweak(&signing_package, &nonces, key_package, None).unwrap();
+    let result = signer.process_message(harness.seal(SigningMessage::SignatureShare(session_id, peer, share))).await;
+
+    assert_eq!(result, Err(SigningError::InvalidSignatureShare(peer)));
+    assert!(matches!(
+        signer.get_state(session_id).unwrap(),
+        SessionView::Failed { error: SigningError::InvalidSignatureShare(culprit) } if culprit == peer
+    ));
+    let (_, envelope) = transport.receive().await.unwrap().expect("Expected an abort message");
+    assert!(matches!(envelope.message, SigningMessage::Abort(id, ..) if id == session_id));
+}
@@ -0,0 +1,173 @@
+use bitcoin::{Address, Network, Transaction};
+use frost_demo::{
+    errors::SigningError,
+    policy::{PolicyEngine, SignerPolicy},
+    service::{SessionEvent, SessionState, SigningService},
+    signer::{run_signing_ceremony_with_config, CeremonyConfig, SignerSelection},
+};
+use std::time::{Duration, SystemTime};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+/// Engine for the harness group on signet.
+fn engine(harness: &TestHarness, policy: SignerPolicy) -> PolicyEngine {
+    let group_script = harness.key_data.address(Network::Signet).unwrap().script_pubkey();
+    PolicyEngine::new(policy, Network::Signet, group_script).unwrap()
+}
+
+fn destination(transaction: &Transaction) -> Address {
+    Address::from_script(&transaction.output[0].script_pubkey, Network::Signet).unwrap()
+}
+
+fn assert_violation(result: Result<u64, SigningError>) {
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))), "Expected policy violation, got {result:?}");
+}
+
+#[tokio::test]
+async fn test_permissive_policy_accepts_transaction() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+
+    let amount = engine(&harness, SignerPolicy::default()).evaluate(&tx, &prevouts, SystemTime::now());
+
+    // Change back to the group is not counted as paid out
+    assert_eq!(amount, Ok(10_000));
+}
+
+#[tokio::test]
+async fn test_policy_rejects_unknown_destination() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (allowed_tx, _) = harness.create_dummy_transaction(1);
+    let (tx, prevouts) = harness.create_dummy_transaction(2);
+    let policy = SignerPolicy {
+        allowed_destinations: Some(vec![destination(&allowed_tx).as_unchecked().clone()]),
+        ..Default::default()
+    };
+    let engine = engine(&harness, policy);
+
+    assert_violation(engine.evaluate(&tx, &prevouts, SystemTime::now()));
+
+    assert!(engine.evaluate(&allowed_tx, &prevouts, SystemTime::now()).is_ok());
+}
+
+#[tokio::test]
+async fn test_policy_enforces_amount_limits() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let now = SystemTime::now();
+
+    let per_tx = engine(&harness, SignerPolicy { max_amount_per_tx: Some(9_999), ..Default::default() });
+    assert_violation(per_tx.evaluate(&tx, &prevouts, now));
+
+    // Two spends fit into the daily limit, the third one only after the window moved on
+    let mut daily = engine(&harness, SignerPolicy { daily_limit: Some(20_000), ..Default::default() });
+    for _ in 0..2 {
+        let amount = daily.evaluate(&tx, &prevouts, now).unwrap();
+        daily.record_spend(amount, now);
+    }
+    assert_violation(daily.evaluate(&tx, &prevouts, now + Duration::from_secs(60)));
+    assert!(daily.evaluate(&tx, &prevouts, now + Duration::from_secs(25 * 60 * 60)).is_ok());
+}
+
+#[tokio::test]
+async fn test_policy_enforces_fee_rate_and_change() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let now = SystemTime::now();
+
+    // 500 sat for 154 vB is just above 3 sat/vB, which must not pass as 3
+    let low_fee_rate = engine(&harness, SignerPolicy { max_fee_rate: Some(3), ..Default::default() });
+    assert_violation(low_fee_rate.evaluate(&tx, &prevouts, now));
+    let high_fee_rate = engine(&harness, SignerPolicy { max_fee_rate: Some(4), ..Default::default() });
+    assert!(high_fee_rate.evaluate(&tx, &prevouts, now).is_ok());
+
+    // Drop the change output, the remainder would go to the miners
+    let mut no_change = tx.clone();
+    no_change.output.truncate(1);
+    let require_change = engine(&harness, SignerPolicy { require_change: true, ..Default::default() });
+    assert_violation(require_change.evaluate(&no_change, &prevouts, now));
+}
+
+#[tokio::test]
+async fn test_signing_ceremony_stops_on_policy_violation() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let policy = SignerPolicy { max_amount_per_tx: Some(5_000), ..Default::default() };
+    let config = CeremonyConfig { policy: Some(policy), ..Default::default() };
+
+    let result = run_signing_ceremony_with_config(harness.key_data.clone(), tx, &prevouts, &config).await;
+
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))), "Expected policy violation, got {result:?}");
+}
+
+#[tokio::test]
+async fn test_policy_checks_networks_and_group_change() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let group_script = harness.key_data.address(Network::Signet).unwrap().script_pubkey();
+
+    // Destinations of another network are refused when the policy is loaded
+    let mainnet = harness.key_data.address(Network::Bitcoin).unwrap();
+    let policy =
+        SignerPolicy { allowed_destinations: Some(vec![mainnet.as_unchecked().clone()]), ..Default::default() };
+    let result = PolicyEngine::new(policy, Network::Signet, group_script);
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))));
+
+    // Only outputs to the group address are change, even when the spent output pays elsewhere
+    let (other, _) = harness.create_dummy_transaction(2);
+    let mut foreign_prevouts = prevouts.clone();
+    foreign_prevouts[0].script_pubkey = other.output[0].script_pubkey.clone();
+    let require_change = engine(&harness, SignerPolicy { require_change: true, ..Default::default() });
+    assert_eq!(require_change.evaluate(&tx, &foreign_prevouts, SystemTime::now()), Ok(10_000));
+}
+
+#[tokio::test]
+async fn test_daily_limit_holds_across_ceremonies() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let policy = SignerPolicy { daily_limit: Some(15_000), ..Default::default() };
+
+    // Without a state directory the signed amounts can't be kept
+    let config = CeremonyConfig { policy: Some(policy.clone()), ..Default::default() };
+    let result = run_signing_ceremony_with_config(harness.key_data.clone(), tx.clone(), &prevouts, &config).await;
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))), "Expected policy violation, got {result:?}");
+
+    // Every ceremony sets up new signers, the second spend still counts towards the limit
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let config = CeremonyConfig {
+        policy: Some(policy),
+        state_dir: Some(state_dir.path().to_path_buf()),
+        selection: SignerSelection::from_indices(&[1, 2]).unwrap(),
+        ..Default::default()
+    };
+    run_signing_ceremony_with_config(harness.key_data.clone(), tx.clone(), &prevouts, &config).await.unwrap();
+    let result = run_signing_ceremony_with_config(harness.key_data.clone(), tx, &prevouts, &config).await;
+    assert!(matches!(result, Err(SigningError::PolicyViolation(_))), "Expected policy violation, got {result:?}");
+}
+
+#[tokio::test]
+async fn test_daily_limit_holds_for_concurrent_sessions() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let config = CeremonyConfig {
+        policy: Some(SignerPolicy { daily_limit: Some(15_000), ..Default::default() }),
+        state_dir: Some(state_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let service = SigningService::new(harness.key_data.clone(), Network::Signet, config).unwrap();
+    let mut events = service.subscribe();
+
+    // Each spend pays 10 000 sat, started together they must not both pass the limit
+    let sessions: Vec<_> = (1..=2).map(|seed| service.sign_psbt(&harness.create_dummy_psbt(seed)).unwrap()).collect();
+    let mut outcomes = Vec::new();
+    while outcomes.len() < sessions.len() {
+        if let SessionEvent::Finished(status) = events.recv().await.unwrap() {
+            outcomes.push(status);
+        }
+    }
+    let signed = outcomes.iter().filter(|status| status.state == SessionState::Complete).count();
+    assert!(signed <= 1, "both spends were signed");
+    let failed = outcomes.iter().find(|status| status.state == SessionState::Failed).expect("no spend failed");
+    assert!(failed.error.as_deref().is_some_and(|error| error.contains("daily limit")), "{:?}", failed.error);
+}
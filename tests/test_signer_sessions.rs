@@ -0,0 +1,119 @@
+use frost_demo::{
+    envelope::Sealer,
+    errors::SigningError,
+    signer::{CeremonyConfig, SessionId, SessionView, SigningMessage, SigningRequest},
+    transport::Transport,
+};
+use frost_secp256k1_tr::{self as frost, SigningPackage};
+use std::{collections::BTreeMap, time::Duration};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+#[tokio::test]
+async fn test_messages_are_routed_to_their_session() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, _) = harness.create_signers();
+    let mut ids = signers.keys();
+    let (signer, peer) = (&signers[ids.next().unwrap()], *ids.next().unwrap());
+    let (first, second) = (SessionId::from([1; 32]), SessionId::from([2; 32]));
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    signer.initiate_signing_round(first, SigningRequest::new(transaction.clone(), &prevouts)).await.unwrap();
+    signer.initiate_signing_round(second, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+
+    let (_, commitments) = frost_secp256k1_tr::round1::commit(
+        harness.key_data.key_packages[&peer].signing_share(),
+        &mut rand::rngs::OsRng,
+    );
+    let message = harness.seal(SigningMessage::NonceCommitment(second, peer, Box::new(commitments)));
+    signer.process_message(message).await.unwrap();
+    let abort = harness.seal(SigningMessage::Abort(first, peer, "policy".to_string()));
+    signer.process_message(abort).await.unwrap();
+
+    assert!(matches!(signer.get_state(first).unwrap(), SessionView::Failed { error: SigningError::Aborted { .. } }));
+    match signer.get_state(second).unwrap() {
+        SessionView::CollectingCommitments { commitments, .. } => assert!(commitments.contains_key(&peer)),
+        other => panic!("Expected CollectingCommitments state, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_session_cap_and_garbage_collection() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let config = CeremonyConfig { max_sessions: 2, round1_timeout: Duration::from_millis(100), ..Default::default() };
+    let signer = signers.values().next().unwrap().clone().with_config(config);
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let request = SigningRequest::new(transaction, &prevouts);
+    let sessions: Vec<SessionId> = (1..=3).map(|i| SessionId::from([i; 32])).collect();
+
+    signer.initiate_signing_round(sessions[0], request.clone()).await.unwrap();
+    signer.initiate_signing_round(sessions[1], request.clone()).await.unwrap();
+    let result = signer.initiate_signing_round(sessions[2], request.clone()).await;
+    assert_eq!(result, Err(SigningError::TooManySessions(2)));
+
+    // A failed session makes room for the next one
+    signer.abort(sessions[0], &SigningError::NotEnoughSigners).await;
+    signer.initiate_signing_round(sessions[2], request).await.unwrap();
+    assert_eq!(signer.sessions().unwrap(), vec![sessions[1], sessions[2]]);
+
+    // Sessions past their deadline fail, are collected and the peers are told to abort them
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(signer.collect_garbage().await.unwrap(), vec![sessions[1], sessions[2]]);
+    assert!(signer.sessions().unwrap().is_empty());
+
+    let mut aborted = Vec::new();
+    while let Ok(Ok((_, envelope))) = tokio::time::timeout(Duration::from_millis(100), transport.next_message()).await {
+        if let SigningMessage::Abort(session_id, ..) = envelope.message {
+            aborted.push(session_id);
+        }
+    }
+    assert_eq!(aborted, vec![sessions[0], sessions[1], sessions[2]]);
+}
+
+#[tokio::test]
+async fn test_early_shares_are_authenticated_before_they_are_kept() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let mut ids = harness.key_data.key_packages.keys().cloned();
+    let (own, peer, forger) = (ids.next().unwrap(), ids.next().unwrap(), ids.next().unwrap());
+    let signer = &signers[&own];
+    let handle = signer.spawn();
+    let session_id = SessionId::random();
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let request = SigningRequest::new(transaction, &prevouts);
+    handle.start(session_id, request.clone()).await.unwrap();
+
+    // The peer signs and sends its share before the signer got the signing package
+    let SessionView::CollectingCommitments { commitments, .. } = signer.get_state(session_id).unwrap() else {
+        panic!("Expected CollectingCommitments state");
+    };
+    let key_package = &harness.key_data.key_packages[&peer];
+    let (nonces, peer_commitments) = frost::round1::commit(key_package.signing_share(), &mut rand::rngs::OsRng);
+    let commitments = BTreeMap::from([(own, commitments[&own]), (peer, peer_commitments)]);
+    let signing_package = SigningPackage::new(commitments, &request.sighash().unwrap());
+    let share = frost::round2::sign_with_tweak(&signing_package, &nonces, key_package, None).unwrap();
+
+    // Forged shares in the peer's name arrive first, they must not take the place of the peer's
+    let forger = Sealer::new(&harness.key_data.key_packages[&forger], &harness.key_data.public).unwrap();
+    let peer_transport = transport.participant(peer).unwrap();
+    for _ in 0..3 {
+        let forged = forger.seal(SigningMessage::SignatureShare(session_id, peer, share)).unwrap();
+        peer_transport.send(own, forged).await.unwrap();
+    }
+    let envelope = harness.seal(SigningMessage::SignatureShare(session_id, peer, share));
+    peer_transport.send(own, envelope).await.unwrap();
+    handle.sign(session_id, signing_package).await.unwrap();
+
+    let shares = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match signer.get_state(session_id).unwrap() {
+                SessionView::CollectingShares { shares, .. } if shares.contains_key(&peer) => break shares,
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    })
+    .await
+    .expect("The early share was not kept");
+    assert_eq!(shares.keys().cloned().collect::<Vec<_>>(), vec![own, peer]);
+}
@@ -0,0 +1,204 @@
+use bitcoin::Network;
+use frost_demo::{
+    approval::{
+        ApprovalDecision, ApprovalQueue, FileApprovalQueue, MemoryApprovalQueue, PendingRequest, TransactionSummary,
+    },
+    errors::{SigningError, StoreError},
+    signer::{run_signing_ceremony_with_signers, CeremonyConfig, SessionId, SigningRequest, SigningRound},
+};
+use frost_secp256k1_tr::Identifier;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+/// Operator that makes the same decision on every request showing up in the queue.
+fn spawn_operator(queue: Arc<MemoryApprovalQueue>, decision: ApprovalDecision) {
+    tokio::spawn(async move {
+        loop {
+            for request in queue.pending().unwrap() {
+                queue.decide(request.session_id, request.participant, decision.clone()).unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+}
+
+#[tokio::test]
+async fn test_transaction_summary() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+
+    let summary = TransactionSummary::new(&SigningRequest::new(tx.clone(), &prevouts)).unwrap();
+
+    assert_eq!(summary.txid, tx.compute_txid());
+    assert_eq!(summary.input_amount, 50_000);
+    assert_eq!(summary.amount(), 10_000);
+    assert_eq!(summary.change(), 39_500);
+    assert_eq!(summary.fee, 500);
+    assert!(summary.fee_rate > 1.0 && summary.fee_rate < 10.0);
+
+    let rendered = summary.render(Network::Signet);
+    let group_address = harness.key_data.address(Network::Signet).unwrap();
+    assert!(rendered.contains(&format!("{group_address}: 39500 sat (change)")));
+    assert!(rendered.contains("Fee: 500 sat"));
+}
+
+#[tokio::test]
+async fn test_signing_waits_for_approval() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let queue = Arc::new(MemoryApprovalQueue::default());
+    let signers = signers.into_iter().map(|(id, signer)| (id, signer.with_approval_queue(queue.clone()))).collect();
+
+    spawn_operator(queue.clone(), ApprovalDecision::Approved);
+    let result =
+        run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &Default::default())
+            .await;
+
+    assert!(result.is_ok(), "signing failed: {:?}", result.err());
+    assert!(queue.pending().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_rejected_request_is_not_signed() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let queue = Arc::new(MemoryApprovalQueue::default());
+    let signers = signers.into_iter().map(|(id, signer)| (id, signer.with_approval_queue(queue.clone()))).collect();
+
+    spawn_operator(queue, ApprovalDecision::Rejected { reason: "unknown recipient".to_string() });
+    let result =
+        run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &Default::default())
+            .await;
+
+    match result {
+        Err(SigningError::ApprovalRejected { reason, .. }) => assert_eq!(reason, "unknown recipient"),
+        other => panic!("Expected ApprovalRejected error, but got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_approval_deadline() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let queue = Arc::new(MemoryApprovalQueue::default());
+    let signers = signers.into_iter().map(|(id, signer)| (id, signer.with_approval_queue(queue.clone()))).collect();
+    let config = CeremonyConfig { approval_timeout: Duration::from_millis(100), ..Default::default() };
+
+    let result = run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await;
+
+    assert!(
+        matches!(result, Err(SigningError::Timeout { round: SigningRound::Approval, .. })),
+        "Expected approval timeout, but got {:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn test_file_approval_queue() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (signers, transport) = harness.create_signers();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let queue: Arc<dyn ApprovalQueue> = Arc::new(FileApprovalQueue::open(dir.path()).unwrap());
+    let signers = signers.into_iter().map(|(id, signer)| (id, signer.with_approval_queue(queue.clone()))).collect();
+
+    let ceremony = tokio::spawn(async move {
+        let config = CeremonyConfig::default();
+        run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config).await
+    });
+
+    // The operator's CLI opens the same queue directory and approves every participant
+    let operator = FileApprovalQueue::open(dir.path()).unwrap();
+    let mut approved = Vec::new();
+    while !ceremony.is_finished() {
+        for request in operator.pending().unwrap() {
+            assert_eq!(request.summary.amount(), 10_000);
+            let unknown = SessionId::random();
+            assert_eq!(
+                operator.decide(unknown, request.participant, ApprovalDecision::Approved),
+                Err(StoreError::UnknownSession(unknown))
+            );
+            operator.decide(request.session_id, request.participant, ApprovalDecision::Approved).unwrap();
+            approved.push((request.session_id, request.participant));
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let result = ceremony.await.unwrap();
+    assert!(result.is_ok(), "signing failed: {:?}", result.err());
+    assert!(!approved.is_empty());
+    for (session_id, participant) in approved {
+        assert_eq!(operator.decision(session_id, participant).unwrap(), Some(ApprovalDecision::Approved));
+        assert_eq!(
+            operator.decide(session_id, participant, ApprovalDecision::Approved),
+            Err(StoreError::UnknownSession(session_id))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_approvals_are_per_participant_and_expire() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let summary = TransactionSummary::new(&SigningRequest::new(tx, &prevouts)).unwrap();
+    let session_id = SessionId::random();
+    let (first, second) = (Identifier::try_from(1).unwrap(), Identifier::try_from(2).unwrap());
+    let deadline = SystemTime::now() + Duration::from_secs(60);
+
+    let queue = FileApprovalQueue::open(dir.path()).unwrap();
+    for participant in [first, second] {
+        queue
+            .submit(&PendingRequest { session_id, participant, index: None, summary: summary.clone(), deadline })
+            .unwrap();
+    }
+    queue.decide(session_id, first, ApprovalDecision::Approved).unwrap();
+    assert_eq!(queue.decision(session_id, first).unwrap(), Some(ApprovalDecision::Approved));
+    assert_eq!(queue.decision(session_id, second).unwrap(), None);
+
+    // Decisions arriving after the deadline are refused
+    let expired = SystemTime::now() - Duration::from_secs(1);
+    queue
+        .submit(&PendingRequest { session_id, participant: second, index: Some(2), summary, deadline: expired })
+        .unwrap();
+    assert_eq!(queue.decide(session_id, second, ApprovalDecision::Approved), Err(StoreError::Expired(session_id)));
+    assert_eq!(queue.decision(session_id, second).unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_resubmitted_transaction_needs_a_new_decision() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let summary = |seed| {
+        let (tx, prevouts) = harness.create_dummy_transaction(seed);
+        TransactionSummary::new(&SigningRequest::new(tx, &prevouts)).unwrap()
+    };
+    let session_id = SessionId::random();
+    let participant = Identifier::try_from(1).unwrap();
+    let deadline = SystemTime::now() + Duration::from_secs(60);
+    let approved = PendingRequest { session_id, participant, index: Some(1), summary: summary(1), deadline };
+    let replaced = PendingRequest { summary: summary(2), ..approved.clone() };
+
+    let queues: [Box<dyn ApprovalQueue>; 2] =
+        [Box::new(FileApprovalQueue::open(dir.path()).unwrap()), Box::new(MemoryApprovalQueue::default())];
+    for queue in queues {
+        queue.submit(&approved).unwrap();
+        queue.decide(session_id, participant, ApprovalDecision::Approved).unwrap();
+        // The same transaction keeps its decision
+        queue.submit(&approved).unwrap();
+        assert_eq!(queue.decision(session_id, participant).unwrap(), Some(ApprovalDecision::Approved));
+
+        // Another transaction under the same session waits for the operator again
+        queue.submit(&replaced).unwrap();
+        assert_eq!(queue.decision(session_id, participant).unwrap(), None);
+        assert_eq!(queue.pending().unwrap(), vec![replaced.clone()]);
+    }
+}
@@ -0,0 +1,184 @@
+use bitcoin::Transaction;
+use frost_demo::{
+    codec::to_bytes,
+    envelope::ReplayGuard,
+    errors::{EnvelopeError, SigningError},
+    signer::{run_signing_ceremony_with_signers, CeremonyConfig, SessionId, SigningMessage, SigningRound},
+    simulation::{FaultConfig, SimulatedNetwork},
+    transport::Transport,
+};
+use frost_secp256k1_tr::Identifier;
+use std::{sync::Arc, time::Duration};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+fn config() -> CeremonyConfig {
+    CeremonyConfig {
+        round1_timeout: Duration::from_millis(500),
+        round2_timeout: Duration::from_millis(500),
+        ceremony_timeout: Duration::from_secs(5),
+        ..Default::default()
+    }
+}
+
+fn participants(harness: &TestHarness) -> Vec<Identifier> {
+    harness.key_data.key_packages.keys().cloned().collect()
+}
+
+/// Runs a ceremony on a simulated network with the given faults.
+async fn run_on(harness: &TestHarness, network: &Arc<SimulatedNetwork>) -> Result<Transaction, SigningError> {
+    let (signers, transport) = network.setup_signers(&harness.key_data).unwrap();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    run_signing_ceremony_with_signers(&harness.key_data, signers, transport, tx, &prevouts, &config()).await
+}
+
+/// A ceremony under faults either signs or fails naming who it gave up on.
+fn assert_clean(result: &Result<Transaction, SigningError>) {
+    match result {
+        Ok(_) | Err(SigningError::Aborted { .. }) => {}
+        Err(SigningError::Timeout { missing, .. }) => assert!(!missing.is_empty(), "Timeout should name the missing"),
+        Err(e) => panic!("Expected success or an attributable error, got {e:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_ceremony_survives_duplicates() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let network =
+        SimulatedNetwork::new(participants(&harness), FaultConfig { duplicate_rate: 1.0, ..Default::default() });
+
+    let result = run_on(&harness, &network).await;
+
+    assert!(result.is_ok(), "signing failed: {:?}", result.err());
+    assert!(network.stats().duplicated > 0);
+}
+
+#[tokio::test]
+async fn test_ceremony_survives_reordering_and_delay() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let faults = FaultConfig {
+        seed: 7,
+        reorder_rate: 0.5,
+        reorder_window: Duration::from_millis(50),
+        delay: Duration::from_millis(20),
+        ..Default::default()
+    };
+    let network = SimulatedNetwork::new(participants(&harness), faults);
+
+    let result = run_on(&harness, &network).await;
+
+    assert!(result.is_ok(), "signing failed: {:?}", result.err());
+    let stats = network.stats();
+    assert!(stats.reordered > 0 && stats.delayed > 0, "{stats:?}");
+}
+
+#[tokio::test]
+async fn test_delay_beyond_the_round_deadline_times_out() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let network = SimulatedNetwork::new(
+        participants(&harness),
+        FaultConfig { delay: Duration::from_secs(2), ..Default::default() },
+    );
+
+    let result = run_on(&harness, &network).await;
+
+    assert_eq!(
+        result.err(),
+        Some(SigningError::Timeout { round: SigningRound::Commitments, missing: participants(&harness) })
+    );
+}
+
+#[tokio::test]
+async fn test_dropped_messages_fail_cleanly() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let network = SimulatedNetwork::new(participants(&harness), FaultConfig { drop_rate: 1.0, ..Default::default() });
+    let result = run_on(&harness, &network).await;
+    assert_eq!(
+        result.err(),
+        Some(SigningError::Timeout { round: SigningRound::Commitments, missing: participants(&harness) })
+    );
+
+    for seed in 0..4 {
+        let network =
+            SimulatedNetwork::new(participants(&harness), FaultConfig { seed, drop_rate: 0.3, ..Default::default() });
+        assert_clean(&run_on(&harness, &network).await);
+    }
+}
+
+#[tokio::test]
+async fn test_partition_below_threshold_names_the_isolated() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let ids = participants(&harness);
+
+    let network = SimulatedNetwork::new(ids.clone(), FaultConfig::default());
+    network.partition([ids[2]]);
+    let result = run_on(&harness, &network).await;
+    assert!(result.is_ok(), "A partition leaving a threshold connected should not block signing: {result:?}");
+
+    let network = SimulatedNetwork::new(ids.clone(), FaultConfig::default());
+    network.partition([ids[1], ids[2]]);
+    let result = run_on(&harness, &network).await;
+    assert_eq!(
+        result.err(),
+        Some(SigningError::Timeout { round: SigningRound::Commitments, missing: ids[1..].to_vec() })
+    );
+    assert!(network.stats().partitioned > 0);
+
+    network.heal();
+    let result = run_on(&harness, &network).await;
+    assert!(result.is_ok(), "signing failed after healing: {:?}", result.err());
+}
+
+#[tokio::test]
+async fn test_tampered_messages_are_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let network = SimulatedNetwork::new(participants(&harness), FaultConfig { tamper_rate: 1.0, ..Default::default() });
+
+    // The payload changes in transit and the receiver blames the sender
+    let ids = participants(&harness);
+    let (sender, receiver) = (network.participant(ids[0]).unwrap(), network.participant(ids[1]).unwrap());
+    let sent = harness.seal(SigningMessage::Abort(SessionId::random(), ids[0], "test".to_string()));
+    sender.send(ids[1], sent.clone()).await.unwrap();
+    let (_, received) = receiver.receive().await.unwrap().unwrap();
+    assert_eq!(received.sequence, sent.sequence);
+    assert_ne!(to_bytes(&received.message).unwrap(), to_bytes(&sent.message).unwrap());
+    let guard = ReplayGuard::new(&harness.key_data.public).unwrap();
+    assert_eq!(guard.open(&received, 1), Err(EnvelopeError::InvalidSignature(ids[0])));
+
+    let result = run_on(&harness, &network).await;
+
+    assert_eq!(
+        result.err(),
+        Some(SigningError::Timeout { round: SigningRound::Commitments, missing: participants(&harness) })
+    );
+    assert!(network.stats().tampered > 0);
+
+    let faults = FaultConfig { seed: 3, tamper_rate: 0.2, ..Default::default() };
+    assert_clean(&run_on(&harness, &SimulatedNetwork::new(participants(&harness), faults)).await);
+}
+
+#[tokio::test]
+async fn test_faults_are_deterministic_for_a_seed() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let ids = participants(&harness);
+    let faults = FaultConfig { seed: 42, drop_rate: 0.3, duplicate_rate: 0.3, ..Default::default() };
+
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        let network = SimulatedNetwork::new(ids.clone(), faults.clone());
+        let (sender, receiver) = (network.participant(ids[0]).unwrap(), network.participant(ids[1]).unwrap());
+        for i in 0..20u8 {
+            let abort = SigningMessage::Abort(SessionId::from([i; 32]), ids[0], "test".to_string());
+            sender.send(ids[1], harness.seal(abort)).await.unwrap();
+        }
+        let mut received = Vec::new();
+        while let Some((_, envelope)) = receiver.receive().await.unwrap() {
+            received.push(envelope.message.session_id());
+        }
+        runs.push((received, network.stats()));
+    }
+
+    assert_eq!(runs[0], runs[1]);
+    assert!(runs[0].1.dropped > 0 && runs[0].1.duplicated > 0, "{:?}", runs[0].1);
+}
@@ -0,0 +1,169 @@
+use frost_demo::{
+    errors::SigningError,
+    signer::{
+        recover_session, run_signing_ceremony_with_config, CeremonyConfig, Recovery, SessionId, SessionView,
+        SigningRequest,
+    },
+    store::{FileJournal, JournalEntry, SessionRecord, StateStore},
+};
+use std::{fs::OpenOptions, io::Write, sync::Arc};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+#[tokio::test]
+async fn test_nonces_are_never_reused_after_restart() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let session_id = SessionId::from([7; 32]);
+
+    // Generate nonces for a session, then "crash" before the session finishes
+    {
+        let (signers, _) = harness.create_signers();
+        let journal = Arc::new(FileJournal::open(state_dir.path()).unwrap());
+        let signer = signers.values().next().unwrap().clone().with_store(journal);
+        signer.initiate_signing_round(session_id, SigningRequest::new(transaction.clone(), &prevouts)).await.unwrap();
+    }
+
+    // Restarted signer aborts the interrupted session
+    let (signers, _) = harness.create_signers();
+    let journal = Arc::new(FileJournal::open(state_dir.path()).unwrap());
+    let signer = signers.values().next().unwrap().clone().with_store(journal.clone());
+    assert_eq!(signer.recover().unwrap(), vec![session_id]);
+    assert!(SessionRecord::replay(journal.load(session_id).unwrap()).error.is_some());
+
+    // Nonces for the session must not be generated again
+    let result = signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await;
+    assert_eq!(result.err(), Some(SigningError::NonceReuse(session_id)));
+}
+
+#[tokio::test]
+async fn test_completed_session_is_recovered_from_journal() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { state_dir: Some(state_dir.path().to_path_buf()), ..Default::default() };
+
+    let signed_tx = run_signing_ceremony_with_config(harness.key_data.clone(), tx, &prevouts, &config).await.unwrap();
+
+    // Any participant that signed has the full session in its journal
+    let journals = std::fs::read_dir(state_dir.path()).unwrap().map(|entry| entry.unwrap().path());
+    let mut recovered = 0;
+    for journal_dir in journals {
+        let journal = FileJournal::open(journal_dir).unwrap();
+        for session_id in journal.sessions().unwrap() {
+            let record = SessionRecord::replay(journal.load(session_id).unwrap());
+            if record.share_signed {
+                let recovery = recover_session(&harness.key_data, &journal, session_id).unwrap();
+                assert_eq!(recovery, Recovery::Completed(signed_tx.clone()));
+                recovered += 1;
+            }
+        }
+    }
+    assert_eq!(recovered, harness.key_data.threshold as usize);
+}
+
+#[tokio::test]
+async fn test_interrupted_session_with_all_shares_is_resumed() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { state_dir: Some(state_dir.path().to_path_buf()), ..Default::default() };
+    let signed_tx = run_signing_ceremony_with_config(harness.key_data.clone(), tx, &prevouts, &config).await.unwrap();
+
+    // Copy a signing participant's journal without its final entry, as if the coordinator crashed before aggregation
+    let (session_id, mut entries) = std::fs::read_dir(state_dir.path())
+        .unwrap()
+        .find_map(|entry| {
+            let journal = FileJournal::open(entry.unwrap().path()).unwrap();
+            let session_id = journal.sessions().unwrap()[0];
+            let entries = journal.load(session_id).unwrap();
+            SessionRecord::replay(entries.clone()).share_signed.then_some((session_id, entries))
+        })
+        .expect("A signing participant journal should exist");
+    assert!(matches!(entries.pop(), Some(JournalEntry::Complete { .. })));
+
+    let copy_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let copy = FileJournal::open(copy_dir.path()).unwrap();
+    for entry in &entries {
+        copy.append(session_id, entry).unwrap();
+    }
+
+    let recovery = recover_session(&harness.key_data, &copy, session_id).unwrap();
+    assert_eq!(recovery, Recovery::Completed(signed_tx));
+}
+
+#[tokio::test]
+async fn test_signer_resumes_session_after_signing_its_share() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { state_dir: Some(state_dir.path().to_path_buf()), ..Default::default() };
+    let signed_tx = run_signing_ceremony_with_config(harness.key_data.clone(), tx, &prevouts, &config).await.unwrap();
+
+    // Copy a signing participant's journal without its final entry, as if the signer crashed after signing
+    let (participant, session_id, mut entries) = std::fs::read_dir(state_dir.path())
+        .unwrap()
+        .find_map(|entry| {
+            let path = entry.unwrap().path();
+            let journal = FileJournal::open(&path).unwrap();
+            let session_id = journal.sessions().unwrap()[0];
+            let entries = journal.load(session_id).unwrap();
+            let participant = path.file_name().unwrap().to_str().unwrap().to_string();
+            SessionRecord::replay(entries.clone()).share_signed.then_some((participant, session_id, entries))
+        })
+        .expect("A signing participant journal should exist");
+    assert!(matches!(entries.pop(), Some(JournalEntry::Complete { .. })));
+    let copy_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let copy = Arc::new(FileJournal::open(copy_dir.path()).unwrap());
+    for entry in &entries {
+        copy.append(session_id, entry).unwrap();
+    }
+
+    let (signers, _) = harness.create_signers();
+    let signer = signers
+        .into_values()
+        .find(|signer| hex::encode(signer.participant_id.serialize()) == participant)
+        .unwrap()
+        .with_store(copy);
+    assert!(signer.recover().unwrap().is_empty());
+    match signer.get_state(session_id).unwrap() {
+        SessionView::CollectingShares { signed, shares, .. } => {
+            assert!(signed);
+            assert!(shares.contains_key(&signer.participant_id));
+        }
+        state => panic!("Unexpected state {state:?}"),
+    }
+
+    signer.complete_signing(session_id, signed_tx).unwrap();
+    assert!(matches!(signer.get_state(session_id).unwrap(), SessionView::Complete { .. }));
+}
+
+#[tokio::test]
+async fn test_truncated_entry_is_dropped_before_appending() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let state_dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let (transaction, prevouts) = harness.create_dummy_transaction(1);
+    let session_id = SessionId::from([8; 32]);
+    let (signers, _) = harness.create_signers();
+    let journal = Arc::new(FileJournal::open(state_dir.path()).unwrap());
+    let signer = signers.values().next().unwrap().clone().with_store(journal.clone());
+    signer.initiate_signing_round(session_id, SigningRequest::new(transaction, &prevouts)).await.unwrap();
+    let entries = journal.load(session_id).unwrap().len();
+
+    // Crash in the middle of appending an entry
+    let path = state_dir.path().join(format!("{session_id}.jsonl"));
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(br#"{"Failed":{"error":"#).unwrap();
+    assert_eq!(journal.load(session_id).unwrap().len(), entries);
+
+    // The restarted signer appends after the last complete entry, later entries stay readable
+    let (signers, _) = harness.create_signers();
+    let signer = signers.values().next().unwrap().clone().with_store(journal.clone());
+    assert_eq!(signer.recover().unwrap(), vec![session_id]);
+    journal.append(session_id, &JournalEntry::Failed { error: "again".to_string() }).unwrap();
+    let record = SessionRecord::replay(journal.load(session_id).unwrap());
+    assert_eq!(record.error.as_deref(), Some("again"));
+    assert_eq!(journal.load(session_id).unwrap().len(), entries + 2);
+}
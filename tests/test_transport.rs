@@ -0,0 +1,66 @@
+use frost_demo::{
+    envelope::Envelope,
+    errors::TransportError,
+    signer::{SessionId, SigningMessage},
+    transport::Transport,
+};
+use frost_secp256k1_tr::Identifier;
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+fn abort(harness: &TestHarness, sender: Identifier) -> Envelope {
+    harness.seal(SigningMessage::Abort(SessionId::from([1; 32]), sender, "test".to_string()))
+}
+
+#[tokio::test]
+async fn test_send_reaches_only_the_receiver() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (_, transport) = harness.create_signers();
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let [alice, bob, carol] = [ids[0], ids[1], ids[2]].map(|id| transport.participant(id).unwrap());
+
+    alice.send(ids[1], abort(&harness, ids[0])).await.unwrap();
+
+    let (sender, envelope) = bob.receive().await.unwrap().expect("Expected the message in the receiver's mailbox");
+    assert_eq!(sender, ids[0]);
+    assert_eq!(envelope.sender(), ids[0]);
+    assert!(bob.receive().await.unwrap().is_none());
+    assert!(alice.receive().await.unwrap().is_none());
+    assert!(carol.receive().await.unwrap().is_none());
+    assert!(transport.receive().await.unwrap().is_none(), "The coordinator must not see private messages");
+}
+
+#[tokio::test]
+async fn test_broadcast_excludes_the_sender() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (_, transport) = harness.create_signers();
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let handles: Vec<_> = ids.iter().map(|id| transport.participant(*id).unwrap()).collect();
+
+    handles[0].broadcast(abort(&harness, ids[0])).await.unwrap();
+
+    assert!(handles[0].receive().await.unwrap().is_none());
+    for handle in &handles[1..] {
+        let (sender, _) = handle.next_message().await.unwrap();
+        assert_eq!(sender, ids[0]);
+        assert!(handle.receive().await.unwrap().is_none());
+    }
+    let (sender, _) = transport.receive().await.unwrap().expect("Expected the coordinator's copy");
+    assert_eq!(sender, ids[0]);
+}
+
+#[tokio::test]
+async fn test_unknown_participants_and_coordinator_sends_are_rejected() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (_, transport) = harness.create_signers();
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let stranger = Identifier::try_from(42u16).unwrap();
+
+    assert_eq!(transport.participant(stranger).err(), Some(TransportError::UnknownParticipant(stranger)));
+    let alice = transport.participant(ids[0]).unwrap();
+    let result = alice.send(stranger, abort(&harness, ids[0])).await;
+    assert_eq!(result, Err(TransportError::UnknownParticipant(stranger)));
+    assert!(matches!(transport.broadcast(abort(&harness, ids[0])).await, Err(TransportError::Broadcast(_))));
+    assert!(matches!(transport.send(ids[1], abort(&harness, ids[0])).await, Err(TransportError::Send(_))));
+}
@@ -0,0 +1,246 @@
+use bitcoin::secp256k1::{Keypair, PublicKey, Secp256k1, SecretKey};
+use frost_demo::{
+    envelope::{identity_keypair, GroupId},
+    offline::{OfflineRequest, OfflineSigner},
+    relay::{self, Peer, RelayMessage, Topic, MAX_RELAY_MESSAGE_SIZE},
+    signer::{
+        run_remote_session, run_signing_ceremony_with_signers, setup_signers_with, CeremonyConfig, SessionId,
+        SigningMessage, SigningRequest,
+    },
+    store::MemoryStore,
+    transport::Transport,
+    websocket::{run_remote_signer, RelayIdentity, WebSocketTransport},
+};
+use frost_secp256k1_tr::Identifier;
+use futures::{SinkExt, StreamExt};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+/// Starts a relay for the harness group on a free localhost port, returns its URL.
+async fn start_relay(harness: &TestHarness) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let coordinator = coordinator_key().x_only_public_key(&Secp256k1::new()).0;
+    let coordinators = HashMap::from([(topic(harness).group_id, coordinator)]);
+    tokio::spawn(relay::serve(listener, coordinators));
+    url
+}
+
+fn coordinator_key() -> SecretKey {
+    SecretKey::from_slice(&[7; 32]).unwrap()
+}
+
+fn topic(harness: &TestHarness) -> Topic {
+    Topic { group_id: GroupId::new(&harness.key_data.public).unwrap(), session_id: None }
+}
+
+async fn connect(harness: &TestHarness, url: &str, id: Identifier) -> WebSocketTransport {
+    let coordinator = PublicKey::from_secret_key(&Secp256k1::new(), &coordinator_key());
+    let identity =
+        RelayIdentity::participant(&harness.key_data.key_packages[&id], &harness.key_data.public, coordinator).unwrap();
+    WebSocketTransport::connect(url, topic(harness), identity).await.unwrap()
+}
+
+type RawConnection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Connects to the relay without a transport, answering its challenge with the given key.
+async fn connect_raw(harness: &TestHarness, url: &str, peer: Peer, keypair: &Keypair, package: bool) -> RawConnection {
+    let (mut websocket, _) = connect_async(url).await.unwrap();
+    let Some(Ok(Message::Text(challenge))) = websocket.next().await else {
+        panic!("Expected a challenge from the relay");
+    };
+    let RelayMessage::Challenge { nonce } = serde_json::from_str(&challenge).unwrap() else {
+        panic!("Expected a challenge from the relay");
+    };
+    let package = package.then_some(&harness.key_data.public);
+    let hello = RelayMessage::hello(&nonce, topic(harness), peer, keypair, package).unwrap();
+    websocket.send(Message::Text(serde_json::to_string(&hello).unwrap())).await.unwrap();
+    websocket
+}
+
+/// Waits for the relay to close the connection.
+async fn assert_closed(mut websocket: RawConnection) {
+    loop {
+        match timeout(Duration::from_secs(5), websocket.next()).await.expect("Relay should close the connection") {
+            None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return,
+            Some(Ok(message)) => assert!(!message.is_text(), "Unexpected message {message:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_signing_ceremony_over_relay() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let url = start_relay(&harness).await;
+    let mut transports = HashMap::new();
+    for id in harness.key_data.key_packages.keys() {
+        transports.insert(*id, Arc::new(connect(&harness, &url, *id).await));
+    }
+    let identity = RelayIdentity::coordinator(coordinator_key(), &harness.key_data.public).unwrap();
+    let coordinator = Arc::new(WebSocketTransport::connect(&url, topic(&harness), identity).await.unwrap());
+
+    let signers = setup_signers_with(&harness.key_data, |id| Ok(transports[&id].clone())).unwrap();
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let config = CeremonyConfig { round1_timeout: Duration::from_secs(10), ..Default::default() };
+    let result =
+        run_signing_ceremony_with_signers(&harness.key_data, signers, coordinator, tx, &prevouts, &config).await;
+
+    assert!(result.is_ok(), "signing failed: {:?}", result.err());
+}
+
+#[tokio::test]
+async fn test_relay_only_sees_ciphertext() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let url = start_relay(&harness).await;
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let alice = connect(&harness, &url, ids[0]).await;
+    let bob = connect(&harness, &url, ids[1]).await;
+    let carol = connect(&harness, &url, ids[2]).await;
+
+    // Subscribes as the coordinator without its transport, like the relay it only sees the frames
+    let keypair = Keypair::from_secret_key(&Secp256k1::new(), &coordinator_key());
+    let mut eavesdropper = connect_raw(&harness, &url, Peer::Coordinator, &keypair, false).await;
+
+    let session_id = SessionId::from([9; 32]);
+    let abort = SigningMessage::Abort(session_id, ids[0], "secret reason".to_string());
+    alice.send(ids[1], harness.seal(abort.clone())).await.unwrap();
+    alice.broadcast(harness.seal(abort)).await.unwrap();
+
+    for _ in 0..2 {
+        let (sender, envelope) = timeout(Duration::from_secs(5), bob.next_message()).await.unwrap().unwrap();
+        assert_eq!(sender, ids[0]);
+        assert!(matches!(envelope.message, SigningMessage::Abort(id, ..) if id == session_id));
+    }
+    let (_, envelope) = timeout(Duration::from_secs(5), carol.next_message()).await.unwrap().unwrap();
+    assert!(matches!(envelope.message, SigningMessage::Abort(id, ..) if id == session_id));
+    assert!(carol.receive().await.unwrap().is_none(), "The private message must only reach its receiver");
+
+    let Some(Ok(Message::Text(frame))) = timeout(Duration::from_secs(5), eavesdropper.next()).await.unwrap() else {
+        panic!("Expected a frame for the coordinator");
+    };
+    assert!(!frame.contains("secret reason"));
+    let RelayMessage::Frame { from, to, .. } = serde_json::from_str(&frame).unwrap() else {
+        panic!("Expected a frame");
+    };
+    assert_eq!((from, to), (Peer::Participant(ids[0]), Peer::Coordinator));
+}
+
+#[tokio::test]
+async fn test_relay_authenticates_peers() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let url = start_relay(&harness).await;
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let alice = connect(&harness, &url, ids[0]).await;
+
+    // A participant can't subscribe as another one, the frames keep waiting for the real one
+    let forger = identity_keypair(&harness.key_data.key_packages[&ids[0]]).unwrap();
+    assert_closed(connect_raw(&harness, &url, Peer::Participant(ids[1]), &forger, true).await).await;
+    let session_id = SessionId::from([9; 32]);
+    alice.send(ids[1], harness.seal(SigningMessage::Abort(session_id, ids[0], "test".to_string()))).await.unwrap();
+    let bob = connect(&harness, &url, ids[1]).await;
+    let (sender, _) = timeout(Duration::from_secs(5), bob.next_message()).await.unwrap().unwrap();
+    assert_eq!(sender, ids[0]);
+
+    // Only the coordinator key the relay was started with subscribes as the coordinator, even if it connects first
+    let secp = Secp256k1::new();
+    let impostor = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[8; 32]).unwrap());
+    assert_closed(connect_raw(&harness, &url, Peer::Coordinator, &impostor, false).await).await;
+    let coordinator = Keypair::from_secret_key(&secp, &coordinator_key());
+    let mut configured = connect_raw(&harness, &url, Peer::Coordinator, &coordinator, false).await;
+    bob.broadcast(harness.seal(SigningMessage::Abort(session_id, ids[1], "test".to_string()))).await.unwrap();
+    let frame = timeout(Duration::from_secs(5), configured.next()).await.unwrap();
+    assert!(matches!(frame, Some(Ok(Message::Text(_)))), "Expected a frame for the coordinator, got {frame:?}");
+}
+
+#[tokio::test]
+async fn test_relay_refuses_oversized_messages() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let url = start_relay(&harness).await;
+    let id = *harness.key_data.key_packages.keys().next().unwrap();
+    let keypair = identity_keypair(&harness.key_data.key_packages[&id]).unwrap();
+    let mut websocket = connect_raw(&harness, &url, Peer::Participant(id), &keypair, true).await;
+
+    let _ = websocket.send(Message::Text("x".repeat(MAX_RELAY_MESSAGE_SIZE + 1))).await;
+    assert_closed(websocket).await;
+}
+
+/// Sends a frame with the given payload to a peer over a raw connection.
+async fn send_frame(websocket: &mut RawConnection, to: Peer, payload: Vec<u8>) {
+    let frame = RelayMessage::Frame { from: to, to, payload };
+    websocket.send(Message::Text(serde_json::to_string(&frame).unwrap())).await.unwrap();
+}
+
+/// Waits for the next frame on a raw connection.
+async fn next_frame(websocket: &mut RawConnection) -> Option<(Peer, Vec<u8>)> {
+    loop {
+        match timeout(Duration::from_secs(1), websocket.next()).await.ok()?? {
+            Ok(Message::Text(text)) => {
+                if let Ok(RelayMessage::Frame { from, payload, .. }) = serde_json::from_str(&text) {
+                    return Some((from, payload));
+                }
+            }
+            Ok(_) => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_relay_caps_the_frames_of_a_sender() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let url = start_relay(&harness).await;
+    let ids: Vec<Identifier> = harness.key_data.key_packages.keys().cloned().collect();
+    let keypair = |id| identity_keypair(&harness.key_data.key_packages[&id]).unwrap();
+    let mut alice = connect_raw(&harness, &url, Peer::Participant(ids[0]), &keypair(ids[0]), true).await;
+    let mut carol = connect_raw(&harness, &url, Peer::Participant(ids[2]), &keypair(ids[2]), true).await;
+
+    // Frames for peers outside the group are dropped, frames for bob queue up to the sender's cap
+    let stranger = Identifier::try_from(9u16).unwrap();
+    send_frame(&mut alice, Peer::Participant(stranger), vec![0]).await;
+    for _ in 0..600 {
+        send_frame(&mut alice, Peer::Participant(ids[1]), vec![0]).await;
+    }
+    send_frame(&mut alice, Peer::Participant(ids[2]), vec![1]).await;
+    assert_eq!(next_frame(&mut carol).await, Some((Peer::Participant(ids[0]), vec![1])));
+
+    // Carol's frame still gets through once alice is capped
+    send_frame(&mut carol, Peer::Participant(ids[1]), vec![2]).await;
+    send_frame(&mut carol, Peer::Participant(ids[0]), vec![3]).await;
+    assert_eq!(next_frame(&mut alice).await, Some((Peer::Participant(ids[2]), vec![3])));
+    let mut bob = connect_raw(&harness, &url, Peer::Participant(ids[1]), &keypair(ids[1]), true).await;
+    let mut received = Vec::new();
+    while let Some(frame) = next_frame(&mut bob).await {
+        received.push(frame);
+    }
+    assert_eq!(received.iter().filter(|(from, _)| *from == Peer::Participant(ids[0])).count(), 512);
+    assert!(received.contains(&(Peer::Participant(ids[2]), vec![2])), "Carol's frame must reach bob");
+}
+
+#[tokio::test]
+async fn test_remote_signers_sign_over_relay() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let url = start_relay(&harness).await;
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    for (id, key_package) in &harness.key_data.key_packages {
+        let transport = connect(&harness, &url, *id).await;
+        let store = Arc::new(MemoryStore::default());
+        let signer = OfflineSigner::new(key_package.clone(), harness.key_data.public.clone(), store).unwrap();
+        let nonce_dir = dir.path().join(hex::encode(id.serialize()));
+        tokio::spawn(async move { run_remote_signer(signer, &transport, &nonce_dir).await });
+    }
+    let identity = RelayIdentity::coordinator(coordinator_key(), &harness.key_data.public).unwrap();
+    let coordinator = Arc::new(WebSocketTransport::connect(&url, topic(&harness), identity).await.unwrap());
+
+    let (tx, prevouts) = harness.create_dummy_transaction(1);
+    let request = OfflineRequest { session_id: SessionId::from([3; 32]), request: SigningRequest::new(tx, &prevouts) };
+    let config = CeremonyConfig { round1_timeout: Duration::from_secs(10), ..Default::default() };
+    let signed = run_remote_session(&harness.key_data, coordinator, &request, &config).await.unwrap();
+    assert_eq!(signed.input[0].witness.len(), 1);
+}
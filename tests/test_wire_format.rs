@@ -0,0 +1,190 @@
+use bitcoin::secp256k1::schnorr;
+use frost_demo::{
+    codec::{debug_json, from_wire, to_wire, MAX_WIRE_SIZE, WIRE_VERSION},
+    envelope::{Envelope, GroupId, PROTOCOL_VERSION},
+    errors::CodecError,
+    signer::{SessionId, SigningMessage},
+};
+use frost_secp256k1_tr as frost;
+use frost_secp256k1_tr::{
+    round1::{NonceCommitment, SigningCommitments},
+    Identifier,
+};
+
+mod utils;
+use crate::utils::test::TestHarness;
+
+/// Golden vectors of the version 1 wire format, any change to them breaks compatibility with deployed signers.
+const ABORT_VECTOR: &str = concat!(
+    "0100000050",
+    "04",
+    "1111111111111111111111111111111111111111111111111111111111111111",
+    "000000200000000000000000000000000000000000000000000000000000000000000001",
+    "0000000774696d656f7574",
+);
+const SHARE_VECTOR: &str = concat!(
+    "0100000069",
+    "02",
+    "2222222222222222222222222222222222222222222222222222222222222222",
+    "000000200000000000000000000000000000000000000000000000000000000000000002",
+    "00000020000000000000000000000000000000000000000000000000000000000000002a",
+);
+const EMPTY_BATCH_VECTOR: &str = concat!(
+    "0100000029",
+    "03",
+    "000000200000000000000000000000000000000000000000000000000000000000000003",
+    "00000000",
+);
+
+const COMMITMENT_VECTOR: &str = concat!(
+    "0100000090",
+    "01",
+    "4444444444444444444444444444444444444444444444444444444444444444",
+    "000000200000000000000000000000000000000000000000000000000000000000000004",
+    "00000047",
+    "00230f8ab3",
+    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+);
+const BATCH_VECTOR: &str = concat!(
+    "01000000cf",
+    "03",
+    "000000200000000000000000000000000000000000000000000000000000000000000005",
+    "00000002",
+    "0000000000000007",
+    "00000047",
+    "00230f8ab3",
+    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+    "0000000000000008",
+    "00000047",
+    "00230f8ab3",
+    "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+);
+const ENVELOPE_VECTOR: &str = concat!(
+    "01000000d4",
+    "0001",
+    "6666666666666666666666666666666666666666666666666666666666666666",
+    "02",
+    "0000000100000002",
+    "02",
+    "2222222222222222222222222222222222222222222222222222222222222222",
+    "000000200000000000000000000000000000000000000000000000000000000000000002",
+    "00000020000000000000000000000000000000000000000000000000000000000000002a",
+    "7777777777777777777777777777777777777777777777777777777777777777",
+    "7777777777777777777777777777777777777777777777777777777777777777",
+);
+
+/// Compressed encodings of the generator and its double, valid nonce commitments.
+const GENERATOR: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const DOUBLE_GENERATOR: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+
+fn id(n: u16) -> Identifier {
+    Identifier::try_from(n).unwrap()
+}
+
+fn share() -> frost::round2::SignatureShare {
+    let mut share = [0u8; 32];
+    share[31] = 0x2a;
+    frost::round2::SignatureShare::deserialize(&share).unwrap()
+}
+
+fn commitments(hiding: &str, binding: &str) -> SigningCommitments {
+    let nonce_commitment = |point: &str| NonceCommitment::deserialize(&hex::decode(point).unwrap()).unwrap();
+    SigningCommitments::new(nonce_commitment(hiding), nonce_commitment(binding))
+}
+
+fn golden_messages() -> Vec<(SigningMessage, String)> {
+    let commitment = commitments(GENERATOR, DOUBLE_GENERATOR);
+    let batch = vec![(7, commitment), (8, commitments(DOUBLE_GENERATOR, GENERATOR))];
+    vec![
+        (SigningMessage::Abort(SessionId::from([0x11; 32]), id(1), "timeout".to_string()), ABORT_VECTOR.into()),
+        (SigningMessage::SignatureShare(SessionId::from([0x22; 32]), id(2), share()), SHARE_VECTOR.into()),
+        (SigningMessage::PreprocessedCommitments(id(3), Vec::new()), EMPTY_BATCH_VECTOR.into()),
+        (
+            SigningMessage::NonceCommitment(SessionId::from([0x44; 32]), id(4), Box::new(commitment)),
+            COMMITMENT_VECTOR.into(),
+        ),
+        (SigningMessage::PreprocessedCommitments(id(5), batch), BATCH_VECTOR.into()),
+    ]
+}
+
+#[test]
+fn test_golden_vectors() {
+    for (message, vector) in golden_messages() {
+        assert_eq!(hex::encode(to_wire(&message).unwrap()), vector, "{message:?}");
+        let decoded: SigningMessage = from_wire(&hex::decode(&vector).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&message).unwrap());
+    }
+}
+
+#[test]
+fn test_envelope_golden_vector() {
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        group_id: GroupId::from([0x66; 32]),
+        round: 2,
+        sequence: (1 << 32) + 2,
+        message: SigningMessage::SignatureShare(SessionId::from([0x22; 32]), id(2), share()),
+        signature: schnorr::Signature::from_slice(&[0x77; 64]).unwrap(),
+    };
+    assert_eq!(hex::encode(to_wire(&envelope).unwrap()), ENVELOPE_VECTOR);
+    let decoded: Envelope = from_wire(&hex::decode(ENVELOPE_VECTOR).unwrap()).unwrap();
+    assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&envelope).unwrap());
+}
+
+#[tokio::test]
+async fn test_commitments_use_frost_serialization() {
+    let harness = TestHarness::new(2, 3, None).await;
+    let (sender, key_package) = harness.key_data.key_packages.iter().next().unwrap();
+    let (_, commitments) = frost::round1::commit(key_package.signing_share(), &mut rand::thread_rng());
+    let message = SigningMessage::NonceCommitment(SessionId::from([0x33; 32]), *sender, Box::new(commitments));
+
+    let frame = to_wire(&message).unwrap();
+    let serialized = commitments.serialize().unwrap();
+    assert!(frame.ends_with(&[(serialized.len() as u32).to_be_bytes().as_slice(), &serialized].concat()));
+    let decoded: SigningMessage = from_wire(&frame).unwrap();
+    assert!(matches!(decoded, SigningMessage::NonceCommitment(_, s, c) if s == *sender && *c == commitments));
+}
+
+#[test]
+fn test_decode_rejects_malformed_frames() {
+    let frame = hex::decode(ABORT_VECTOR).unwrap();
+
+    let trailing = [frame.as_slice(), &[0]].concat();
+    assert_eq!(from_wire::<SigningMessage>(&trailing).err(), Some(CodecError::TrailingBytes(1)));
+
+    let mut version = frame.clone();
+    version[0] = WIRE_VERSION + 1;
+    assert_eq!(from_wire::<SigningMessage>(&version).err(), Some(CodecError::UnsupportedVersion(WIRE_VERSION + 1)));
+
+    let mut unknown = frame.clone();
+    unknown[5] = 0x7f;
+    assert_eq!(
+        from_wire::<SigningMessage>(&unknown).err(),
+        Some(CodecError::UnknownVariant { kind: "message", tag: 0x7f })
+    );
+
+    // A body length past the end of the frame
+    let mut truncated = frame.clone();
+    truncated[4] += 1;
+    assert!(matches!(from_wire::<SigningMessage>(&truncated), Err(CodecError::Truncated(_))));
+
+    let oversized = vec![WIRE_VERSION; MAX_WIRE_SIZE + 1];
+    assert_eq!(
+        from_wire::<SigningMessage>(&oversized).err(),
+        Some(CodecError::Oversized { size: MAX_WIRE_SIZE + 1, max: MAX_WIRE_SIZE })
+    );
+
+    // Shares are scalars below the group order
+    let mut share = hex::decode(SHARE_VECTOR).unwrap();
+    share[share.len() - 32..].fill(0xff);
+    assert!(matches!(from_wire::<SigningMessage>(&share), Err(CodecError::Invalid(_))));
+}
+
+#[test]
+fn test_debug_json() {
+    let json = debug_json::<SigningMessage>(&hex::decode(ABORT_VECTOR).unwrap()).unwrap();
+    assert!(json.contains("Abort") && json.contains("timeout"), "{json}");
+}
@@ -3,20 +3,33 @@
 #[cfg(test)]
 pub mod test {
     use bitcoin::{
+        absolute::LockTime,
+        psbt::Psbt,
         secp256k1::{Secp256k1, SecretKey},
-        Address, Amount, Network, OutPoint, Transaction, TxOut,
+        transaction::Version,
+        Address, Amount, Network, OutPoint, Transaction, TxIn, TxOut, Txid,
     };
     use frost_demo::{
         bitcoin::create_unsigned_transaction,
+        chain::{ChainBackend, MockBackend},
+        envelope::{Envelope, Sealer},
         generate_keys,
         keys::KeyData,
-        signer::{setup_signers, FrostSigner},
+        signer::{setup_signers, CeremonyConfig, FrostSigner, SigningMessage},
+        spend,
         transport::InMemoryTransport,
+        SpendArgs,
     };
     use frost_secp256k1_tr::Identifier;
     use std::{collections::HashMap, str::FromStr, sync::Arc};
     use tempfile::NamedTempFile;
 
+    /// Value of the output funding the group on a mock chain.
+    pub const FUNDING_VALUE: Amount = Amount::from_sat(100_000);
+
+    /// Height the funding transaction is confirmed at.
+    pub const FUNDING_HEIGHT: u64 = 100;
+
     /// A test harness to simplify setup for state machine tests.
     pub struct TestHarness {
         pub key_data: KeyData,
@@ -65,5 +78,67 @@ pub mod test {
 
             (transaction, prevouts)
         }
+
+        /// Serialized PSBT of the dummy transaction, with the spent output as its witness UTXO.
+        pub fn create_dummy_psbt(&self, seed: u64) -> Vec<u8> {
+            let (transaction, prevouts) = self.create_dummy_transaction(seed);
+            let mut psbt = Psbt::from_unsigned_tx(transaction).unwrap();
+            psbt.inputs[0].witness_utxo = Some(prevouts[0].clone());
+            psbt.serialize()
+        }
+
+        /// Transaction paying [`FUNDING_VALUE`] to the group address, its first output is the funded outpoint.
+        pub fn funding_transaction(&self) -> Transaction {
+            let previous_output =
+                OutPoint::from_str("f2ba6014dd5598a2333b7d1553c932f7a9d7a22b704481da4a10fb0032e35f4b:0").unwrap();
+            let script_pubkey = self.key_data.address(Network::Signet).unwrap().script_pubkey();
+            Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn { previous_output, ..Default::default() }],
+                output: vec![TxOut { value: FUNDING_VALUE, script_pubkey }],
+            }
+        }
+
+        /// Confirms the funding transaction on the mock chain at [`FUNDING_HEIGHT`], returns the funded outpoint.
+        pub fn fund_group(&self, backend: &MockBackend) -> OutPoint {
+            let funding = self.funding_transaction();
+            let outpoint = OutPoint { txid: funding.compute_txid(), vout: 0 };
+            backend.add_transaction(funding, Some(FUNDING_HEIGHT));
+            outpoint
+        }
+
+        /// Destination address of the dummy transaction with the seed.
+        pub fn destination(&self, seed: u64) -> Address {
+            let (tx, _) = self.create_dummy_transaction(seed);
+            Address::from_script(&tx.output[0].script_pubkey, Network::Signet).unwrap()
+        }
+
+        /// Spends 10000 sat of the outpoint through the backend, like the `spend` command.
+        pub async fn spend(
+            &self,
+            outpoint: OutPoint,
+            backend: &dyn ChainBackend,
+            estimate_fee: bool,
+        ) -> Result<Txid, anyhow::Error> {
+            let (utxo, to) = (outpoint.to_string(), self.destination(1).to_string());
+            spend(SpendArgs {
+                keys_path: self._temp_file.path(),
+                utxo: &utxo,
+                to: &to,
+                amount: 10_000,
+                network: Network::Signet,
+                backend,
+                ceremony: CeremonyConfig::default(),
+                estimate_fee,
+            })
+            .await
+        }
+
+        /// Seals a message in an envelope signed by its sender.
+        pub fn seal(&self, message: SigningMessage) -> Envelope {
+            let key_package = &self.key_data.key_packages[&message.sender()];
+            Sealer::new(key_package, &self.key_data.public).unwrap().seal(message).unwrap()
+        }
     }
 }